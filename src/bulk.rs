@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use rayon::prelude::*;
+
+use crate::error::SproutError;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
+use crate::query::{self, Query};
+
+/// The new content planned for one note, and the command-specific outcome
+/// that is reported once it has been written.
+pub struct Change<T> {
+    /// Canonical path of the note
+    pub path: PathBuf,
+    /// Content before the change, for rollback
+    pub original: String,
+    pub content: String,
+    pub outcome: T,
+}
+
+pub struct Failure {
+    pub file: String,
+    pub error: SproutError,
+}
+
+pub struct BulkReport<T> {
+    /// Whether any change was written. False when `--atomic` aborted the run.
+    pub applied: bool,
+    /// Number of notes selected
+    pub total: usize,
+    pub succeeded: Vec<Change<T>>,
+    pub failed: Vec<Failure>,
+}
+
+impl<T> BulkReport<T> {
+    /// `Err(BulkFailed)` when any note failed, so the process exits non-zero
+    /// after the report has been printed.
+    pub fn status(&self) -> Result<(), SproutError> {
+        if self.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(SproutError::BulkFailed(self.failed.len(), self.total))
+        }
+    }
+}
+
+/// Whether a positional file list asks for bulk mode: several files or `-`.
+pub fn is_bulk(files: &[PathBuf]) -> bool {
+    files.len() != 1 || files[0] == Path::new("-")
+}
+
+/// Expand `files` (where `-` means one path per line on stdin) and append the
+/// tracked notes selected by `query`. Duplicates are dropped, first one wins.
+pub fn resolve_targets(
+    files: &[PathBuf],
+    query: Option<&Query>,
+    vault: &Path,
+    scan_options: &ScanOptions,
+    today: NaiveDate,
+) -> Result<Vec<PathBuf>, SproutError> {
+    let mut targets = expand_stdin(files)?;
+    if let Some(q) = query {
+        let notes = scan(vault, scan_options)?
+            .into_iter()
+            .filter(|n| n.sprout.maturity.is_some())
+            .collect();
+        targets.extend(query::filter(notes, Some(q), today).into_iter().map(|n| n.path));
+    }
+    Ok(dedup(targets))
+}
+
+/// Replace each `-` in `files` with the paths read from stdin.
+pub fn expand_stdin(files: &[PathBuf]) -> Result<Vec<PathBuf>, SproutError> {
+    let mut out = Vec::new();
+    for f in files {
+        if f != Path::new("-") {
+            out.push(f.clone());
+            continue;
+        }
+        for line in std::io::stdin().lock().lines() {
+            let line = line.map_err(|e| SproutError::ParseError(format!("stdin: {e}")))?;
+            let line = line.trim();
+            if !line.is_empty() {
+                out.push(PathBuf::from(line));
+            }
+        }
+    }
+    Ok(out)
+}
+
+pub fn scan(vault: &Path, scan_options: &ScanOptions) -> Result<Vec<NoteMetaInfo>, SproutError> {
+    note::scan_vault_metadata(vault, scan_options)
+        .map_err(|e| SproutError::VaultNotFound(e.to_string()))
+}
+
+/// Drop paths that resolve to a note already in the list.
+pub fn dedup(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    paths
+        .into_iter()
+        .filter(|p| seen.insert(std::fs::canonicalize(p).unwrap_or_else(|_| p.clone())))
+        .collect()
+}
+
+/// Run `plan` for every target in parallel, keeping target order.
+pub fn plan_all<T, F>(targets: &[PathBuf], plan: F) -> (Vec<T>, Vec<Failure>)
+where
+    T: Send,
+    F: Fn(&Path) -> Result<T, SproutError> + Sync,
+{
+    let planned: Vec<_> = targets
+        .par_iter()
+        .map(|file| {
+            plan(file).map_err(|error| Failure {
+                file: file.display().to_string(),
+                error,
+            })
+        })
+        .collect();
+
+    let mut changes = Vec::new();
+    let mut failures = Vec::new();
+    for p in planned {
+        match p {
+            Ok(c) => changes.push(c),
+            Err(f) => failures.push(f),
+        }
+    }
+    (changes, failures)
+}
+
+/// Write the planned changes in parallel.
+///
+/// With `atomic`, nothing is written if any note failed to plan, and notes
+/// already written are restored if a write fails.
+pub fn apply<T: Send>(changes: Vec<Change<T>>, mut failed: Vec<Failure>, atomic: bool) -> BulkReport<T> {
+    let total = changes.len() + failed.len();
+    if atomic && !failed.is_empty() {
+        return BulkReport {
+            applied: false,
+            total,
+            succeeded: Vec::new(),
+            failed,
+        };
+    }
+
+    let written: Vec<_> = changes
+        .into_par_iter()
+        .map(|c| match note::write_note(&c.path, &c.content) {
+            Ok(()) => Ok(c),
+            Err(error) => Err(Failure {
+                file: c.path.display().to_string(),
+                error,
+            }),
+        })
+        .collect();
+
+    let mut succeeded = Vec::new();
+    for w in written {
+        match w {
+            Ok(c) => succeeded.push(c),
+            Err(f) => failed.push(f),
+        }
+    }
+
+    if atomic && !failed.is_empty() {
+        for c in &succeeded {
+            if let Err(e) = note::write_note(&c.path, &c.original) {
+                eprintln!("warning: failed to roll back {}: {e}", c.path.display());
+            }
+        }
+        return BulkReport {
+            applied: false,
+            total,
+            succeeded: Vec::new(),
+            failed,
+        };
+    }
+
+    BulkReport {
+        applied: !succeeded.is_empty(),
+        total,
+        succeeded,
+        failed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn planner(dir: &Path) -> impl Fn(&Path) -> Result<Change<()>, SproutError> + Sync + '_ {
+        move |file: &Path| {
+            let path = dir.join(file);
+            let original = fs::read_to_string(&path)
+                .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+            Ok(Change {
+                path,
+                content: original.to_uppercase(),
+                original,
+                outcome: (),
+            })
+        }
+    }
+
+    fn setup() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.md"), "a").unwrap();
+        fs::write(dir.path().join("b.md"), "b").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_apply_partial() {
+        let dir = setup();
+        let targets = vec![PathBuf::from("a.md"), PathBuf::from("missing.md"), PathBuf::from("b.md")];
+        let (changes, failed) = plan_all(&targets, planner(dir.path()));
+        let report = apply(changes, failed, false);
+
+        assert!(report.applied);
+        assert_eq!(report.succeeded.len(), 2);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].file, "missing.md");
+        assert_eq!(fs::read_to_string(dir.path().join("a.md")).unwrap(), "A");
+        assert!(matches!(report.status(), Err(SproutError::BulkFailed(1, 3))));
+    }
+
+    #[test]
+    fn test_apply_atomic_writes_nothing_on_failure() {
+        let dir = setup();
+        let targets = vec![PathBuf::from("a.md"), PathBuf::from("missing.md")];
+        let (changes, failed) = plan_all(&targets, planner(dir.path()));
+        let report = apply(changes, failed, true);
+
+        assert!(!report.applied);
+        assert!(report.succeeded.is_empty());
+        assert!(matches!(report.status(), Err(SproutError::BulkFailed(1, 2))));
+        assert_eq!(fs::read_to_string(dir.path().join("a.md")).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_apply_atomic_rolls_back_failed_write() {
+        let dir = setup();
+        let targets = vec![PathBuf::from("a.md")];
+        let (mut changes, failed) = plan_all(&targets, planner(dir.path()));
+        changes.push(Change {
+            path: dir.path().join("no-such-dir/c.md"),
+            original: String::new(),
+            content: "c".into(),
+            outcome: (),
+        });
+        let report = apply(changes, failed, true);
+
+        assert!(!report.applied);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join("a.md")).unwrap(), "a");
+    }
+
+    #[test]
+    fn test_is_bulk() {
+        assert!(!is_bulk(&[PathBuf::from("a.md")]));
+        assert!(is_bulk(&[PathBuf::from("-")]));
+        assert!(is_bulk(&[PathBuf::from("a.md"), PathBuf::from("b.md")]));
+        assert!(is_bulk(&[]));
+    }
+}
@@ -1,11 +1,41 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
 
+use chrono::{Datelike, NaiveDate};
 use serde::{Deserialize, Serialize};
 
 use crate::frontmatter::SproutFrontmatter;
+use crate::note::{self, NoteContent};
 
-const CACHE_VERSION: u32 = 1;
+/// Version of the global `frontmatter.json` cache that all vaults shared
+/// before the per-vault files. Each vault imports its entries on first load.
+const LEGACY_VERSION: u32 = 1;
+
+/// Binary cache file layout (all integers little-endian):
+///
+/// ```text
+/// header:  "SPRC" u32:FORMAT_VERSION u32:len vault-path
+/// record:  u32:len payload
+/// payload: u8:0 u32:len path i64:mtime_secs u32:mtime_nanos u64:size frontmatter   (upsert)
+///        | u8:1 u32:len path                                                    (remove)
+/// frontmatter: u8:presence-flags, then each present field in struct order
+///              (u16:len maturity, dates as i32 days from CE, u32 interval, f64 ease)
+/// content: u8:0                                                            (not cached)
+///        | u8:1 u32:count links u32:count tags u32:count aliases u32:count headings
+///          u32:count block-ids u8:has-title [title] u32:word-count u8:has-id [id]
+/// ```
+///
+/// Records are appended on save and replayed in order on load, so a run that
+/// changes a handful of notes writes a handful of records. The file is
+/// rewritten in full once dead records outnumber live entries.
+const MAGIC: &[u8; 4] = b"SPRC";
+const FORMAT_VERSION: u32 = 2;
+const RECORD_UPSERT: u8 = 0;
+const RECORD_REMOVE: u8 = 1;
+/// Dead records tolerated before compaction, on top of one per live entry
+const COMPACT_SLACK: usize = 64;
 
 #[derive(Serialize, Deserialize)]
 struct CacheEntry {
@@ -13,95 +43,280 @@ struct CacheEntry {
     mtime_nanos: u32,
     size: u64,
     frontmatter: SproutFrontmatter,
+    /// Links, tags, aliases, anchors, title and word count. `None` for entries
+    /// imported from the global JSON cache, which did not store them; such
+    /// entries are re-read on scan.
+    #[serde(default)]
+    content: Option<NoteContent>,
 }
 
+/// The global JSON cache, keyed by canonical note path across all vaults.
 #[derive(Serialize, Deserialize)]
-struct CacheFile {
+struct LegacyCacheFile {
     version: u32,
     entries: HashMap<PathBuf, CacheEntry>,
 }
 
+/// Frontmatter cache for one vault, stored in its own file so that vaults do
+/// not rewrite each other's entries.
 pub struct FrontmatterCache {
+    vault: PathBuf,
+    /// Where `save` writes; `None` when there is no user cache directory
+    path: Option<PathBuf>,
     entries: HashMap<PathBuf, CacheEntry>,
-    dirty: bool,
+    /// Records in the file on disk, live or superseded
+    records: usize,
+    /// Paths inserted or removed since load, in order
+    pending: Vec<PathBuf>,
+    /// The file is missing, unreadable, imported from the global JSON or due
+    /// for compaction
+    rewrite: bool,
+    /// Global JSON cache this cache imported from; this vault's entries are
+    /// dropped from it after the first full write
+    legacy: Option<PathBuf>,
+}
+
+pub struct CacheStats {
+    pub vault: PathBuf,
+    pub path: Option<PathBuf>,
+    pub entries: usize,
+    /// Entries whose file is gone or has changed since it was cached
+    pub stale: usize,
+    pub size_bytes: u64,
 }
 
 impl FrontmatterCache {
-    pub fn load() -> Self {
-        let path = match cache_path() {
-            Some(p) => p,
-            None => {
-                return Self {
-                    entries: HashMap::new(),
-                    dirty: false,
-                }
-            }
+    /// Load the cache for a canonical vault path.
+    pub fn load(vault: &Path) -> Self {
+        Self::load_from(vault, cache_path(vault), legacy_cache_path().as_deref())
+    }
+
+    /// A cache that is never written, for tests of code that takes one.
+    #[cfg(test)]
+    pub fn in_memory(vault: &Path) -> Self {
+        Self::load_from(vault, None, None)
+    }
+
+    /// Load from `path`, or, when there is no file yet, import this vault's
+    /// entries from the global JSON cache at `legacy`.
+    fn load_from(vault: &Path, path: Option<PathBuf>, legacy: Option<&Path>) -> Self {
+        let mut cache = Self {
+            vault: vault.to_path_buf(),
+            path,
+            entries: HashMap::new(),
+            records: 0,
+            pending: Vec::new(),
+            rewrite: false,
+            legacy: None,
+        };
+        let Some(path) = cache.path.clone() else {
+            return cache;
         };
 
-        let data = match std::fs::read_to_string(&path) {
-            Ok(d) => d,
+        match std::fs::read(&path) {
+            Ok(data) => {
+                if cache.replay(&data).is_none() {
+                    // Foreign, outdated or corrupt: start over
+                    cache.entries.clear();
+                    cache.records = 0;
+                    cache.rewrite = true;
+                }
+            }
             Err(_) => {
-                return Self {
-                    entries: HashMap::new(),
-                    dirty: false,
+                if let Some(entries) = legacy.and_then(|l| load_legacy(l, vault)) {
+                    cache.entries = entries;
+                    cache.rewrite = true;
+                    cache.legacy = legacy.map(Path::to_path_buf);
                 }
             }
-        };
+        }
+        cache
+    }
 
-        match serde_json::from_str::<CacheFile>(&data) {
-            Ok(cf) if cf.version == CACHE_VERSION => Self {
-                entries: cf.entries,
-                dirty: false,
-            },
-            _ => Self {
-                entries: HashMap::new(),
-                dirty: false,
-            },
+    /// Apply the records in `data`. `None` if the header does not match this
+    /// vault. A truncated final record (interrupted append) is ignored.
+    fn replay(&mut self, data: &[u8]) -> Option<()> {
+        let mut r = Reader { data, pos: 0 };
+        if r.take(4)? != MAGIC {
+            return None;
+        }
+        if r.u32()? != FORMAT_VERSION {
+            return None;
+        }
+        if r.str()? != self.vault.to_string_lossy() {
+            return None;
+        }
+
+        while let Some(len) = r.u32() {
+            let Some(payload) = r.take(len as usize) else {
+                self.rewrite = true;
+                break;
+            };
+            let mut p = Reader { data: payload, pos: 0 };
+            let kind = p.u8()?;
+            let path = PathBuf::from(p.str()?);
+            match kind {
+                RECORD_UPSERT => {
+                    let entry = CacheEntry {
+                        mtime_secs: p.i64()?,
+                        mtime_nanos: p.u32()?,
+                        size: p.u64()?,
+                        frontmatter: decode_frontmatter(&mut p)?,
+                        content: decode_content(&mut p)?,
+                    };
+                    self.entries.insert(path, entry);
+                }
+                RECORD_REMOVE => {
+                    self.entries.remove(&path);
+                }
+                _ => return None,
+            }
+            self.records += 1;
         }
+        Some(())
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.rewrite || !self.pending.is_empty()
     }
 
     pub fn save(&self) {
-        if !self.dirty {
-            return;
+        self.write();
+    }
+
+    /// Save, then treat the saved state as loaded, so that a long-running
+    /// process can save repeatedly without re-appending earlier changes.
+    pub fn flush(&mut self) {
+        if let Some(records) = self.write() {
+            self.records = records;
+            self.pending.clear();
+            self.rewrite = false;
         }
-        let path = match cache_path() {
-            Some(p) => p,
-            None => return,
-        };
+    }
+
+    /// Write pending changes. Returns the number of records now in the file,
+    /// or `None` if nothing was written.
+    fn write(&self) -> Option<usize> {
+        if !self.is_dirty() {
+            return None;
+        }
+        let path = self.path.as_ref()?;
         if let Some(parent) = path.parent() {
             let _ = std::fs::create_dir_all(parent);
         }
-        let cf = CacheFile {
-            version: CACHE_VERSION,
-            entries: self
-                .entries
-                .iter()
-                .map(|(k, v)| (k.clone(), CacheEntry {
-                    mtime_secs: v.mtime_secs,
-                    mtime_nanos: v.mtime_nanos,
-                    size: v.size,
-                    frontmatter: v.frontmatter.clone(),
-                }))
-                .collect(),
-        };
-        let data = match serde_json::to_string(&cf) {
-            Ok(d) => d,
-            Err(_) => return,
-        };
-        // Atomic save: write to temp file then rename
-        let tmp = path.with_extension("tmp");
-        if std::fs::write(&tmp, &data).is_ok() {
-            let _ = std::fs::rename(&tmp, &path);
+
+        // Serialise with other sprout processes saving the same vault. Best
+        // effort: without a lock the save still goes ahead.
+        let _lock = lock(path);
+
+        let compact = self.rewrite
+            || self.records + self.pending.len() > 2 * self.entries.len() + COMPACT_SLACK;
+        if !compact && self.append(path).is_ok() {
+            return Some(self.records + self.pending.len());
+        }
+        self.write_full(path)
+    }
+
+    /// Append one record per pending change. Fails if the file is gone, so
+    /// that a header-less file is never created.
+    fn append(&self, path: &Path) -> std::io::Result<()> {
+        let mut buf = Vec::new();
+        for p in &self.pending {
+            encode_record(&mut buf, p, self.entries.get(p));
+        }
+        let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+        file.write_all(&buf)
+    }
+
+    fn write_full(&self, path: &Path) -> Option<usize> {
+        // Another process may have saved since this cache was loaded: keep
+        // what is on disk now and apply this process's changes on top.
+        let disk = Self::load_from(&self.vault, Some(path.to_path_buf()), None).entries;
+        let pending: HashSet<&PathBuf> = self.pending.iter().collect();
+        let mut merged: HashMap<&PathBuf, &CacheEntry> = disk
+            .iter()
+            .filter(|(p, _)| !pending.contains(p))
+            .collect();
+        for (p, entry) in &self.entries {
+            if pending.contains(p) || !merged.contains_key(p) {
+                merged.insert(p, entry);
+            }
+        }
+
+        let records = merged.len();
+        let mut buf = Vec::with_capacity(64 + records * 96);
+        buf.extend_from_slice(MAGIC);
+        put_u32(&mut buf, FORMAT_VERSION);
+        put_str(&mut buf, &self.vault.to_string_lossy());
+        for (p, entry) in merged {
+            encode_record(&mut buf, p, Some(entry));
+        }
+
+        note::write_atomic(path, &buf).ok()?;
+        // This vault's part of the global cache has been imported; other
+        // vaults import theirs on their first load.
+        if let Some(legacy) = &self.legacy {
+            let _ = drop_legacy(legacy, &self.vault);
+        }
+        Some(records)
+    }
+
+    /// Drop entries for files that no longer exist. Paths in `scanned` were
+    /// just seen by a scan and are kept without touching the filesystem.
+    pub fn prune(&mut self, scanned: &HashSet<PathBuf>) {
+        let gone: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|path| !scanned.contains(*path) && !path.exists())
+            .cloned()
+            .collect();
+        for path in gone {
+            self.entries.remove(&path);
+            self.pending.push(path);
+        }
+    }
+
+    pub fn remove(&mut self, path: &Path) {
+        if self.entries.remove(path).is_some() {
+            self.pending.push(path.to_path_buf());
         }
     }
 
-    pub fn get(&self, path: &Path, mtime_secs: i64, mtime_nanos: u32, size: u64) -> Option<&SproutFrontmatter> {
+    pub fn stats(&self) -> CacheStats {
+        let stale = self
+            .entries
+            .iter()
+            .filter(|(path, entry)| !entry.is_fresh(path))
+            .count();
+        let size_bytes = self
+            .path
+            .as_ref()
+            .and_then(|p| std::fs::metadata(p).ok())
+            .map(|m| m.len())
+            .unwrap_or(0);
+        CacheStats {
+            vault: self.vault.clone(),
+            path: self.path.clone(),
+            entries: self.entries.len(),
+            stale,
+            size_bytes,
+        }
+    }
+
+    /// Cached frontmatter and, when stored, content data for an unchanged file.
+    pub fn get(
+        &self,
+        path: &Path,
+        mtime_secs: i64,
+        mtime_nanos: u32,
+        size: u64,
+    ) -> Option<(&SproutFrontmatter, Option<&NoteContent>)> {
         let entry = self.entries.get(path)?;
         if entry.mtime_secs == mtime_secs
             && entry.mtime_nanos == mtime_nanos
             && entry.size == size
         {
-            Some(&entry.frontmatter)
+            Some((&entry.frontmatter, entry.content.as_ref()))
         } else {
             None
         }
@@ -114,7 +329,9 @@ impl FrontmatterCache {
         mtime_nanos: u32,
         size: u64,
         frontmatter: SproutFrontmatter,
+        content: NoteContent,
     ) {
+        let path_key = path.clone();
         self.entries.insert(
             path,
             CacheEntry {
@@ -122,22 +339,348 @@ impl FrontmatterCache {
                 mtime_nanos,
                 size,
                 frontmatter,
+                content: Some(content),
             },
         );
-        self.dirty = true;
+        self.pending.push(path_key);
     }
 }
 
-fn cache_path() -> Option<PathBuf> {
+impl CacheEntry {
+    fn is_fresh(&self, path: &Path) -> bool {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return false;
+        };
+        let mtime = meta
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
+        mtime.is_some_and(|d| {
+            d.as_secs() as i64 == self.mtime_secs
+                && d.subsec_nanos() == self.mtime_nanos
+                && meta.len() == self.size
+        })
+    }
+}
+
+/// Entries of the global JSON cache for notes under `vault`.
+fn load_legacy(path: &Path, vault: &Path) -> Option<HashMap<PathBuf, CacheEntry>> {
+    let data = std::fs::read_to_string(path).ok()?;
+    let cf = serde_json::from_str::<LegacyCacheFile>(&data).ok()?;
+    (cf.version == LEGACY_VERSION).then(|| {
+        cf.entries
+            .into_iter()
+            .filter(|(p, _)| p.starts_with(vault))
+            .collect()
+    })
+}
+
+/// Remove `vault`'s entries from the global JSON cache at `path`, deleting the
+/// file once no vault has entries left (or it cannot be read, so nothing can
+/// be imported from it). Returns whether the file changed.
+fn drop_legacy(path: &Path, vault: &Path) -> std::io::Result<bool> {
+    // Vaults sharing the file must not undo each other's removals
+    let _lock = lock(path);
+    let data = match std::fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let mut cf = match serde_json::from_str::<LegacyCacheFile>(&data) {
+        Ok(cf) if cf.version == LEGACY_VERSION => cf,
+        _ => {
+            std::fs::remove_file(path)?;
+            return Ok(true);
+        }
+    };
+    let before = cf.entries.len();
+    cf.entries.retain(|p, _| !p.starts_with(vault));
+    if cf.entries.is_empty() {
+        std::fs::remove_file(path)?;
+        return Ok(true);
+    }
+    if cf.entries.len() == before {
+        return Ok(false);
+    }
+    let json = serde_json::to_string(&cf).map_err(std::io::Error::other)?;
+    note::write_atomic(path, json.as_bytes())?;
+    Ok(true)
+}
+
+/// Take an exclusive advisory lock on `<cache file>.lock`, held until the
+/// returned file is dropped. A separate file is locked because saves replace
+/// the cache file itself.
+pub fn lock(path: &Path) -> Option<std::fs::File> {
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path.with_extension("lock"))
+        .ok()?;
+    file.lock().ok()?;
+    Some(file)
+}
+
+/// Remove the cache file and search index for a vault, and its entries in
+/// the global JSON cache of earlier releases. The lock file stays: another
+/// process may hold it, and a new one would not exclude that process.
+/// Returns whether anything was removed.
+pub fn clear(vault: &Path) -> std::io::Result<bool> {
+    let Some(path) = cache_path(vault) else {
+        return Ok(false);
+    };
+    let _lock = lock(&path);
+    let mut removed = false;
+    // The search index lives next to the cache
+    for p in [path.with_extension("idx"), path.clone()] {
+        match std::fs::remove_file(&p) {
+            Ok(()) => removed = true,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    if let Some(legacy) = legacy_cache_path() {
+        removed |= drop_legacy(&legacy, vault)?;
+    }
+    Ok(removed)
+}
+
+/// `<cache dir>/sprout/frontmatter.json`, the global cache of earlier releases.
+fn legacy_cache_path() -> Option<PathBuf> {
     dirs::cache_dir().map(|d| d.join("sprout").join("frontmatter.json"))
 }
 
+/// `<cache dir>/sprout/vaults/<hash>.bin`, where `<hash>` is the FNV-1a hash
+/// of the canonical vault path.
+pub fn cache_path(vault: &Path) -> Option<PathBuf> {
+    let name = format!("{:016x}.bin", fnv1a(vault.to_string_lossy().as_bytes()));
+    dirs::cache_dir().map(|d| d.join("sprout").join("vaults").join(name))
+}
+
+/// 64-bit FNV-1a. Stable across builds, unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+// ── binary encoding ──
+
+pub fn put_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+pub fn put_str(buf: &mut Vec<u8>, s: &str) {
+    put_u32(buf, s.len() as u32);
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode_record(buf: &mut Vec<u8>, path: &Path, entry: Option<&CacheEntry>) {
+    let start = buf.len();
+    put_u32(buf, 0); // length, patched below
+    match entry {
+        Some(e) => {
+            buf.push(RECORD_UPSERT);
+            put_str(buf, &path.to_string_lossy());
+            buf.extend_from_slice(&e.mtime_secs.to_le_bytes());
+            put_u32(buf, e.mtime_nanos);
+            buf.extend_from_slice(&e.size.to_le_bytes());
+            encode_frontmatter(buf, &e.frontmatter);
+            encode_content(buf, e.content.as_ref());
+        }
+        None => {
+            buf.push(RECORD_REMOVE);
+            put_str(buf, &path.to_string_lossy());
+        }
+    }
+    let len = (buf.len() - start - 4) as u32;
+    buf[start..start + 4].copy_from_slice(&len.to_le_bytes());
+}
+
+fn encode_frontmatter(buf: &mut Vec<u8>, fm: &SproutFrontmatter) {
+    let flags = [
+        fm.maturity.is_some(),
+        fm.created.is_some(),
+        fm.last_review.is_some(),
+        fm.review_interval.is_some(),
+        fm.next_review.is_some(),
+        fm.ease.is_some(),
+    ]
+    .iter()
+    .enumerate()
+    .fold(0u8, |acc, (i, &set)| acc | ((set as u8) << i));
+    buf.push(flags);
+
+    if let Some(m) = &fm.maturity {
+        buf.extend_from_slice(&(m.len() as u16).to_le_bytes());
+        buf.extend_from_slice(m.as_bytes());
+    }
+    let date = |buf: &mut Vec<u8>, d: Option<NaiveDate>| {
+        if let Some(d) = d {
+            buf.extend_from_slice(&d.num_days_from_ce().to_le_bytes());
+        }
+    };
+    date(buf, fm.created);
+    date(buf, fm.last_review);
+    if let Some(i) = fm.review_interval {
+        put_u32(buf, i);
+    }
+    date(buf, fm.next_review);
+    if let Some(e) = fm.ease {
+        buf.extend_from_slice(&e.to_le_bytes());
+    }
+}
+
+fn decode_frontmatter(r: &mut Reader) -> Option<SproutFrontmatter> {
+    let flags = r.u8()?;
+    let has = |bit: u8| flags & (1 << bit) != 0;
+    let date = |r: &mut Reader| NaiveDate::from_num_days_from_ce_opt(r.i32()?);
+
+    let maturity = if has(0) {
+        let len = r.u16()? as usize;
+        Some(String::from_utf8(r.take(len)?.to_vec()).ok()?)
+    } else {
+        None
+    };
+    let created = if has(1) { Some(date(r)?) } else { None };
+    let last_review = if has(2) { Some(date(r)?) } else { None };
+    let review_interval = if has(3) { Some(r.u32()?) } else { None };
+    let next_review = if has(4) { Some(date(r)?) } else { None };
+    let ease = if has(5) { Some(r.f64()?) } else { None };
+    Some(SproutFrontmatter {
+        maturity,
+        created,
+        last_review,
+        review_interval,
+        next_review,
+        ease,
+    })
+}
+
+fn encode_content(buf: &mut Vec<u8>, content: Option<&NoteContent>) {
+    let Some(c) = content else {
+        buf.push(0);
+        return;
+    };
+    buf.push(1);
+    for list in [&c.links, &c.tags, &c.aliases, &c.headings, &c.block_ids] {
+        put_u32(buf, list.len() as u32);
+        for s in list {
+            put_str(buf, s);
+        }
+    }
+    match &c.title {
+        Some(t) => {
+            buf.push(1);
+            put_str(buf, t);
+        }
+        None => buf.push(0),
+    }
+    put_u32(buf, c.word_count as u32);
+    match &c.id {
+        Some(id) => {
+            buf.push(1);
+            put_str(buf, id);
+        }
+        None => buf.push(0),
+    }
+}
+
+fn decode_content(r: &mut Reader) -> Option<Option<NoteContent>> {
+    if r.u8()? == 0 {
+        return Some(None);
+    }
+    let list = |r: &mut Reader| -> Option<Vec<String>> {
+        let n = r.u32()?;
+        (0..n).map(|_| r.str()).collect()
+    };
+    let links = list(r)?;
+    let tags = list(r)?;
+    let aliases = list(r)?;
+    let headings = list(r)?;
+    let block_ids = list(r)?;
+    let title = if r.u8()? != 0 { Some(r.str()?) } else { None };
+    let word_count = r.u32()? as usize;
+    let id = if r.u8()? != 0 { Some(r.str()?) } else { None };
+    Some(Some(NoteContent { links, tags, title, word_count, aliases, headings, block_ids, id }))
+}
+
+/// Little-endian decoder; every read is `None` past the end of the data.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Reader { data, pos: 0 }
+    }
+
+    pub fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    pub fn u8(&mut self) -> Option<u8> {
+        Some(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    pub fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn i32(&mut self) -> Option<i32> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    pub fn i64(&mut self) -> Option<i64> {
+        self.array().map(i64::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    pub fn str(&mut self) -> Option<String> {
+        let len = self.u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use chrono::NaiveDate;
     use tempfile::TempDir;
 
+    fn empty_cache() -> FrontmatterCache {
+        FrontmatterCache {
+            vault: PathBuf::from("/test"),
+            path: None,
+            entries: HashMap::new(),
+            records: 0,
+            pending: Vec::new(),
+            rewrite: false,
+            legacy: None,
+        }
+    }
+
     fn sample_frontmatter() -> SproutFrontmatter {
         SproutFrontmatter {
             maturity: Some("seedling".into()),
@@ -151,26 +694,20 @@ mod tests {
 
     #[test]
     fn test_cache_get_hit() {
-        let mut cache = FrontmatterCache {
-            entries: HashMap::new(),
-            dirty: false,
-        };
+        let mut cache = empty_cache();
         let path = PathBuf::from("/test/note.md");
-        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter());
-        assert!(cache.dirty);
+        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter(), NoteContent::default());
+        assert!(cache.is_dirty());
         let result = cache.get(&path, 1000, 500, 200);
         assert!(result.is_some());
-        assert_eq!(result.unwrap().maturity.as_deref(), Some("seedling"));
+        assert_eq!(result.unwrap().0.maturity.as_deref(), Some("seedling"));
     }
 
     #[test]
     fn test_cache_get_miss_mtime() {
-        let mut cache = FrontmatterCache {
-            entries: HashMap::new(),
-            dirty: false,
-        };
+        let mut cache = empty_cache();
         let path = PathBuf::from("/test/note.md");
-        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter());
+        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter(), NoteContent::default());
         // Different mtime_secs
         assert!(cache.get(&path, 1001, 500, 200).is_none());
         // Different mtime_nanos
@@ -181,20 +718,14 @@ mod tests {
 
     #[test]
     fn test_cache_get_missing_path() {
-        let cache = FrontmatterCache {
-            entries: HashMap::new(),
-            dirty: false,
-        };
+        let cache = empty_cache();
         assert!(cache.get(Path::new("/nonexistent"), 0, 0, 0).is_none());
     }
 
     #[test]
     fn test_cache_save_not_dirty() {
         // Should not write when not dirty
-        let cache = FrontmatterCache {
-            entries: HashMap::new(),
-            dirty: false,
-        };
+        let cache = empty_cache();
         cache.save(); // no-op, no error
     }
 
@@ -204,23 +735,20 @@ mod tests {
         let cache_file = dir.path().join("frontmatter.json");
 
         // Write cache manually
-        let mut cache = FrontmatterCache {
-            entries: HashMap::new(),
-            dirty: true,
-        };
+        let mut cache = empty_cache();
         let path = PathBuf::from("/test/note.md");
-        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter());
+        cache.insert(path.clone(), 1000, 500, 200, sample_frontmatter(), NoteContent::default());
 
-        let cf = CacheFile {
-            version: CACHE_VERSION,
+        let cf = LegacyCacheFile {
+            version: LEGACY_VERSION,
             entries: cache.entries,
         };
         let data = serde_json::to_string(&cf).unwrap();
         std::fs::write(&cache_file, &data).unwrap();
 
         // Read it back
-        let loaded: CacheFile = serde_json::from_str(&std::fs::read_to_string(&cache_file).unwrap()).unwrap();
-        assert_eq!(loaded.version, CACHE_VERSION);
+        let loaded: LegacyCacheFile = serde_json::from_str(&std::fs::read_to_string(&cache_file).unwrap()).unwrap();
+        assert_eq!(loaded.version, LEGACY_VERSION);
         assert!(loaded.entries.contains_key(&path));
         let entry = &loaded.entries[&path];
         assert_eq!(entry.mtime_secs, 1000);
@@ -235,19 +763,318 @@ mod tests {
         std::fs::write(&cache_file, "not json").unwrap();
         // load() uses dirs::cache_dir(), so we can't directly test with custom path,
         // but we verify the deserialization logic
-        let result = serde_json::from_str::<CacheFile>("not json");
+        let result = serde_json::from_str::<LegacyCacheFile>("not json");
         assert!(result.is_err());
+        assert!(load_legacy(&cache_file, Path::new("/test")).is_none());
     }
 
     #[test]
     fn test_cache_load_wrong_version() {
-        let cf = CacheFile {
+        let dir = TempDir::new().unwrap();
+        let cache_file = dir.path().join("frontmatter.json");
+        let cf = LegacyCacheFile {
             version: 999,
             entries: HashMap::new(),
         };
-        let data = serde_json::to_string(&cf).unwrap();
-        let loaded: CacheFile = serde_json::from_str(&data).unwrap();
-        // Version mismatch should trigger empty cache in load()
-        assert_ne!(loaded.version, CACHE_VERSION);
+        std::fs::write(&cache_file, serde_json::to_string(&cf).unwrap()).unwrap();
+        // Version mismatch: nothing is imported
+        assert!(load_legacy(&cache_file, Path::new("/test")).is_none());
+    }
+
+    #[test]
+    fn test_cache_path_per_vault() {
+        let a = cache_path(Path::new("/notes/a"));
+        let b = cache_path(Path::new("/notes/b"));
+        assert_ne!(a, b);
+        assert_eq!(a, cache_path(Path::new("/notes/a")));
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn test_cache_save_load_and_prune() {
+        let dir = TempDir::new().unwrap();
+        let cache_file = dir.path().join("vaults/x.bin");
+        let vault = dir.path().to_path_buf();
+        let kept = dir.path().join("kept.md");
+        let gone = dir.path().join("gone.md");
+        std::fs::write(&kept, "x").unwrap();
+
+        let mut cache = FrontmatterCache::load_from(&vault, Some(cache_file.clone()), None);
+        cache.insert(kept.clone(), 0, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.insert(gone.clone(), 0, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.save();
+
+        // A cache file written for another vault is ignored
+        let other = FrontmatterCache::load_from(Path::new("/elsewhere"), Some(cache_file.clone()), None);
+        assert!(other.entries.is_empty());
+
+        let mut cache = FrontmatterCache::load_from(&vault, Some(cache_file.clone()), None);
+        assert_eq!(cache.entries.len(), 2);
+        // Both entries are stale: one is gone, the other has a different mtime
+        assert_eq!(cache.stats().stale, 2);
+        cache.prune(&HashSet::new());
+        assert!(cache.is_dirty());
+        assert!(cache.entries.contains_key(&kept));
+        assert!(!cache.entries.contains_key(&gone));
+    }
+
+    fn cache_at(vault: &Path, file: &Path) -> FrontmatterCache {
+        FrontmatterCache::load_from(vault, Some(file.to_path_buf()), None)
+    }
+
+    #[test]
+    fn test_binary_roundtrip_all_fields() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let mut cache = cache_at(Path::new("/v"), &file);
+        let full = SproutFrontmatter {
+            last_review: NaiveDate::from_ymd_opt(2025, 12, 31),
+            ..sample_frontmatter()
+        };
+        let content = NoteContent {
+            links: vec!["b".into(), "c.md".into()],
+            tags: vec!["rust".into()],
+            title: Some("Title".into()),
+            word_count: 42,
+            aliases: vec!["T".into()],
+            headings: vec!["Title".into(), "Setup".into()],
+            block_ids: vec!["p1".into()],
+            id: Some("1a".into()),
+        };
+        cache.insert(PathBuf::from("/v/a.md"), -5, 7, 9, full, content.clone());
+        cache.insert(PathBuf::from("/v/b.md"), 1, 2, 3, SproutFrontmatter::default(), NoteContent::default());
+        cache.save();
+
+        let loaded = cache_at(Path::new("/v"), &file);
+        assert!(!loaded.is_dirty());
+        let (a, a_content) = loaded.get(Path::new("/v/a.md"), -5, 7, 9).unwrap();
+        assert_eq!(a_content, Some(&content));
+        assert_eq!(a.maturity.as_deref(), Some("seedling"));
+        assert_eq!(a.last_review, NaiveDate::from_ymd_opt(2025, 12, 31));
+        assert_eq!(a.next_review, NaiveDate::from_ymd_opt(2026, 1, 2));
+        assert_eq!(a.review_interval, Some(1));
+        assert_eq!(a.ease, Some(2.5));
+        let (b, b_content) = loaded.get(Path::new("/v/b.md"), 1, 2, 3).unwrap();
+        assert!(b.is_untracked());
+        assert_eq!(b_content, Some(&NoteContent::default()));
+    }
+
+    #[test]
+    fn test_save_appends_changed_entries_only() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let mut cache = cache_at(Path::new("/v"), &file);
+        for i in 0..100 {
+            cache.insert(PathBuf::from(format!("/v/{i}.md")), i, 0, 1, sample_frontmatter(), NoteContent::default());
+        }
+        cache.save();
+        let full_len = std::fs::metadata(&file).unwrap().len();
+
+        let mut cache = cache_at(Path::new("/v"), &file);
+        assert_eq!(cache.records, 100);
+        cache.insert(PathBuf::from("/v/0.md"), 99, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.entries.remove(Path::new("/v/1.md"));
+        cache.pending.push(PathBuf::from("/v/1.md"));
+        cache.save();
+        let appended = std::fs::metadata(&file).unwrap().len() - full_len;
+        assert!(appended > 0 && appended < full_len / 20, "appended {appended} of {full_len} bytes");
+
+        let cache = cache_at(Path::new("/v"), &file);
+        assert_eq!(cache.records, 102);
+        assert_eq!(cache.entries.len(), 99);
+        assert!(cache.get(Path::new("/v/0.md"), 99, 0, 1).is_some());
+        assert!(cache.get(Path::new("/v/1.md"), 1, 0, 1).is_none());
+    }
+
+    #[test]
+    fn test_flush_does_not_repeat_records() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let mut cache = cache_at(Path::new("/v"), &file);
+        cache.insert(PathBuf::from("/v/a.md"), 1, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.flush();
+        assert!(!cache.is_dirty());
+        cache.insert(PathBuf::from("/v/b.md"), 1, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.flush();
+        cache.remove(Path::new("/v/a.md"));
+        cache.flush();
+        assert_eq!(cache.records, 3);
+
+        let loaded = cache_at(Path::new("/v"), &file);
+        assert_eq!(loaded.records, 3);
+        assert_eq!(loaded.entries.len(), 1);
+        assert!(loaded.get(Path::new("/v/b.md"), 1, 0, 1).is_some());
+    }
+
+    #[test]
+    fn test_compacts_when_dead_records_dominate() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let path = PathBuf::from("/v/a.md");
+        for i in 0..(COMPACT_SLACK as i64 + 5) {
+            let mut cache = cache_at(Path::new("/v"), &file);
+            cache.insert(path.clone(), i, 0, 1, sample_frontmatter(), NoteContent::default());
+            cache.save();
+        }
+        let cache = cache_at(Path::new("/v"), &file);
+        assert!(cache.records < COMPACT_SLACK, "records {}", cache.records);
+        assert!(cache.get(&path, COMPACT_SLACK as i64 + 4, 0, 1).is_some());
+    }
+
+    #[test]
+    fn test_truncated_tail_and_foreign_header() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let mut cache = cache_at(Path::new("/v"), &file);
+        cache.insert(PathBuf::from("/v/a.md"), 1, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.insert(PathBuf::from("/v/b.md"), 1, 0, 1, sample_frontmatter(), NoteContent::default());
+        cache.save();
+
+        // Interrupted append: the partial record is dropped, the rest survives
+        let mut data = std::fs::read(&file).unwrap();
+        data.truncate(data.len() - 3);
+        std::fs::write(&file, &data).unwrap();
+        let cache = cache_at(Path::new("/v"), &file);
+        assert_eq!(cache.entries.len(), 1);
+        assert!(cache.rewrite);
+
+        // Another vault's file, or a different format version, is discarded
+        assert!(cache_at(Path::new("/w"), &file).entries.is_empty());
+        data[4] = 99;
+        std::fs::write(&file, &data).unwrap();
+        assert!(cache_at(Path::new("/v"), &file).entries.is_empty());
+    }
+
+    #[test]
+    fn test_imports_global_json_cache() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("c.bin");
+        let json = dir.path().join("frontmatter.json");
+        // The format shipped before per-vault files: no vault, no content
+        std::fs::write(
+            &json,
+            r#"{"version":1,"entries":{
+                "/v/a.md":{"mtime_secs":1,"mtime_nanos":2,"size":3,"frontmatter":{"maturity":"seedling"}},
+                "/vw/b.md":{"mtime_secs":1,"mtime_nanos":2,"size":3,"frontmatter":{}}}}"#,
+        )
+        .unwrap();
+
+        let cache = FrontmatterCache::load_from(Path::new("/v"), Some(file.clone()), Some(&json));
+        assert_eq!(cache.entries.len(), 1);
+        let (fm, content) = cache.get(Path::new("/v/a.md"), 1, 2, 3).unwrap();
+        assert_eq!(fm.maturity.as_deref(), Some("seedling"));
+        assert!(content.is_none());
+        assert!(cache.is_dirty());
+        cache.save();
+        assert!(file.exists());
+        assert!(cache_at(Path::new("/v"), &file).get(Path::new("/v/a.md"), 1, 2, 3).is_some());
+        // Other vaults' entries stay for them to import
+        let rest = load_legacy(&json, Path::new("/vw")).unwrap();
+        assert_eq!(rest.len(), 1);
+        assert!(load_legacy(&json, Path::new("/v")).unwrap().is_empty());
+
+        // The last vault to import removes the file
+        let other = dir.path().join("d.bin");
+        let cache = FrontmatterCache::load_from(Path::new("/vw"), Some(other.clone()), Some(&json));
+        assert_eq!(cache.entries.len(), 1);
+        cache.save();
+        assert!(other.exists());
+        assert!(!json.exists());
+    }
+
+    #[test]
+    fn test_drop_legacy_keeps_other_vaults() {
+        let dir = TempDir::new().unwrap();
+        let json = dir.path().join("frontmatter.json");
+        std::fs::write(
+            &json,
+            r#"{"version":1,"entries":{
+                "/v/a.md":{"mtime_secs":1,"mtime_nanos":2,"size":3,"frontmatter":{}},
+                "/vw/b.md":{"mtime_secs":1,"mtime_nanos":2,"size":3,"frontmatter":{}}}}"#,
+        )
+        .unwrap();
+        assert!(!drop_legacy(&json, Path::new("/elsewhere")).unwrap());
+        assert!(drop_legacy(&json, Path::new("/v")).unwrap());
+        assert_eq!(load_legacy(&json, Path::new("/vw")).unwrap().len(), 1);
+        assert!(drop_legacy(&json, Path::new("/vw")).unwrap());
+        assert!(!json.exists());
+        assert!(!drop_legacy(&json, Path::new("/vw")).unwrap());
+
+        // Nothing can be imported from an unreadable file
+        std::fs::write(&json, "{").unwrap();
+        assert!(drop_legacy(&json, Path::new("/v")).unwrap());
+        assert!(!json.exists());
+    }
+
+    /// Compare the JSON and binary formats on a large vault, and check that
+    /// the binary file is smaller, loads faster and saves one change much
+    /// faster than the JSON file is rewritten. A full binary save also merges
+    /// with the file on disk and is not compared. Timings need an optimised build:
+    /// `cargo test --release bench_cache_formats -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_cache_formats() {
+        use std::time::Instant;
+
+        const NOTES: usize = 50_000;
+        let dir = TempDir::new().unwrap();
+        let vault = dir.path().join("vault");
+        let bin = dir.path().join("c.bin");
+        let json = dir.path().join("c.json");
+
+        let mut cache = cache_at(&vault, &bin);
+        for i in 0..NOTES {
+            let path = vault.join(format!("notes/{i:05}-some-note-title.md"));
+            cache.insert(path, 1_770_000_000 + i as i64, 123_456_789, 2048, sample_frontmatter(), NoteContent::default());
+        }
+
+        let t = Instant::now();
+        cache.save();
+        let bin_save = t.elapsed();
+        let cf = LegacyCacheFile {
+            version: LEGACY_VERSION,
+            entries: cache
+                .entries
+                .iter()
+                .map(|(k, v)| {
+                    (k.clone(), CacheEntry { frontmatter: v.frontmatter.clone(), content: v.content.clone(), ..*v })
+                })
+                .collect(),
+        };
+        let t = Instant::now();
+        std::fs::write(&json, serde_json::to_string(&cf).unwrap()).unwrap();
+        let json_save = t.elapsed();
+
+        let t = Instant::now();
+        let loaded = cache_at(&vault, &bin);
+        let bin_load = t.elapsed();
+        assert_eq!(loaded.entries.len(), NOTES);
+        let t = Instant::now();
+        let loaded_json = load_legacy(&json, &vault).unwrap();
+        let json_load = t.elapsed();
+        assert_eq!(loaded_json.len(), NOTES);
+
+        // One changed note: binary appends a record, JSON rewrites everything
+        let mut changed = loaded;
+        changed.insert(vault.join("notes/00000-some-note-title.md"), 1, 0, 1, sample_frontmatter(), NoteContent::default());
+        let t = Instant::now();
+        changed.save();
+        let bin_update = t.elapsed();
+        let t = Instant::now();
+        std::fs::write(&json, serde_json::to_string(&cf).unwrap()).unwrap();
+        let json_update = t.elapsed();
+
+        let size = |p: &Path| std::fs::metadata(p).unwrap().len();
+        println!("{NOTES} entries        binary      json");
+        println!("file size       {:>10} {:>10}", size(&bin), size(&json));
+        println!("load            {bin_load:>10.2?} {json_load:>10.2?}");
+        println!("full save       {bin_save:>10.2?} {json_save:>10.2?}");
+        println!("save 1 change   {bin_update:>10.2?} {json_update:>10.2?}");
+
+        assert!(size(&bin) < size(&json));
+        assert!(bin_load < json_load);
+        // Appending a record against rewriting every entry
+        assert!(bin_update * 10 < json_update);
     }
 }
@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -19,33 +19,80 @@ pub struct Cli {
 #[derive(Subcommand)]
 pub enum Commands {
     /// List notes due for review today
-    Review,
-    /// Mark a note as reviewed with a difficulty rating
+    Review {
+        /// Only include notes matching a query, e.g. "maturity:budding AND interval>14"
+        #[arg(long)]
+        query: Option<String>,
+        #[command(flatten)]
+        sort: SortArgs,
+    },
+    /// Mark notes as reviewed with a difficulty rating
+    #[command(allow_missing_positional = true)]
     Done {
-        /// Path to the reviewed note file
-        file: PathBuf,
+        /// Paths to the reviewed note files (`-` reads paths from stdin)
+        #[arg(num_args = 0.., required_unless_present = "query")]
+        files: Vec<PathBuf>,
         /// Difficulty rating
         rating: Rating,
+        /// Also review every tracked note matching a query
+        #[arg(long)]
+        query: Option<String>,
+        /// Change no note unless every note succeeds
+        #[arg(long)]
+        atomic: bool,
     },
-    /// Change the maturity level of a note
+    /// Change the maturity level of notes
+    #[command(allow_missing_positional = true)]
     Promote {
-        /// Path to the note file
-        file: PathBuf,
+        /// Paths to the note files (`-` reads paths from stdin)
+        #[arg(num_args = 0.., required_unless_present = "query")]
+        files: Vec<PathBuf>,
         /// Target maturity level
         maturity: Maturity,
+        /// Also promote every tracked note matching a query
+        #[arg(long)]
+        query: Option<String>,
+        /// Change no note unless every note succeeds
+        #[arg(long)]
+        atomic: bool,
     },
     /// Show statistics about your note collection
-    Stats,
-    /// Add sprout frontmatter to a new or existing note
+    Stats {
+        /// Include review history, retention and growth statistics
+        #[arg(long)]
+        detailed: bool,
+        /// Include review streaks and a heatmap of reviews over the past year
+        #[arg(long)]
+        calendar: bool,
+        /// Only include notes matching a query, e.g. "maturity:budding AND interval>14"
+        #[arg(long)]
+        query: Option<String>,
+    },
+    /// Add sprout frontmatter to new or existing notes
     Init {
-        /// Path to the note file
-        file: PathBuf,
+        /// Paths to the note files (`-` reads paths from stdin)
+        #[arg(required_unless_present_any = ["all", "glob"])]
+        files: Vec<PathBuf>,
+        /// Initialize every note in the vault that is missing sprout fields
+        #[arg(long)]
+        all: bool,
+        /// Initialize notes missing sprout fields whose vault-relative path matches a glob
+        #[arg(long)]
+        glob: Option<String>,
+        /// Change no note unless every note succeeds
+        #[arg(long)]
+        atomic: bool,
     },
     /// List all tracked notes
     List {
         /// Filter by maturity level
         #[arg(long)]
         maturity: Option<Maturity>,
+        /// Only include notes matching a query, e.g. "maturity:budding AND interval>14"
+        #[arg(long)]
+        query: Option<String>,
+        #[command(flatten)]
+        sort: SortArgs,
     },
     /// Show detailed information about a single note
     Show {
@@ -59,10 +106,142 @@ pub enum Commands {
         /// Template name to use
         #[arg(long)]
         template: Option<String>,
+        /// Luhmann ID to file the new note under (id_scheme = "luhmann")
+        #[arg(long)]
+        parent: Option<String>,
+        /// Directory for the new note, relative to the vault
+        #[arg(long)]
+        dir: Option<PathBuf>,
+        /// Template variable for {{key}} (repeatable)
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_var)]
+        vars: Vec<(String, String)>,
     },
+    /// Search the text of notes, best matches first
+    Search {
+        /// Words, "quoted phrases" and prefix* terms; a note must match all
+        #[arg(required = true)]
+        terms: Vec<String>,
+        /// Only notes of this maturity
+        #[arg(long)]
+        maturity: Option<Maturity>,
+        /// Only notes with this tag (nested tags included)
+        #[arg(long)]
+        tag: Option<String>,
+        /// Maximum number of results
+        #[arg(long, default_value_t = 20)]
+        limit: usize,
+    },
+    /// Suggest notes to link with a note, and unlinked mentions of it
+    Related {
+        /// Path to the note file
+        file: PathBuf,
+        /// Maximum number of suggestions
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+    /// Link plain-text mentions of other notes' titles and aliases
+    Autolink {
+        /// Path to the note file
+        file: PathBuf,
+        /// Link every mention without asking
+        #[arg(long, conflicts_with = "dry_run")]
+        yes: bool,
+        /// Only show the diff
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Check the vault for problems
+    Check {
+        #[command(subcommand)]
+        action: CheckAction,
+    },
+    /// Show due, overdue and upcoming reviews, stale seedlings and incomplete notes
+    Agenda {
+        /// Days after creation before an unreviewed seedling counts as stale
+        #[arg(long, default_value_t = 14)]
+        stale_days: u32,
+    },
+    /// Recommend scheduler parameters fitted to your review history
+    Optimize {
+        /// Share of reviews that should not be rated hard
+        #[arg(long, default_value_t = 0.9)]
+        target_retention: f64,
+    },
+    /// Inspect or reset the vault's frontmatter cache
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+    /// Watch the vault and keep the cache and link index up to date
+    Watch {
+        /// Print index events (added, due, link broken, ...) as they happen
+        #[arg(long)]
+        events: bool,
+    },
+    /// Answer JSON-RPC requests from editor integrations, keeping the index warm
+    Serve {
+        /// Listen on a Unix socket instead of stdin/stdout
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Run a Language Server Protocol server on stdin/stdout
+    Lsp,
 }
 
-#[derive(ValueEnum, Clone, Debug, PartialEq, Eq)]
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum CheckAction {
+    /// Report links to missing notes, headings and block ids
+    Links,
+}
+
+#[derive(Subcommand, Clone, Debug, PartialEq, Eq)]
+pub enum CacheAction {
+    /// Show the cache file, entry count and stale entries
+    Stats,
+    /// Delete the vault's cache file
+    Clear,
+    /// Delete the cache and rebuild it with a full scan
+    Rebuild,
+}
+
+/// Ordering and pagination options shared by `review` and `list`.
+/// Also the `review` / `list` parameters of `sprout serve`.
+#[derive(Args, Clone, Debug, Default, serde::Deserialize)]
+#[serde(default)]
+pub struct SortArgs {
+    /// Sort key (default: next-review for review, path for list)
+    #[arg(long)]
+    pub sort: Option<SortKey>,
+    /// Reverse the sort order (notes missing the sort key stay last)
+    #[arg(long)]
+    pub reverse: bool,
+    /// Maximum number of notes to output
+    #[arg(long)]
+    pub limit: Option<usize>,
+    /// Number of notes to skip before output
+    #[arg(long, default_value_t = 0)]
+    pub offset: usize,
+    /// Seed for --sort random (default: current time)
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SortKey {
+    NextReview,
+    /// Days late divided by interval
+    OverdueRatio,
+    Ease,
+    Interval,
+    Links,
+    Created,
+    Path,
+    Random,
+}
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Rating {
     Hard,
     Good,
@@ -102,3 +281,10 @@ pub enum OutputFormat {
     Human,
     Json,
 }
+
+/// `--var key=value`, split at the first `=`.
+fn parse_var(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected KEY=VALUE, found '{s}'"))
+}
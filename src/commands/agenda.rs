@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use chrono::{Local, NaiveDate};
+
+use crate::cli::OutputFormat;
+use crate::error::SproutError;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
+use crate::output;
+
+/// Overdue buckets: (label, min days late, max days late). `None` = unbounded.
+const OVERDUE_BUCKETS: &[(&str, i64, Option<i64>)] = &[
+    ("1-3 days", 1, Some(3)),
+    ("4-7 days", 4, Some(7)),
+    ("8-30 days", 8, Some(30)),
+    ("over 30 days", 31, None),
+];
+
+const UPCOMING_DAYS: i64 = 7;
+
+pub struct OverdueGroup<'a> {
+    pub label: &'static str,
+    pub min_days: i64,
+    pub max_days: Option<i64>,
+    pub notes: Vec<&'a NoteMetaInfo>,
+}
+
+pub struct Agenda<'a> {
+    pub today: NaiveDate,
+    pub due_today: Vec<&'a NoteMetaInfo>,
+    pub overdue: Vec<OverdueGroup<'a>>,
+    pub upcoming: Vec<&'a NoteMetaInfo>,
+    pub stale_seedlings: Vec<&'a NoteMetaInfo>,
+    pub incomplete: Vec<(&'a NoteMetaInfo, Vec<&'static str>)>,
+}
+
+pub fn run(
+    vault: &Path,
+    scan_options: &ScanOptions,
+    stale_days: u32,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let notes = note::scan_vault_metadata(vault, scan_options)
+        .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+
+    let today = Local::now().date_naive();
+    let agenda = build_agenda(&notes, today, stale_days);
+
+    output::format_agenda(&agenda, stale_days, format);
+    Ok(())
+}
+
+pub fn build_agenda(notes: &[NoteMetaInfo], today: NaiveDate, stale_days: u32) -> Agenda<'_> {
+    let mut due_today = Vec::new();
+    let mut overdue: Vec<OverdueGroup> = OVERDUE_BUCKETS
+        .iter()
+        .map(|&(label, min_days, max_days)| OverdueGroup {
+            label,
+            min_days,
+            max_days,
+            notes: Vec::new(),
+        })
+        .collect();
+    let mut upcoming = Vec::new();
+    let mut stale_seedlings = Vec::new();
+    let mut incomplete = Vec::new();
+
+    for n in notes {
+        // No sprout fields at all → untracked, not part of the agenda
+        if n.sprout.is_untracked() {
+            continue;
+        }
+        let missing = n.sprout.missing_fields();
+        if !missing.is_empty() {
+            incomplete.push((n, missing));
+            continue;
+        }
+
+        // Complete from here on: every field is present
+        let next_review = n.sprout.next_review.unwrap_or(today);
+        let days_late = (today - next_review).num_days();
+        if days_late == 0 {
+            due_today.push(n);
+        } else if days_late > 0 {
+            if let Some(group) = overdue
+                .iter_mut()
+                .find(|g| days_late >= g.min_days && g.max_days.is_none_or(|max| days_late <= max))
+            {
+                group.notes.push(n);
+            }
+        } else if -days_late <= UPCOMING_DAYS {
+            upcoming.push(n);
+        }
+
+        let created = n.sprout.created.unwrap_or(today);
+        let never_reviewed = n.sprout.last_review.is_none_or(|lr| lr <= created);
+        if n.sprout.maturity.as_deref() == Some("seedling")
+            && never_reviewed
+            && (today - created).num_days() >= stale_days as i64
+        {
+            stale_seedlings.push(n);
+        }
+    }
+
+    due_today.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    for group in &mut overdue {
+        group.notes.sort_by_key(|n| n.sprout.next_review);
+    }
+    upcoming.sort_by_key(|n| n.sprout.next_review);
+    stale_seedlings.sort_by_key(|n| n.sprout.created);
+    incomplete.sort_by(|a, b| a.0.relative_path.cmp(&b.0.relative_path));
+
+    Agenda {
+        today,
+        due_today,
+        overdue,
+        upcoming,
+        stale_seedlings,
+        incomplete,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::SproutFrontmatter;
+    use std::path::PathBuf;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn note(name: &str, maturity: &str, created: NaiveDate, last_review: NaiveDate, next_review: NaiveDate) -> NoteMetaInfo {
+        NoteMetaInfo {
+            path: PathBuf::from(format!("/vault/{name}")),
+            relative_path: name.to_string(),
+            sprout: SproutFrontmatter {
+                maturity: Some(maturity.into()),
+                created: Some(created),
+                last_review: Some(last_review),
+                review_interval: Some(1),
+                next_review: Some(next_review),
+                ease: Some(2.5),
+            },
+            content: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_build_agenda_sections() {
+        let today = date(2026, 3, 31);
+        let old = date(2026, 1, 1);
+        let notes = vec![
+            note("today.md", "budding", old, date(2026, 3, 30), today),
+            note("late2.md", "budding", old, date(2026, 3, 1), date(2026, 3, 29)),
+            note("late10.md", "budding", old, date(2026, 3, 1), date(2026, 3, 21)),
+            note("late90.md", "budding", old, date(2025, 12, 1), date(2026, 1, 1)),
+            note("soon.md", "budding", old, date(2026, 3, 30), date(2026, 4, 7)),
+            note("later.md", "budding", old, date(2026, 3, 30), date(2026, 4, 8)),
+            note("stale.md", "seedling", old, old, date(2026, 4, 30)),
+            note("fresh.md", "seedling", date(2026, 3, 25), date(2026, 3, 25), date(2026, 4, 30)),
+            NoteMetaInfo {
+                path: PathBuf::from("/vault/partial.md"),
+                relative_path: "partial.md".into(),
+                sprout: SproutFrontmatter {
+                    maturity: Some("seedling".into()),
+                    ..Default::default()
+                },
+                content: Default::default(),
+            },
+            NoteMetaInfo {
+                path: PathBuf::from("/vault/plain.md"),
+                relative_path: "plain.md".into(),
+                sprout: SproutFrontmatter::default(),
+                content: Default::default(),
+            },
+        ];
+
+        let agenda = build_agenda(&notes, today, 14);
+        let names = |v: &[&NoteMetaInfo]| v.iter().map(|n| n.relative_path.clone()).collect::<Vec<_>>();
+
+        assert_eq!(names(&agenda.due_today), vec!["today.md"]);
+        assert_eq!(names(&agenda.overdue[0].notes), vec!["late2.md"]);
+        assert!(agenda.overdue[1].notes.is_empty());
+        assert_eq!(names(&agenda.overdue[2].notes), vec!["late10.md"]);
+        assert_eq!(names(&agenda.overdue[3].notes), vec!["late90.md"]);
+        assert_eq!(names(&agenda.upcoming), vec!["soon.md"]);
+        assert_eq!(names(&agenda.stale_seedlings), vec!["stale.md"]);
+        assert_eq!(agenda.incomplete.len(), 1);
+        assert_eq!(agenda.incomplete[0].0.relative_path, "partial.md");
+        assert_eq!(agenda.incomplete[0].1.len(), 5);
+    }
+}
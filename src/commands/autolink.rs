@@ -0,0 +1,375 @@
+use std::collections::{BTreeSet, HashMap};
+use std::io::{BufRead, IsTerminal, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use pulldown_cmark::{Event, Options, Parser, Tag};
+use regex::Regex;
+
+use crate::cli::OutputFormat;
+use crate::error::SproutError;
+use crate::frontmatter::NoteFormat;
+use crate::index::{LinkTarget, VaultIndex};
+use crate::links;
+use crate::note::{self, ScanOptions};
+use crate::output;
+use crate::search;
+
+/// Bare URLs, which CommonMark does not parse as links.
+const URL_PATTERN: &str = r"https?://\S+";
+
+/// Org links (`[[target]]`, `[[target][description]]`) and `~code~` or
+/// `=verbatim=` markup.
+const ORG_SKIP_PATTERN: &str = r"\[\[[^\]]*\](?:\[[^\]]*\])?\]|~[^~\n]+~|=[^=\n]+=";
+
+/// A plain-text mention of another note that can become a link.
+pub struct Proposal {
+    /// 1-based line and character column
+    pub line: usize,
+    pub column: usize,
+    /// Byte range in the file
+    pub start: usize,
+    pub end: usize,
+    /// The mention as written
+    pub text: String,
+    pub relative_target: String,
+    /// Replacement, such as `[[Ownership]]` or `[[ml|machine learning]]`
+    pub link: String,
+    pub applied: bool,
+}
+
+/// A line of the note before and after linking.
+pub struct LineChange {
+    pub line: usize,
+    pub old: String,
+    pub new: String,
+}
+
+pub struct Autolink {
+    pub path: PathBuf,
+    pub relative_path: String,
+    pub proposals: Vec<Proposal>,
+    /// Every proposal applied, shown before anything is written
+    pub diff: Vec<LineChange>,
+    pub dry_run: bool,
+}
+
+pub fn run(
+    file: &Path,
+    vault: &Path,
+    scan_options: &ScanOptions,
+    yes: bool,
+    dry_run: bool,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    if !file.exists() {
+        return Err(SproutError::FileNotFound(file.display().to_string()));
+    }
+    note::ensure_in_vault(file, vault)?;
+    let file_canonical = std::fs::canonicalize(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+
+    let index = VaultIndex::build(vault, scan_options, Local::now().date_naive())?;
+    let Some(meta) = index.note(&file_canonical).cloned() else {
+        // Excluded from scanning or not a note extension
+        return Err(SproutError::FileNotFound(file.display().to_string()));
+    };
+    let content = std::fs::read_to_string(&file_canonical)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+
+    let names = note_names(&index, &meta.path);
+    let texts: Vec<&str> = names.iter().map(|(name, _)| name.as_str()).collect();
+    let mut proposals: Vec<Proposal> = find_mentions(&meta.path, &content, &texts)
+        .into_iter()
+        .map(|(start, end, n)| {
+            let target = &names[n].1;
+            let text = content[start..end].to_string();
+            let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+            Proposal {
+                line: content[..start].matches('\n').count() + 1,
+                column: content[line_start..start].chars().count() + 1,
+                start,
+                end,
+                link: link_for(&index, &meta.path, target, &text),
+                relative_target: index.note(target).map(|n| n.relative_path.clone()).unwrap_or_default(),
+                text,
+                applied: false,
+            }
+        })
+        .collect();
+
+    let all = vec![true; proposals.len()];
+    let mut autolink = Autolink {
+        diff: line_changes(&content, &proposals, &all),
+        path: meta.path.clone(),
+        relative_path: meta.relative_path.clone(),
+        proposals: Vec::new(),
+        dry_run: false,
+    };
+
+    output::format_autolink_diff(&autolink, format);
+
+    // Without --yes or a terminal to ask on, the diff is all there is
+    let interactive = *format == OutputFormat::Human && std::io::stdin().is_terminal();
+    let accepted = if proposals.is_empty() || dry_run || (!yes && !interactive) {
+        autolink.dry_run = true;
+        vec![false; proposals.len()]
+    } else if yes {
+        all
+    } else {
+        ask(&autolink.relative_path, &content, &proposals)?
+    };
+
+    if accepted.contains(&true) {
+        let mut updated = content.clone();
+        for (p, _) in proposals.iter().zip(&accepted).rev().filter(|(_, a)| **a) {
+            updated.replace_range(p.start..p.end, &p.link);
+        }
+        note::write_note(&meta.path, &updated)?;
+    }
+    for (p, a) in proposals.iter_mut().zip(accepted) {
+        p.applied = a;
+    }
+    autolink.proposals = proposals;
+    output::format_autolink(&autolink, format);
+    Ok(())
+}
+
+/// Titles (file names for notes without one) and aliases of every note but
+/// `source`, longest first, with the note they name. Names shared by two
+/// notes, or with `source`, are left out, as are names that cannot be
+/// written inside a wiki link.
+fn note_names(index: &VaultIndex, source: &Path) -> Vec<(String, PathBuf)> {
+    let notes = index.notes();
+    let mut owners: HashMap<String, (String, BTreeSet<PathBuf>)> = HashMap::new();
+    for n in &notes {
+        let title = match &n.content.title {
+            Some(title) => title.clone(),
+            None => n.path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+        };
+        for name in std::iter::once(&title).chain(&n.content.aliases) {
+            let name = name.trim().to_string();
+            if name.contains(['[', ']', '|', '#']) || search::tokenize(&name).is_empty() {
+                continue;
+            }
+            owners.entry(name.to_lowercase()).or_insert_with(|| (name, BTreeSet::new())).1.insert(n.path.clone());
+        }
+    }
+
+    let mut names: Vec<(String, PathBuf)> = owners
+        .into_values()
+        .filter(|(_, paths)| paths.len() == 1)
+        .filter_map(|(name, paths)| paths.into_iter().next().filter(|p| p != source).map(|p| (name, p)))
+        .collect();
+    names.sort_by(|a, b| b.0.chars().count().cmp(&a.0.chars().count()).then_with(|| a.0.cmp(&b.0)));
+    names
+}
+
+/// Unlinked mentions of `names` in the body of a note, as byte ranges with
+/// the index of the name, in file order. Earlier names win where mentions
+/// overlap. Mentions inside `skip_ranges` are left alone.
+fn find_mentions(path: &Path, content: &str, names: &[&str]) -> Vec<(usize, usize, usize)> {
+    let mut taken = skip_ranges(path, content);
+    let mut found = Vec::new();
+    let mut offset = 0;
+    for raw in content.split_inclusive('\n') {
+        let line_start = offset;
+        offset += raw.len();
+        let line = raw.trim_end_matches(['\n', '\r']);
+        for (n, name) in names.iter().enumerate() {
+            let chars = name.chars().count();
+            for start in links::find_mentions(line, name) {
+                let end = start + line[start..].char_indices().nth(chars).map_or(line.len() - start, |(b, _)| b);
+                let (start, end) = (line_start + start, line_start + end);
+                if taken.iter().any(|r| start < r.end && r.start < end) {
+                    continue;
+                }
+                taken.push(start..end);
+                found.push((start, end, n));
+            }
+        }
+    }
+    found.sort();
+    found
+}
+
+/// Byte ranges a mention may not overlap. Markdown is tokenized as CommonMark
+/// with wiki links, skipping the frontmatter, headings, code spans and blocks
+/// (fenced or indented), HTML (comments included), links and images. Org
+/// notes skip the property drawer and keywords, headings, `#+begin_` blocks
+/// and `ORG_SKIP_PATTERN` spans. Bare URLs are skipped in both.
+fn skip_ranges(path: &Path, content: &str) -> Vec<Range<usize>> {
+    let mut skipped: Vec<Range<usize>> = match NoteFormat::from_path(path) {
+        NoteFormat::Markdown => {
+            let mut options = Options::empty();
+            options.insert(Options::ENABLE_WIKILINKS);
+            options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+            Parser::new_ext(content, options)
+                .into_offset_iter()
+                .filter(|(event, _)| {
+                    matches!(
+                        event,
+                        Event::Start(
+                            Tag::MetadataBlock(_)
+                                | Tag::Heading { .. }
+                                | Tag::CodeBlock(_)
+                                | Tag::HtmlBlock
+                                | Tag::Link { .. }
+                                | Tag::Image { .. }
+                        ) | Event::Code(_)
+                            | Event::Html(_)
+                            | Event::InlineHtml(_)
+                    )
+                })
+                .map(|(_, range)| range)
+                .collect()
+        }
+        NoteFormat::Org => {
+            let body_start = search::body_start(path, content);
+            let heading_re = Regex::new(r"^\*+\s").unwrap();
+            let skip_re = Regex::new(ORG_SKIP_PATTERN).unwrap();
+            let mut skipped = Vec::new();
+            let mut in_block = false;
+            let mut offset = 0;
+            for (i, line) in content.split_inclusive('\n').enumerate() {
+                let range = offset..offset + line.len();
+                offset += line.len();
+                let lower = line.trim_start().to_ascii_lowercase();
+                if in_block {
+                    in_block = !lower.starts_with("#+end_");
+                    skipped.push(range);
+                } else if lower.starts_with("#+begin_") {
+                    in_block = true;
+                    skipped.push(range);
+                } else if i < body_start || lower.starts_with("#+") || heading_re.is_match(line) {
+                    skipped.push(range);
+                } else {
+                    skipped.extend(skip_re.find_iter(line).map(|m| range.start + m.start()..range.start + m.end()));
+                }
+            }
+            skipped
+        }
+    };
+    let url_re = Regex::new(URL_PATTERN).unwrap();
+    skipped.extend(url_re.find_iter(content).map(|m| m.range()));
+    skipped
+}
+
+/// `[[text]]` when the mention already resolves to `target` as a link,
+/// otherwise the target's link name with `text` as the description.
+fn link_for(index: &VaultIndex, source: &Path, target: &Path, text: &str) -> String {
+    if index.resolve(source, text) == LinkTarget::Note(target.to_path_buf()) {
+        return format!("[[{text}]]");
+    }
+    let name = index.link_name(target).unwrap_or_else(|| text.to_string());
+    match NoteFormat::from_path(source) {
+        NoteFormat::Markdown => format!("[[{name}|{text}]]"),
+        NoteFormat::Org => format!("[[{name}][{text}]]"),
+    }
+}
+
+/// Lines containing an accepted proposal, before and after.
+fn line_changes(content: &str, proposals: &[Proposal], accepted: &[bool]) -> Vec<LineChange> {
+    let mut changes: Vec<LineChange> = Vec::new();
+    for (p, _) in proposals.iter().zip(accepted).rev().filter(|(_, a)| **a) {
+        let start = content[..p.start].rfind('\n').map_or(0, |i| i + 1);
+        let end = content[p.end..].find('\n').map_or(content.len(), |i| p.end + i);
+        match changes.last_mut() {
+            Some(change) if change.line == p.line => {
+                let column = p.start - start;
+                change.new.replace_range(column..column + (p.end - p.start), &p.link);
+            }
+            _ => {
+                let old = content[start..end].trim_end_matches('\r').to_string();
+                let mut new = old.clone();
+                new.replace_range(p.start - start..p.end - start, &p.link);
+                changes.push(LineChange { line: p.line, old, new });
+            }
+        }
+    }
+    changes.reverse();
+    changes
+}
+
+/// Ask about each proposal on the terminal: yes, no, all remaining or quit.
+fn ask(relative_path: &str, content: &str, proposals: &[Proposal]) -> Result<Vec<bool>, SproutError> {
+    let stdin = std::io::stdin();
+    let mut accepted = vec![false; proposals.len()];
+    let mut all = false;
+    for (i, p) in proposals.iter().enumerate() {
+        if !all {
+            let line = content.lines().nth(p.line - 1).unwrap_or("");
+            eprintln!("{relative_path}:{}:{}: {}", p.line, p.column, search::excerpt(line));
+            eprint!("  link \"{}\" to {} as {}? [y,n,a,q] ", p.text, p.relative_target, p.link);
+            let _ = std::io::stderr().flush();
+            let mut answer = String::new();
+            let read = stdin
+                .lock()
+                .read_line(&mut answer)
+                .map_err(|e| SproutError::ParseError(format!("stdin: {e}")))?;
+            match answer.trim().to_ascii_lowercase().as_str() {
+                _ if read == 0 => break,
+                "y" | "yes" => {}
+                "a" | "all" => all = true,
+                "q" | "quit" => break,
+                _ => continue,
+            }
+        }
+        accepted[i] = true;
+    }
+    Ok(accepted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mentions(name: &str, content: &str, names: &[&str]) -> Vec<String> {
+        find_mentions(Path::new(name), content, names)
+            .into_iter()
+            .map(|(start, end, n)| format!("{}:{}", &content[start..end], names[n]))
+            .collect()
+    }
+
+    #[test]
+    fn test_find_mentions_skips_code_headings_and_links() {
+        let content = "---\ntitle: Rust\n---\n# Rust\n\nRust and rust async.\n```\nRust\n```\n`Rust` [[Rust]] [Rust](rust.md) https://rust.org/rust\n";
+        assert_eq!(mentions("a.md", content, &["rust async", "rust"]), vec!["Rust:rust", "rust async:rust async"]);
+
+        let org = ":PROPERTIES:\n:ID: x\n:END:\n#+title: Rust\n* Rust\n#+begin_src rust\nRust\n#+end_src\nsee Rust, [[file:rust.org][Rust]] ~Rust~\n";
+        assert_eq!(mentions("a.org", org, &["rust"]), vec!["Rust:rust"]);
+    }
+
+    #[test]
+    fn test_find_mentions_skips_indented_code_and_html() {
+        let content = "Rust here.\n\n    let x = Rust::new();\n\n<!-- Rust todo -->\n\nInline <!-- Rust --> and <span>Rust</span> Rust.\n";
+        let found = find_mentions(Path::new("a.md"), content, &["rust"]);
+        let lines: Vec<usize> = found.iter().map(|&(start, _, _)| content[..start].matches('\n').count() + 1).collect();
+        assert_eq!(lines, vec![1, 7, 7]);
+        // Text between HTML tags is still text; the inline comment is not
+        let rest: Vec<&str> = found.iter().map(|&(start, _, _)| &content[start..]).collect();
+        assert!(rest[1].starts_with("Rust</span>"));
+        assert!(rest[2].starts_with("Rust.\n"));
+    }
+
+    #[test]
+    fn test_line_changes() {
+        let content = "a Rust b Rust\nc\n";
+        let proposal = |start: usize, link: &str| Proposal {
+            line: 1,
+            column: start + 1,
+            start,
+            end: start + 4,
+            text: "Rust".into(),
+            relative_target: "rust.md".into(),
+            link: link.into(),
+            applied: false,
+        };
+        let proposals = [proposal(2, "[[Rust]]"), proposal(9, "[[rust|Rust]]")];
+        let changes = line_changes(content, &proposals, &[true, true]);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].new, "a [[Rust]] b [[rust|Rust]]");
+        assert_eq!(line_changes(content, &proposals, &[false, true])[0].new, "a Rust b [[rust|Rust]]");
+    }
+}
@@ -0,0 +1,47 @@
+use std::path::Path;
+
+use crate::cache::{self, FrontmatterCache};
+use crate::cli::{CacheAction, OutputFormat};
+use crate::error::SproutError;
+use crate::note::{self, ScanOptions};
+use crate::output;
+
+pub fn run(
+    action: &CacheAction,
+    vault: &Path,
+    scan_options: &ScanOptions,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+
+    match action {
+        CacheAction::Stats => {
+            let stats = FrontmatterCache::load(&vault_canonical).stats();
+            output::format_cache_stats(&stats, format);
+        }
+        CacheAction::Clear => {
+            let removed = clear(&vault_canonical)?;
+            let path = cache::cache_path(&vault_canonical);
+            output::format_cache_clear(
+                &vault_canonical.to_string_lossy(),
+                path.as_ref().map(|p| p.to_string_lossy()).as_deref(),
+                removed,
+                format,
+            );
+        }
+        CacheAction::Rebuild => {
+            clear(&vault_canonical)?;
+            note::scan_vault_metadata(&vault_canonical, scan_options)
+                .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+            let stats = FrontmatterCache::load(&vault_canonical).stats();
+            output::format_cache_stats(&stats, format);
+        }
+    }
+    Ok(())
+}
+
+fn clear(vault: &Path) -> Result<bool, SproutError> {
+    cache::clear(vault)
+        .map_err(|e| SproutError::CacheError(format!("failed to remove cache: {e}")))
+}
@@ -0,0 +1,100 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::cli::{CheckAction, OutputFormat};
+use crate::error::SproutError;
+use crate::frontmatter::NoteFormat;
+use crate::index::{LinkTarget, VaultIndex};
+use crate::links;
+use crate::note::ScanOptions;
+use crate::output;
+
+/// What a broken link points to that does not exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Missing {
+    Note,
+    Heading,
+    Block,
+}
+
+impl Missing {
+    pub fn code(self) -> &'static str {
+        match self {
+            Missing::Note => "missing_note",
+            Missing::Heading => "missing_heading",
+            Missing::Block => "missing_block",
+        }
+    }
+}
+
+pub struct BrokenLink {
+    pub path: PathBuf,
+    pub relative_path: String,
+    /// 1-based line and character column of the link
+    pub line: usize,
+    pub column: usize,
+    pub target: String,
+    pub missing: Missing,
+}
+
+pub fn run(
+    action: &CheckAction,
+    vault: &Path,
+    scan_options: &ScanOptions,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    match action {
+        CheckAction::Links => {
+            let index = VaultIndex::build(vault, scan_options, Local::now().date_naive())?;
+            let mut notes = index.notes();
+            notes.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+            let mut broken = Vec::new();
+            for n in &notes {
+                // Cached targets tell which notes are worth reading for positions
+                if n.content.links.iter().any(|t| is_broken(&index.resolve(&n.path, t))) {
+                    broken.extend(broken_links(&index, &n.path, &n.relative_path));
+                }
+            }
+            output::format_check_links(&broken, notes.len(), format);
+            if broken.is_empty() {
+                Ok(())
+            } else {
+                Err(SproutError::BrokenLinks(broken.len()))
+            }
+        }
+    }
+}
+
+fn is_broken(target: &LinkTarget) -> bool {
+    matches!(target, LinkTarget::Missing | LinkTarget::MissingAnchor(_))
+}
+
+/// Links in the note at `path` to missing notes, headings or block ids.
+fn broken_links(index: &VaultIndex, path: &Path, relative_path: &str) -> Vec<BrokenLink> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    links::find_links(NoteFormat::from_path(path), &content)
+        .into_iter()
+        .filter_map(|link| {
+            let missing = match index.resolve(path, &link.target) {
+                LinkTarget::Missing => Missing::Note,
+                LinkTarget::MissingAnchor(_) if link.anchor().is_some_and(|a| a.starts_with('^')) => Missing::Block,
+                LinkTarget::MissingAnchor(_) => Missing::Heading,
+                _ => return None,
+            };
+            let before = &content[..link.start];
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Some(BrokenLink {
+                path: path.to_path_buf(),
+                relative_path: relative_path.to_string(),
+                line: before.matches('\n').count() + 1,
+                column: before[line_start..].chars().count() + 1,
+                target: link.target,
+                missing,
+            })
+        })
+        .collect()
+}
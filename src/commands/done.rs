@@ -1,15 +1,40 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use chrono::Local;
+use chrono::{Local, NaiveDate};
 
+use crate::bulk::{self, Change};
 use crate::cli::{OutputFormat, Rating};
 use crate::config::Config;
 use crate::error::SproutError;
-use crate::frontmatter::write_back;
+use crate::frontmatter::{parse_note_at, ParsedNote};
+use crate::history::{self, HistoryEvent, ReviewRecord};
+use crate::index::VaultIndex;
 use crate::links;
 use crate::note;
-use crate::output;
-use crate::srs;
+use crate::output::{self, BulkEntry};
+use crate::query::Query;
+use crate::srs::{self, SrsOutput};
+
+/// A review computed but not yet written. `next_review` is decided later so
+/// that bulk runs can load-balance notes against each other.
+struct PreparedReview {
+    path: PathBuf,
+    relative_path: String,
+    original: String,
+    parsed: ParsedNote,
+    maturity: String,
+    interval: u32,
+    ease: f64,
+    next_review: NaiveDate,
+    link_count: usize,
+    srs: SrsOutput,
+}
+
+pub struct ReviewOutcome {
+    relative_path: String,
+    record: ReviewRecord,
+    next_review: NaiveDate,
+}
 
 pub fn run(
     file: &Path,
@@ -18,73 +43,202 @@ pub fn run(
     config: &Config,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
+    run_with(file, rating, vault, config, None, format)
+}
+
+/// Like `run`; `index`, when given, stands in for a vault scan for link
+/// resolution and load balancing (`sprout serve` passes its own).
+pub fn run_with(
+    file: &Path,
+    rating: &Rating,
+    vault: &Path,
+    config: &Config,
+    index: Option<&VaultIndex>,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let today = Local::now().date_naive();
+    let built;
+    let index = match index {
+        Some(index) => index,
+        None => {
+            built = VaultIndex::build(vault, &config.scan_options(), today)?;
+            &built
+        }
+    };
+    let prepared = prepare(file, rating, vault, config, index, today)?;
+
+    // Determine final next_review with optional load balancing
+    let final_next_review = if config.load_balance() {
+        let existing_dates: Vec<NaiveDate> = index.notes().iter().filter_map(|n| n.sprout.next_review).collect();
+        srs::load_balance(prepared.srs.new_interval, today, &existing_dates)
+    } else {
+        prepared.srs.next_review
+    };
+
+    let change = finish(prepared, rating, final_next_review, today);
+    note::write_note(&change.path, &change.content)?;
+    history::append(&HistoryEvent::Review(change.outcome.record.clone()));
+
+    let record = &change.outcome.record;
+    output::format_done(
+        &change.path.to_string_lossy(),
+        &record.maturity,
+        today,
+        record.new_interval,
+        final_next_review,
+        record.new_ease,
+        format,
+    );
+
+    Ok(())
+}
+
+/// Review many notes at once. SRS calculation runs in parallel; load balancing
+/// is applied in target order so each note sees the dates assigned before it.
+pub fn run_bulk(
+    files: &[PathBuf],
+    query: Option<&str>,
+    rating: &Rating,
+    atomic: bool,
+    vault: &Path,
+    config: &Config,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let query = query.map(Query::parse).transpose()?;
+    let today = Local::now().date_naive();
+    let targets = bulk::resolve_targets(files, query.as_ref(), vault, &config.scan_options(), today)?;
+
+    let index = VaultIndex::build(vault, &config.scan_options(), today)?;
+    let (prepared, failed) = bulk::plan_all(&targets, |file| prepare(file, rating, vault, config, &index, today));
+
+    let mut existing_dates: Vec<NaiveDate> = if config.load_balance() {
+        index.notes().iter().filter_map(|n| n.sprout.next_review).collect()
+    } else {
+        Vec::new()
+    };
+    let changes: Vec<_> = prepared
+        .into_iter()
+        .map(|p| {
+            let next_review = if config.load_balance() {
+                let d = srs::load_balance(p.srs.new_interval, today, &existing_dates);
+                existing_dates.push(d);
+                d
+            } else {
+                p.srs.next_review
+            };
+            finish(p, rating, next_review, today)
+        })
+        .collect();
+
+    let report = bulk::apply(changes, failed, atomic);
+    for c in &report.succeeded {
+        history::append(&HistoryEvent::Review(c.outcome.record.clone()));
+    }
+
+    let results: Vec<BulkEntry> = report
+        .succeeded
+        .iter()
+        .map(|c| {
+            let o = &c.outcome;
+            (
+                output::done_json(
+                    &c.path.to_string_lossy(),
+                    &o.record.maturity,
+                    today,
+                    o.record.new_interval,
+                    o.next_review,
+                    o.record.new_ease,
+                ),
+                format!(
+                    "Reviewed: {} [{}] → interval {}d, next: {}",
+                    o.relative_path, o.record.maturity, o.record.new_interval, o.next_review
+                ),
+            )
+        })
+        .collect();
+    output::format_bulk(report.applied, &results, &report.failed, format);
+    report.status()
+}
+
+fn prepare(
+    file: &Path,
+    rating: &Rating,
+    vault: &Path,
+    config: &Config,
+    index: &VaultIndex,
+    today: NaiveDate,
+) -> Result<PreparedReview, SproutError> {
     if !file.exists() {
         return Err(SproutError::FileNotFound(file.display().to_string()));
     }
 
     note::ensure_in_vault(file, vault)?;
 
-    let parsed = note::read_note(file)?;
+    let original = std::fs::read_to_string(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+    let parsed = parse_note_at(file, &original);
 
     // Validate required fields
-    let maturity = parsed
-        .sprout
-        .maturity
-        .as_ref()
-        .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
-    let ease = parsed
-        .sprout
-        .ease
-        .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
-    let interval = parsed
-        .sprout
-        .review_interval
-        .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
-    let next_review = parsed
-        .sprout
-        .next_review
-        .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
-
-    let raw_yaml = parsed
-        .frontmatter_raw
-        .as_ref()
-        .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
+    let missing = || SproutError::NoFrontmatter(file.display().to_string());
+    let maturity = parsed.sprout.maturity.clone().ok_or_else(missing)?;
+    let ease = parsed.sprout.ease.ok_or_else(missing)?;
+    let interval = parsed.sprout.review_interval.ok_or_else(missing)?;
+    let next_review = parsed.sprout.next_review.ok_or_else(missing)?;
+    if parsed.frontmatter_raw.is_none() {
+        return Err(missing());
+    }
 
-    let today = Local::now().date_naive();
-    let link_count = links::count_links(&parsed.body);
+    let path = std::fs::canonicalize(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+    // Links through aliases count once per note
+    let link_count = links::count_links(parsed.format, &parsed.body, |t| index.link_key(&path, t));
 
-    let srs_output = srs::calculate(&srs::SrsInput {
+    let srs = srs::calculate_with(
+        &srs::SrsInput {
+            interval,
+            ease,
+            next_review,
+            today,
+            rating: rating.clone(),
+            link_count,
+            link_weight: config.link_weight(),
+            max_interval: config.max_interval(),
+        },
+        &config.srs_params(),
+    );
+
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+    let relative_path = path
+        .strip_prefix(&vault_canonical)
+        .unwrap_or(&path)
+        .to_string_lossy()
+        .to_string();
+
+    Ok(PreparedReview {
+        path,
+        relative_path,
+        original,
+        parsed,
+        maturity,
         interval,
         ease,
         next_review,
-        today,
-        rating: rating.clone(),
         link_count,
-        link_weight: config.link_weight(),
-        max_interval: config.max_interval(),
-    });
-
-    // Determine final next_review with optional load balancing
-    let final_next_review = if config.load_balance() {
-        let vault_canonical = std::fs::canonicalize(vault)
-            .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
-        let exclude_dirs = config.exclude_dirs();
-        let all_notes = note::scan_vault_metadata(&vault_canonical, &exclude_dirs)
-            .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
-
-        let existing_dates: Vec<_> = all_notes
-            .iter()
-            .filter_map(|n| n.sprout.next_review)
-            .collect();
-
-        srs::load_balance(srs_output.new_interval, today, &existing_dates)
-    } else {
-        srs_output.next_review
-    };
+        srs,
+    })
+}
 
+/// Build the updated note content once the final `next_review` is known.
+fn finish(
+    p: PreparedReview,
+    rating: &Rating,
+    final_next_review: NaiveDate,
+    today: NaiveDate,
+) -> Change<ReviewOutcome> {
     // Write back updated frontmatter
-    let ease_str = format!("{:.2}", srs_output.new_ease);
-    let interval_str = srs_output.new_interval.to_string();
+    let ease_str = format!("{:.2}", p.srs.new_ease);
+    let interval_str = p.srs.new_interval.to_string();
     let next_review_str = final_next_review.to_string();
     let today_str = today.to_string();
 
@@ -95,21 +249,28 @@ pub fn run(
         ("ease", &ease_str),
     ];
 
-    let content = write_back(raw_yaml, &parsed.body, &updates);
-    note::write_note(file, &content)?;
-
-    let file_canonical = std::fs::canonicalize(file)
-        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+    let raw_yaml = p.parsed.frontmatter_raw.as_deref().unwrap_or_default();
+    let content = p.parsed.format.write_back(raw_yaml, &p.parsed.body, &updates);
 
-    output::format_done(
-        &file_canonical.to_string_lossy(),
-        maturity,
-        today,
-        srs_output.new_interval,
-        final_next_review,
-        srs_output.new_ease,
-        format,
-    );
-
-    Ok(())
+    Change {
+        path: p.path.clone(),
+        original: p.original,
+        content,
+        outcome: ReviewOutcome {
+            relative_path: p.relative_path,
+            record: ReviewRecord {
+                date: today,
+                path: p.path,
+                rating: rating.clone(),
+                maturity: p.maturity,
+                interval: p.interval,
+                ease: p.ease,
+                delayed: (today - p.next_review).num_days().max(0),
+                link_count: p.link_count,
+                new_interval: p.srs.new_interval,
+                new_ease: p.srs.new_ease,
+            },
+            next_review: final_next_review,
+        },
+    }
 }
@@ -1,13 +1,15 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use chrono::{Local, NaiveDate};
 
 use crate::cli::OutputFormat;
+use globset::Glob;
 use crate::config::Config;
 use crate::error::SproutError;
-use crate::frontmatter::{append_field, build_new_frontmatter, has_field, write_back};
+use crate::bulk::{self, Change};
+use crate::frontmatter::NoteFormat;
 use crate::note;
-use crate::output;
+use crate::output::{self, BulkEntry};
 
 const SPROUT_FIELDS: &[&str] = &[
     "maturity",
@@ -29,7 +31,27 @@ pub struct InitResult {
 
 /// Core init logic, usable from both `sprout init` and `sprout note` (auto-init).
 pub fn init_note(file: &Path, _vault: &Path, config: &Config) -> Result<InitResult, SproutError> {
-    let parsed = note::read_note(file)?;
+    let change = plan_init(file, config)?;
+    note::write_note(&change.path, &change.content)?;
+
+    if let Some(fields) = &change.outcome.fields_added {
+        // Case C: partial — warn about added fields
+        eprintln!(
+            "warning: missing fields added with defaults: {}",
+            fields.join(", ")
+        );
+    }
+    Ok(change.outcome)
+}
+
+/// Compute the initialized content of a note without writing it.
+fn plan_init(file: &Path, config: &Config) -> Result<Change<InitResult>, SproutError> {
+    let original = std::fs::read_to_string(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+    let format = NoteFormat::from_path(file);
+    let parsed = format.parse(&original);
+    let path = std::fs::canonicalize(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
 
     let today = Local::now().date_naive();
     let tomorrow = today + chrono::Duration::days(1);
@@ -50,23 +72,27 @@ pub fn init_note(file: &Path, _vault: &Path, config: &Config) -> Result<InitResu
                 ("next_review", &tomorrow_str),
                 ("ease", &ease_str),
             ];
-            let content = build_new_frontmatter(&fields, &parsed.body);
-            note::write_note(file, &content)?;
-
-            Ok(InitResult {
-                maturity: "seedling".to_string(),
-                review_interval: 1,
-                next_review: tomorrow,
-                ease: default_ease,
-                created: today,
-                fields_added: None,
+            let content = format.build_new_frontmatter(&fields, &parsed.body);
+
+            Ok(Change {
+                path,
+                original,
+                content,
+                outcome: InitResult {
+                    maturity: "seedling".to_string(),
+                    review_interval: 1,
+                    next_review: tomorrow,
+                    ease: default_ease,
+                    created: today,
+                    fields_added: None,
+                },
             })
         }
         Some(raw_yaml) => {
             // Check which fields exist
             let mut missing: Vec<&str> = Vec::new();
             for &field in SPROUT_FIELDS {
-                if !has_field(raw_yaml, field) {
+                if !format.has_field(raw_yaml, field) {
                     missing.push(field);
                 }
             }
@@ -100,65 +126,67 @@ pub fn init_note(file: &Path, _vault: &Path, config: &Config) -> Result<InitResu
             // Append missing fields
             let mut yaml = raw_yaml.clone();
             for &(field, ref value) in &defaults {
-                yaml = append_field(&yaml, field, value);
+                yaml = format.append_field(&yaml, field, value);
             }
 
-            let content = write_back(&yaml, &parsed.body, &[]);
-            note::write_note(file, &content)?;
+            let content = format.write_back(&yaml, &parsed.body, &[]);
 
-            if all_missing {
+            let outcome = if all_missing {
                 // Case B: frontmatter exists but no sprout fields
-                Ok(InitResult {
+                InitResult {
                     maturity: "seedling".to_string(),
                     review_interval: 1,
                     next_review: tomorrow,
                     ease: default_ease,
                     created: today,
                     fields_added: None,
-                })
+                }
             } else {
-                // Case C: partial — warn about added fields
+                // Case C: partial — the added fields are reported to the caller
                 let field_names: Vec<String> = missing.iter().map(|s| s.to_string()).collect();
-                eprintln!(
-                    "warning: missing fields added with defaults: {}",
-                    field_names.join(", ")
-                );
 
-                let final_maturity = if has_field(raw_yaml, "maturity") {
+                let final_maturity = if format.has_field(raw_yaml, "maturity") {
                     parsed.sprout.maturity.as_deref().unwrap_or("seedling").to_string()
                 } else {
                     "seedling".to_string()
                 };
-                let final_interval = if has_field(raw_yaml, "review_interval") {
+                let final_interval = if format.has_field(raw_yaml, "review_interval") {
                     parsed.sprout.review_interval.unwrap_or(1)
                 } else {
                     1
                 };
-                let final_next_review = if has_field(raw_yaml, "next_review") {
+                let final_next_review = if format.has_field(raw_yaml, "next_review") {
                     parsed.sprout.next_review.unwrap_or(tomorrow)
                 } else {
                     tomorrow
                 };
-                let final_ease = if has_field(raw_yaml, "ease") {
+                let final_ease = if format.has_field(raw_yaml, "ease") {
                     parsed.sprout.ease.unwrap_or(default_ease)
                 } else {
                     default_ease
                 };
-                let final_created = if has_field(raw_yaml, "created") {
+                let final_created = if format.has_field(raw_yaml, "created") {
                     parsed.sprout.created.unwrap_or(today)
                 } else {
                     today
                 };
 
-                Ok(InitResult {
+                InitResult {
                     maturity: final_maturity,
                     review_interval: final_interval,
                     next_review: final_next_review,
                     ease: final_ease,
                     created: final_created,
                     fields_added: Some(field_names),
-                })
-            }
+                }
+            };
+
+            Ok(Change {
+                path,
+                original,
+                content,
+                outcome,
+            })
         }
     }
 }
@@ -201,3 +229,77 @@ pub fn run(
 
     Ok(())
 }
+
+
+/// Initialize many notes at once. `--all` and `--glob` select notes in the
+/// vault that are missing sprout fields; fully initialized notes are skipped.
+pub fn run_bulk(
+    files: &[PathBuf],
+    all: bool,
+    glob: Option<&str>,
+    atomic: bool,
+    vault: &Path,
+    config: &Config,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let matcher = glob
+        .map(|g| {
+            Glob::new(g)
+                .map(|g| g.compile_matcher())
+                .map_err(|e| SproutError::ParseError(format!("glob: {e}")))
+        })
+        .transpose()?;
+
+    let mut targets = bulk::expand_stdin(files)?;
+    if all || matcher.is_some() {
+        let notes = bulk::scan(vault, &config.scan_options())?;
+        targets.extend(
+            notes
+                .into_iter()
+                .filter(|n| !n.sprout.missing_fields().is_empty())
+                .filter(|n| matcher.as_ref().is_none_or(|m| m.is_match(&n.relative_path)))
+                .map(|n| n.path),
+        );
+    }
+    let targets = bulk::dedup(targets);
+
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+    let (changes, failed) = bulk::plan_all(&targets, |file| {
+        if !file.exists() {
+            return Err(SproutError::FileNotFound(file.display().to_string()));
+        }
+        note::ensure_in_vault(file, vault)?;
+        plan_init(file, config)
+    });
+    let report = bulk::apply(changes, failed, atomic);
+
+    let results: Vec<BulkEntry> = report
+        .succeeded
+        .iter()
+        .map(|c| {
+            let r = &c.outcome;
+            let relative_path = c
+                .path
+                .strip_prefix(&vault_canonical)
+                .unwrap_or(&c.path)
+                .to_string_lossy()
+                .to_string();
+            (
+                output::init_json(
+                    &c.path.to_string_lossy(),
+                    &relative_path,
+                    &r.maturity,
+                    r.review_interval,
+                    r.next_review,
+                    r.ease,
+                    r.created,
+                    r.fields_added.as_deref(),
+                ),
+                format!("Initialized: {relative_path} [{}]", r.maturity),
+            )
+        })
+        .collect();
+    output::format_bulk(report.applied, &results, &report.failed, format);
+    report.status()
+}
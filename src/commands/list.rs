@@ -1,20 +1,38 @@
 use std::path::Path;
 
-use crate::cli::{Maturity, OutputFormat};
+use chrono::Local;
+
+use crate::cli::{Maturity, OutputFormat, SortArgs, SortKey};
 use crate::error::SproutError;
-use crate::note;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
 use crate::output;
+use crate::query::{self, Query};
+use crate::sort;
 
 pub fn run(
     vault: &Path,
     maturity_filter: Option<&Maturity>,
-    exclude_dirs: &[String],
+    scan_options: &ScanOptions,
+    query: Option<&str>,
+    sort_args: &SortArgs,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
-    let notes = note::scan_vault_metadata(vault, exclude_dirs)
+    // Parse before scanning so syntax errors are reported immediately
+    let query = query.map(Query::parse).transpose()?;
+    let notes = note::scan_vault_metadata(vault, scan_options)
         .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+    run_with(notes, maturity_filter, query.as_ref(), sort_args, format)
+}
 
-    let mut tracked: Vec<_> = notes
+/// Output the tracked notes among `notes`, which `sprout serve` takes from its index.
+pub fn run_with(
+    notes: Vec<NoteMetaInfo>,
+    maturity_filter: Option<&Maturity>,
+    query: Option<&Query>,
+    sort_args: &SortArgs,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let tracked: Vec<_> = notes
         .into_iter()
         .filter(|n| n.sprout.maturity.is_some())
         .filter(|n| {
@@ -26,8 +44,11 @@ pub fn run(
         })
         .collect();
 
-    // Sort by relative_path alphabetical ascending
-    tracked.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+    let today = Local::now().date_naive();
+    let tracked = query::filter(tracked, query, today);
+
+    // Default: relative_path alphabetical ascending
+    let tracked = sort::sort_and_paginate(tracked, sort_args, SortKey::Path, today);
 
     let entries: Vec<_> = tracked
         .iter()
@@ -0,0 +1,621 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::Duration;
+
+use chrono::Local;
+use serde_json::{json, Value};
+
+use crate::cli::{Maturity, OutputFormat, Rating};
+use crate::commands::watch::VaultWatcher;
+use crate::commands::{done, init, promote};
+use crate::config::Config;
+use crate::error::SproutError;
+use crate::frontmatter::{self, NoteFormat};
+use crate::index::{self, LinkTarget, VaultIndex};
+use crate::links;
+use crate::output;
+
+/// How often vault changes are picked up while the editor is idle.
+const POLL: Duration = Duration::from_millis(500);
+
+// JSON-RPC / LSP error codes
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const REQUEST_FAILED: i64 = -32803;
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+const COMPLETION_KIND_FILE: u8 = 17;
+const MESSAGE_INFO: u8 = 3;
+
+const COMMANDS: [&str; 3] = ["sprout.done", "sprout.promote", "sprout.init"];
+
+type RpcResult = Result<Value, (i64, String)>;
+
+fn invalid_params() -> (i64, String) {
+    (INVALID_PARAMS, "invalid params".to_string())
+}
+
+/// Read one `Content-Length` framed message; `None` at end of input or on a
+/// malformed header. A body that is not JSON is returned as `Null`.
+fn read_message(reader: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let mut body = vec![0; length?];
+    reader.read_exact(&mut body).ok()?;
+    Some(serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn write_message(out: &mut impl Write, message: &Value) -> std::io::Result<()> {
+    let body = serde_json::to_string(message).unwrap();
+    write!(out, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    out.flush()
+}
+
+/// LSP position (line, UTF-16 code unit) of a byte offset.
+fn position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+fn range(text: &str, start: usize, end: usize) -> Value {
+    json!({ "start": position(text, start), "end": position(text, end) })
+}
+
+/// Byte offset of an LSP position, clamped to the line and the text.
+fn offset(text: &str, line: usize, character: usize) -> usize {
+    let mut start = 0;
+    for _ in 0..line {
+        match text[start..].find('\n') {
+            Some(i) => start += i + 1,
+            None => return text.len(),
+        }
+    }
+    let mut units = 0;
+    for (i, c) in text[start..].char_indices() {
+        if units >= character || c == '\n' {
+            return start + i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+fn line_range(text: &str, line: usize) -> Value {
+    let start = offset(text, line, 0);
+    let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+    range(text, start, end)
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = PathBuf::from(index::percent_decode(uri.strip_prefix("file://")?));
+    // The index holds canonical paths
+    Some(std::fs::canonicalize(&path).unwrap_or(path))
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for b in path.to_string_lossy().bytes() {
+        if b.is_ascii_alphanumeric() || b"/-._~".contains(&b) {
+            uri.push(b as char);
+        } else {
+            uri.push_str(&format!("%{b:02X}"));
+        }
+    }
+    uri
+}
+
+fn command_action(title: String, command: &str, arguments: Value) -> Value {
+    json!({
+        "title": title,
+        "kind": "source",
+        "command": { "title": title, "command": command, "arguments": arguments },
+    })
+}
+
+struct Server {
+    index: VaultIndex,
+    watcher: VaultWatcher,
+    config: Config,
+    /// Text of the documents open in the editor, by URI
+    documents: HashMap<String, String>,
+    /// Whether the client accepts `workspace/applyEdit`
+    apply_edit: bool,
+    shutdown: bool,
+    next_id: u64,
+    /// Messages to send once the current one is handled
+    outbox: Vec<Value>,
+}
+
+impl Server {
+    /// Apply vault changes seen by the watcher; diagnostics of open documents
+    /// may depend on them.
+    fn refresh(&mut self) {
+        let mut changes = self.watcher.pending().apply(&mut self.index);
+        changes.extend(self.index.tick(Local::now().date_naive()));
+        if !changes.is_empty() {
+            self.publish_all();
+        }
+    }
+
+    /// Handle one client message. Returns true on `exit`.
+    fn handle(&mut self, message: Value) -> bool {
+        // Responses to our own requests (`workspace/applyEdit`) need no action
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            return false;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let Some(id) = message.get("id").cloned() else {
+            return self.notify(method, &params);
+        };
+        let response = match self.request(method, &params) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => {
+                json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+            }
+        };
+        self.outbox.push(response);
+        false
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> RpcResult {
+        match method {
+            "initialize" => {
+                self.apply_edit = params["capabilities"]["workspace"]["applyEdit"] == true;
+                Ok(json!({
+                    "capabilities": {
+                        "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                        "hoverProvider": true,
+                        "definitionProvider": true,
+                        "completionProvider": { "triggerCharacters": ["["] },
+                        "codeActionProvider": true,
+                        "executeCommandProvider": { "commands": COMMANDS },
+                    },
+                    "serverInfo": { "name": "sprout", "version": env!("CARGO_PKG_VERSION") },
+                }))
+            }
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => self.hover(params),
+            "textDocument/definition" => self.definition(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/codeAction" => self.code_actions(params),
+            "workspace/executeCommand" => self.execute(params),
+            _ => Err((METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        }
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> bool {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default().to_string();
+        match method {
+            "exit" => return true,
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.clone(), text.to_string());
+                self.publish(&uri);
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes.and_then(|c| c.last()).and_then(|c| c["text"].as_str()) {
+                    self.documents.insert(uri.clone(), text.to_string());
+                    self.publish(&uri);
+                }
+            }
+            "textDocument/didSave" => {
+                // Don't wait for the watcher: other notes' links may resolve differently now
+                if let Some(path) = uri_to_path(&uri) {
+                    self.index.apply(&[path], &[]);
+                }
+                self.publish_all();
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(&uri);
+                self.outbox.push(json!({
+                    "jsonrpc": "2.0",
+                    "method": "textDocument/publishDiagnostics",
+                    "params": { "uri": uri, "diagnostics": [] },
+                }));
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// The editor's text of a document, or the file's if it is not open.
+    fn text(&self, uri: &str) -> Option<String> {
+        match self.documents.get(uri) {
+            Some(text) => Some(text.clone()),
+            None => std::fs::read_to_string(uri_to_path(uri)?).ok(),
+        }
+    }
+
+    /// Document path, text and cursor offset of a text document position request.
+    fn locate(&self, params: &Value) -> Result<(PathBuf, String, usize), (i64, String)> {
+        let uri = params["textDocument"]["uri"].as_str().ok_or_else(invalid_params)?;
+        let path = uri_to_path(uri).ok_or_else(invalid_params)?;
+        let text = self.text(uri).ok_or_else(|| (REQUEST_FAILED, format!("{uri}: cannot read document")))?;
+        let line = params["position"]["line"].as_u64().ok_or_else(invalid_params)?;
+        let character = params["position"]["character"].as_u64().ok_or_else(invalid_params)?;
+        let offset = offset(&text, line as usize, character as usize);
+        Ok((path, text, offset))
+    }
+
+    fn link_at(path: &Path, text: &str, offset: usize) -> Option<links::LinkSpan> {
+        links::find_links(NoteFormat::from_path(path), text).into_iter().find(|l| l.start <= offset && offset < l.end)
+    }
+
+    fn hover(&self, params: &Value) -> RpcResult {
+        let (path, text, offset) = self.locate(params)?;
+        let Some(link) = Self::link_at(&path, &text, offset) else {
+            return Ok(Value::Null);
+        };
+        let contents = match self.index.resolve(&path, &link.target) {
+            LinkTarget::Note(target) => self.describe(&target),
+            LinkTarget::File(target) => format!("`{}`", self.relative(&target)),
+            LinkTarget::External => return Ok(Value::Null),
+            LinkTarget::Missing => format!("No note matches `{}`", link.target),
+            LinkTarget::MissingAnchor(target) => {
+                format!("{}\n\nNo section matches `#{}`", self.describe(&target), link.anchor().unwrap_or_default())
+            }
+        };
+        Ok(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": range(&text, link.start, link.end),
+        }))
+    }
+
+    /// Hover text for a note: title, path and review state.
+    fn describe(&self, path: &Path) -> String {
+        let Some(note) = self.index.note(path) else {
+            return format!("`{}`", self.relative(path));
+        };
+        let title = note.content.title.as_deref().unwrap_or(&note.relative_path);
+        let mut parts = vec![format!("**{title}**"), format!("`{}`", note.relative_path)];
+        let sprout = &note.sprout;
+        match &sprout.maturity {
+            None => parts.push("Not tracked by sprout".to_string()),
+            Some(maturity) => {
+                let mut state = maturity.clone();
+                if let Some(next) = sprout.next_review {
+                    let today = Local::now().date_naive();
+                    let when = if next < today {
+                        " (overdue)"
+                    } else if next == today {
+                        " (due today)"
+                    } else {
+                        ""
+                    };
+                    state.push_str(&format!(" · next review {next}{when}"));
+                }
+                if let Some(interval) = sprout.review_interval {
+                    state.push_str(&format!(" · interval {interval}d"));
+                }
+                if let Some(ease) = sprout.ease {
+                    state.push_str(&format!(" · ease {ease:.2}"));
+                }
+                parts.push(state);
+            }
+        }
+        parts.join("\n\n")
+    }
+
+    fn relative(&self, path: &Path) -> String {
+        path.strip_prefix(self.index.vault()).unwrap_or(path).to_string_lossy().to_string()
+    }
+
+    fn definition(&self, params: &Value) -> RpcResult {
+        let (path, text, offset) = self.locate(params)?;
+        let Some(link) = Self::link_at(&path, &text, offset) else {
+            return Ok(Value::Null);
+        };
+        match self.index.resolve(&path, &link.target) {
+            LinkTarget::Note(target) | LinkTarget::File(target) | LinkTarget::MissingAnchor(target) => Ok(json!({
+                "uri": path_to_uri(&target),
+                "range": { "start": { "line": 0, "character": 0 }, "end": { "line": 0, "character": 0 } },
+            })),
+            LinkTarget::External | LinkTarget::Missing => Ok(Value::Null),
+        }
+    }
+
+    /// Note names after an unclosed `[[` on the cursor's line.
+    fn completion(&self, params: &Value) -> RpcResult {
+        let (_, text, offset) = self.locate(params)?;
+        let line_start = text[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let before = &text[line_start..offset];
+        let Some(open) = before.rfind("[[") else {
+            return Ok(Value::Null);
+        };
+        let typed = &before[open + 2..];
+        // Past the target: `[[a]] ...` or the display text of `[[a|b`
+        if typed.contains("]]") || typed.contains('|') {
+            return Ok(Value::Null);
+        }
+        let edit_range = range(&text, line_start + open + 2, offset);
+
+        let mut notes = self.index.notes();
+        notes.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        let items: Vec<Value> = notes
+            .iter()
+            .filter_map(|note| {
+                let name = self.index.link_name(&note.path)?;
+                let label = note.content.title.clone().unwrap_or_else(|| name.clone());
+                let detail = match &note.sprout.maturity {
+                    Some(maturity) => format!("{} · {maturity}", note.relative_path),
+                    None => note.relative_path.clone(),
+                };
+                Some(json!({
+                    "label": label,
+                    "kind": COMPLETION_KIND_FILE,
+                    "detail": detail,
+                    "filterText": format!("{label} {name}"),
+                    "textEdit": { "range": edit_range, "newText": name },
+                }))
+            })
+            .collect();
+        Ok(json!({ "isIncomplete": false, "items": items }))
+    }
+
+    /// done / promote for tracked notes, init for notes missing sprout fields.
+    fn code_actions(&self, params: &Value) -> RpcResult {
+        let uri = params["textDocument"]["uri"].as_str().ok_or_else(invalid_params)?;
+        let path = uri_to_path(uri).ok_or_else(invalid_params)?;
+        if !path.starts_with(self.index.vault()) || !self.config.scan_options().is_note(&path) {
+            return Ok(json!([]));
+        }
+        if let Some(only) = params["context"]["only"].as_array() {
+            if !only.iter().filter_map(Value::as_str).any(|kind| "source".starts_with(kind)) {
+                return Ok(json!([]));
+            }
+        }
+        let text = self.text(uri).unwrap_or_default();
+        let sprout = frontmatter::parse_note_at(&path, &text).sprout;
+
+        let mut actions = Vec::new();
+        if sprout.maturity.is_some()
+            && sprout.ease.is_some()
+            && sprout.review_interval.is_some()
+            && sprout.next_review.is_some()
+        {
+            for rating in ["hard", "good", "easy"] {
+                actions.push(command_action(format!("sprout: reviewed ({rating})"), "sprout.done", json!([uri, rating])));
+            }
+        }
+        if let Some(current) = &sprout.maturity {
+            for maturity in ["seedling", "budding", "evergreen"].into_iter().filter(|m| m != current) {
+                actions.push(command_action(format!("sprout: promote to {maturity}"), "sprout.promote", json!([uri, maturity])));
+            }
+        }
+        if !sprout.missing_fields().is_empty() {
+            let title = if sprout.maturity.is_some() {
+                "sprout: add missing fields (init)"
+            } else {
+                "sprout: start tracking (init)"
+            };
+            actions.push(command_action(title.to_string(), "sprout.init", json!([uri])));
+        }
+        Ok(Value::Array(actions))
+    }
+
+    /// Run done / promote / init on a note as the CLI would, then bring the
+    /// editor's buffer in line with the rewritten file.
+    fn execute(&mut self, params: &Value) -> RpcResult {
+        let command = params["command"].as_str().ok_or_else(invalid_params)?;
+        let arguments = params["arguments"].as_array().cloned().unwrap_or_default();
+        let uri = arguments.first().and_then(Value::as_str).ok_or_else(invalid_params)?.to_string();
+        let path = uri_to_path(&uri).ok_or_else(invalid_params)?;
+        let argument = arguments.get(1).cloned().unwrap_or(Value::Null);
+
+        // The commands rewrite the file on disk, which must match the buffer
+        let before = std::fs::read_to_string(&path).ok();
+        if let Some(text) = self.documents.get(&uri) {
+            if before.as_ref() != Some(text) {
+                return Err((REQUEST_FAILED, "save the note before running sprout commands".to_string()));
+            }
+        }
+
+        let vault = self.index.vault().to_path_buf();
+        let config = &self.config;
+        let format = &OutputFormat::Json;
+        let (result, output) = match command {
+            "sprout.done" => {
+                let rating: Rating = serde_json::from_value(argument).map_err(|_| invalid_params())?;
+                let index = &self.index;
+                output::capture(|| done::run_with(&path, &rating, &vault, config, Some(index), format))
+            }
+            "sprout.promote" => {
+                let maturity: Maturity = serde_json::from_value(argument).map_err(|_| invalid_params())?;
+                output::capture(|| promote::run(&path, &maturity, &vault, format))
+            }
+            "sprout.init" => output::capture(|| init::run(&path, &vault, config, format)),
+            _ => return Err((INVALID_PARAMS, format!("unknown command: {command}"))),
+        };
+        result.map_err(|e| (REQUEST_FAILED, format!("{}: {e}", e.error_code())))?;
+        let output = output.into_iter().next().unwrap_or(Value::Null);
+        self.index.apply(std::slice::from_ref(&path), &[]);
+
+        let relative = self.relative(&path);
+        let message = match command {
+            "sprout.done" => format!("Reviewed {relative}: next review {}", output["next_review"].as_str().unwrap_or("?")),
+            "sprout.promote" => format!("Promoted {relative} to {}", output["new_maturity"].as_str().unwrap_or("?")),
+            _ => format!("Tracking {relative} as {}", output["maturity"].as_str().unwrap_or("?")),
+        };
+        self.outbox.push(json!({
+            "jsonrpc": "2.0",
+            "method": "window/showMessage",
+            "params": { "type": MESSAGE_INFO, "message": message },
+        }));
+
+        if let (true, Some(old), Ok(new)) = (self.apply_edit, self.documents.get(&uri), std::fs::read_to_string(&path)) {
+            let edit = json!({ "changes": { &uri: [{ "range": range(old, 0, old.len()), "newText": new }] } });
+            self.next_id += 1;
+            self.outbox.push(json!({
+                "jsonrpc": "2.0",
+                "id": self.next_id,
+                "method": "workspace/applyEdit",
+                "params": { "label": message, "edit": edit },
+            }));
+        }
+        self.publish_all();
+        Ok(output)
+    }
+
+    fn publish_all(&mut self) {
+        let mut uris: Vec<String> = self.documents.keys().cloned().collect();
+        uris.sort();
+        for uri in uris {
+            self.publish(&uri);
+        }
+    }
+
+    fn publish(&mut self, uri: &str) {
+        let diagnostics = self.diagnostics(uri);
+        self.outbox.push(json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }));
+    }
+
+    /// Broken links and unreadable or incomplete sprout fields.
+    fn diagnostics(&self, uri: &str) -> Vec<Value> {
+        let (Some(path), Some(text)) = (uri_to_path(uri), self.documents.get(uri)) else {
+            return Vec::new();
+        };
+        let diagnostic = |range: Value, severity: u8, code: &str, message: String| {
+            json!({ "range": range, "severity": severity, "source": "sprout", "code": code, "message": message })
+        };
+
+        let format = NoteFormat::from_path(&path);
+        let mut diagnostics = Vec::new();
+        for link in links::find_links(format, text) {
+            match self.index.resolve(&path, &link.target) {
+                LinkTarget::Missing => {
+                    let message = format!("broken link: no note matches `{}`", link.target);
+                    diagnostics.push(diagnostic(range(text, link.start, link.end), SEVERITY_WARNING, "broken_link", message));
+                }
+                LinkTarget::MissingAnchor(_) => {
+                    let message = format!("stale anchor: no section matches `#{}`", link.anchor().unwrap_or_default());
+                    diagnostics.push(diagnostic(range(text, link.start, link.end), SEVERITY_WARNING, "stale_anchor", message));
+                }
+                _ => {}
+            }
+        }
+
+        let errors = format.check_fields(text);
+        for error in &errors {
+            diagnostics.push(diagnostic(line_range(text, error.line), SEVERITY_ERROR, "invalid_field", error.message.clone()));
+        }
+        if errors.is_empty() {
+            let sprout = format.parse(text).sprout;
+            let missing = sprout.missing_fields();
+            if sprout.maturity.is_some() && !missing.is_empty() {
+                let message = format!("missing sprout fields: {}", missing.join(", "));
+                diagnostics.push(diagnostic(line_range(text, 0), SEVERITY_WARNING, "missing_fields", message));
+            }
+        }
+        diagnostics
+    }
+}
+
+/// Speak the Language Server Protocol on stdin/stdout until the client exits.
+pub fn run(vault: &Path, config: &Config) -> Result<(), SproutError> {
+    let index = VaultIndex::build(vault, &config.scan_options(), Local::now().date_naive())?;
+    let watcher = VaultWatcher::start(index.vault())?;
+    let mut server = Server {
+        index,
+        watcher,
+        config: config.clone(),
+        documents: HashMap::new(),
+        apply_edit: false,
+        shutdown: false,
+        next_id: 0,
+        outbox: Vec::new(),
+    };
+
+    // Read on a separate thread so that vault changes are noticed while
+    // the editor is quiet
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(std::io::stdin());
+        while let Some(message) = read_message(&mut reader) {
+            if tx.send(message).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut stdout = std::io::stdout();
+    loop {
+        let message = match rx.recv_timeout(POLL) {
+            Ok(message) => Some(message),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        };
+        server.refresh();
+        let exit = message.is_some_and(|m| server.handle(m));
+        for message in server.outbox.drain(..) {
+            write_message(&mut stdout, &message).map_err(|e| SproutError::LspError(e.to_string()))?;
+        }
+        if exit {
+            return if server.shutdown {
+                Ok(())
+            } else {
+                Err(SproutError::LspError("exit before shutdown".to_string()))
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_positions_count_utf16_units() {
+        let text = "a\nnäh 🌱 [[x]]\n";
+        let link = text.find("[[").unwrap();
+        assert_eq!(position(text, link), json!({ "line": 1, "character": 7 }));
+        assert_eq!(offset(text, 1, 7), link);
+        assert_eq!(offset(text, 0, 99), 1);
+        assert_eq!(offset(text, 9, 0), text.len());
+    }
+
+    #[test]
+    fn test_uri_roundtrip() {
+        let path = Path::new("/tmp/My Notes/a#b.md");
+        let uri = path_to_uri(path);
+        assert_eq!(uri, "file:///tmp/My%20Notes/a%23b.md");
+        assert_eq!(uri_to_path(&uri).unwrap(), path);
+    }
+
+    #[test]
+    fn test_read_message() {
+        let mut input = "Content-Length: 2\r\nContent-Type: x\r\n\r\n{}Content-Length: 1\r\n\r\nx".as_bytes();
+        assert_eq!(read_message(&mut input), Some(json!({})));
+        assert_eq!(read_message(&mut input), Some(Value::Null));
+        assert_eq!(read_message(&mut input), None);
+    }
+}
@@ -1,8 +1,18 @@
+pub mod agenda;
+pub mod autolink;
+pub mod cache;
+pub mod check;
 pub mod done;
 pub mod init;
 pub mod list;
+pub mod lsp;
 pub mod note;
+pub mod optimize;
 pub mod promote;
+pub mod related;
 pub mod review;
+pub mod search;
+pub mod serve;
 pub mod show;
 pub mod stats;
+pub mod watch;
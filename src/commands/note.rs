@@ -1,11 +1,14 @@
-use std::io::Write;
-use std::path::Path;
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
 use std::process::Command;
 
 use crate::cli::OutputFormat;
 use crate::config::Config;
 use crate::error::SproutError;
-use crate::note;
+use crate::frontmatter;
+use crate::naming::{self, FilenameScheme, IdScheme};
+use crate::note::{self, NoteContent, NoteMetaInfo};
 use crate::output;
 use crate::template;
 
@@ -35,7 +38,7 @@ pub fn run_list(
     config: &Config,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
-    let paths = note::scan_vault_paths(vault, &config.exclude_dirs())
+    let paths = note::scan_vault_paths(vault, &config.scan_options())
         .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
 
     let mut candidates: Vec<(String, String)> = paths
@@ -49,48 +52,119 @@ pub fn run_list(
     Ok(())
 }
 
+/// How `sprout note <title>` makes a new note, from its flags.
+#[derive(Default)]
+pub struct CreateOptions<'a> {
+    /// Template name, else `default_template`
+    pub template: Option<&'a str>,
+    /// Luhmann ID to file the note under
+    pub parent: Option<&'a str>,
+    /// Directory in the vault, else the template's or `default_note_dir`
+    pub dir: Option<&'a Path>,
+    /// `--var key=value` template variables
+    pub vars: &'a [(String, String)],
+}
+
 pub fn run_create(
     title: &str,
     vault: &Path,
     config: &Config,
-    template_name: Option<&str>,
+    options: &CreateOptions,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
+    create(title, vault, config, options, format).map(|_| ())
+}
+
+/// Create the note, or find the one that already goes by `title`, and
+/// print it. Returns its path.
+fn create(
+    title: &str,
+    vault: &Path,
+    config: &Config,
+    options: &CreateOptions,
+    format: &OutputFormat,
+) -> Result<PathBuf, SproutError> {
+    let CreateOptions { template: template_name, parent, dir, vars: user_vars } = *options;
+
     // Validate title
     validate_title(title)?;
+    template::validate_user_vars(user_vars)?;
 
     // Strip .md suffix if present
     let title = title.strip_suffix(".md").unwrap_or(title);
 
+    let scheme = config.filename_scheme();
+    let id_scheme = config.id_scheme();
+    if parent.is_some() && id_scheme != IdScheme::Luhmann {
+        return Err(SproutError::ParseError("--parent needs id_scheme = \"luhmann\"".into()));
+    }
+
     let vault_canonical = std::fs::canonicalize(vault)
         .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
-    let file_path = vault_canonical.join(format!("{title}.md"));
-    let relative_path = format!("{title}.md");
+    let tmpl_name = template_name.unwrap_or_else(|| config.default_template());
+    let dir = note_dir(&vault_canonical, dir.or_else(|| config.note_dir(tmpl_name)))?;
+    let notes = note::scan_vault_metadata(&vault_canonical, &config.scan_options())
+        .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
 
-    if file_path.exists() {
+    if let Some(existing) = find_existing(&vault_canonical, &dir, &notes, scheme, title) {
         // Idempotent: return existing file info
         output::format_note_created(
-            &file_path.to_string_lossy(),
-            &relative_path,
+            &existing.path.to_string_lossy(),
+            &existing.relative_path,
             false,
             false,
             format,
         );
-        return Ok(());
+        return Ok(existing.path);
     }
 
+    // Names are unique across the vault, not just the directory, so that
+    // `[[stem]]` links stay unambiguous.
+    let now = chrono::Local::now().naive_local();
+    let stems: HashSet<String> = notes.iter().map(|n| stem(&n.path).to_lowercase()).collect();
+    let ids: HashSet<String> = notes
+        .iter()
+        .filter_map(|n| n.content.id.clone().or_else(|| naming::id_of(&stem(&n.path), scheme, id_scheme)))
+        .collect();
+    let id = match id_scheme {
+        IdScheme::Timestamp => naming::timestamp_id(now, &ids),
+        IdScheme::Luhmann => naming::luhmann_id(parent, &ids)?,
+    };
+    let slug = naming::slugify(title);
+    let file_stem = naming::unique_stem(&naming::file_stem(scheme, title, &id, &slug)?, &stems);
+    let relative_path = dir.join(format!("{file_stem}.md")).to_string_lossy().to_string();
+    let file_path = vault_canonical.join(&relative_path);
+
     // Load and expand template
-    let tmpl_name = template_name.unwrap_or_else(|| config.default_template());
     let template_content = template::load_template(&config.template_dir(), tmpl_name)?;
-    let today = chrono::Local::now().date_naive().to_string();
-    let expanded = template::expand(
-        &template_content,
+    let stdin = if template::uses_stdin(&template_content) {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|e| SproutError::ParseError(format!("failed to read stdin: {e}")))?;
+        Some(input)
+    } else {
+        None
+    };
+    let vars = template::TemplateVars {
         title,
-        &today,
-        config.allow_template_exec(),
-    )?;
+        now,
+        id: &id,
+        slug: &slug,
+        vault: &vault_canonical,
+        stdin: stdin.as_deref(),
+        user: user_vars,
+    };
+    let mut expanded = template::expand(&template_content, &vars, config.allow_template_exec())?;
+    if matches!(scheme, FilenameScheme::Id | FilenameScheme::IdSlug) {
+        expanded = ensure_id(&file_path, &expanded, &id);
+    }
+    if scheme != FilenameScheme::Title {
+        expanded = ensure_title(&file_path, &expanded, title);
+    }
 
     // Write the file
+    create_note_dir(&vault_canonical, &dir)?;
     note::write_note(&file_path, &expanded)?;
 
     // Auto-init if configured
@@ -112,9 +186,117 @@ pub fn run_create(
         format,
     );
 
+    Ok(file_path)
+}
+
+/// `dir` relative to the vault, empty for the vault root. Relative
+/// directories are taken from the vault root; absolute ones must lie in the
+/// vault, and `..` is refused.
+fn note_dir(vault: &Path, dir: Option<&Path>) -> Result<PathBuf, SproutError> {
+    let Some(dir) = dir else {
+        return Ok(PathBuf::new());
+    };
+    let outside = || SproutError::OutsideVault(dir.display().to_string());
+    let relative = if dir.is_absolute() {
+        let canonical = std::fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        canonical.strip_prefix(vault).map_err(|_| outside())?.to_path_buf()
+    } else {
+        dir.to_path_buf()
+    };
+    let mut clean = PathBuf::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => return Err(outside()),
+        }
+    }
+    Ok(clean)
+}
+
+/// Create the missing directories of `dir` one level at a time, checking
+/// that each stays in the vault so a symlink cannot lead the rest outside.
+fn create_note_dir(vault: &Path, dir: &Path) -> Result<(), SproutError> {
+    let mut current = vault.to_path_buf();
+    for part in dir.components() {
+        current.push(part);
+        if !current.exists() {
+            std::fs::create_dir(&current).map_err(|e| {
+                SproutError::ParseError(format!("failed to create {}: {e}", current.display()))
+            })?;
+        }
+        note::ensure_in_vault(&current, vault)?;
+    }
     Ok(())
 }
 
+fn stem(path: &Path) -> String {
+    path.file_stem().unwrap_or_default().to_string_lossy().to_string()
+}
+
+/// The note that already goes by `title`, ignoring case: with the title
+/// scheme `{title}.md` in `dir` or a note of that name anywhere in the vault,
+/// otherwise a note with that title; then a note with `title` among its
+/// aliases, e.g. `ML` for machine-learning.md. The first by path wins.
+fn find_existing(
+    vault: &Path,
+    dir: &Path,
+    notes: &[NoteMetaInfo],
+    scheme: FilenameScheme,
+    title: &str,
+) -> Option<NoteMetaInfo> {
+    let relative_path = dir.join(format!("{title}.md")).to_string_lossy().to_string();
+    let in_dir = vault.join(&relative_path);
+    if scheme == FilenameScheme::Title && in_dir.exists() {
+        return Some(
+            notes
+                .iter()
+                .find(|n| n.relative_path == relative_path)
+                .cloned()
+                .unwrap_or_else(|| NoteMetaInfo {
+                    path: in_dir,
+                    relative_path,
+                    sprout: Default::default(),
+                    content: Default::default(),
+                }),
+        );
+    }
+
+    let lower = title.to_lowercase();
+    let first = |matches: &dyn Fn(&NoteMetaInfo) -> bool| {
+        notes.iter().filter(|n| matches(n)).min_by(|a, b| a.path.cmp(&b.path)).cloned()
+    };
+    let by_name = if scheme == FilenameScheme::Title {
+        first(&|n| stem(&n.path).to_lowercase() == lower)
+    } else {
+        first(&|n| n.content.title.as_ref().is_some_and(|t| t.to_lowercase() == lower))
+    };
+    by_name.or_else(|| first(&|n| n.content.aliases.iter().any(|a| a.to_lowercase() == lower)))
+}
+
+/// `content` with `# {title}` added after the frontmatter, unless its
+/// frontmatter or a heading already gives the note a title.
+/// `content` with `id` in its frontmatter, unless the template already
+/// gives one, so that later notes find the ID without reading file names.
+fn ensure_id(path: &Path, content: &str, id: &str) -> String {
+    let parsed = frontmatter::parse_note_at(path, content);
+    match parsed.frontmatter_raw.as_deref() {
+        Some(raw) if parsed.format.has_field(raw, "id") => content.to_string(),
+        Some(raw) => parsed.format.write_back(raw, &parsed.body, &[("id", id)]),
+        None => parsed.format.build_new_frontmatter(&[("id", id)], &parsed.body),
+    }
+}
+
+fn ensure_title(path: &Path, content: &str, title: &str) -> String {
+    let parsed = frontmatter::parse_note_at(path, content);
+    if NoteContent::extract(content, &parsed).title.is_some() {
+        return content.to_string();
+    }
+    let head_len = if content.ends_with(&parsed.body) { content.len() - parsed.body.len() } else { 0 };
+    let (head, body) = content.split_at(head_len);
+    format!("{head}# {title}\n{}{body}", if body.is_empty() { "" } else { "\n" })
+}
+
 fn cmd_available(name: &str) -> bool {
     Command::new("sh")
         .args(["-c", &format!("command -v {name} >/dev/null 2>&1")])
@@ -206,7 +388,7 @@ pub fn run_interactive(
     let script_path = script_file.path().to_string_lossy().to_string();
 
     // Scan vault for candidates
-    let paths = note::scan_vault_paths(vault, &config.exclude_dirs())
+    let paths = note::scan_vault_paths(vault, &config.scan_options())
         .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
 
     let mut candidates: Vec<(String, String)> = paths
@@ -254,10 +436,10 @@ pub fn run_interactive(
     match exit_code {
         130 => {
             // Ctrl-C / Esc: do nothing
-            return Ok(());
+            Ok(())
         }
         2 => {
-            return Err(SproutError::FzfError("fzf encountered an error".into()));
+            Err(SproutError::FzfError("fzf encountered an error".into()))
         }
         0 | 1 => {
             // Parse output: line 1 = query, line 2 = selected item (if any)
@@ -275,13 +457,8 @@ pub fn run_interactive(
 
             if !query.is_empty() {
                 // No selection, but query is non-empty: create new note
-                run_create(query, vault, config, template_name, format)?;
-
-                // Resolve the created file path and open in editor
-                let vault_canonical = std::fs::canonicalize(vault)
-                    .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
-                let title = query.strip_suffix(".md").unwrap_or(query);
-                let file_path = vault_canonical.join(format!("{title}.md"));
+                let options = CreateOptions { template: template_name, ..Default::default() };
+                let file_path = create(query, vault, config, &options, format)?;
                 return open_in_editor(&editor, &file_path);
             }
 
@@ -336,6 +513,16 @@ mod tests {
         assert!(validate_title("a..b").is_err());
     }
 
+    #[test]
+    fn test_note_dir() {
+        let vault = Path::new("/notes");
+        assert_eq!(note_dir(vault, None).unwrap(), PathBuf::new());
+        assert_eq!(note_dir(vault, Some(Path::new("./inbox/"))).unwrap(), PathBuf::from("inbox"));
+        assert_eq!(note_dir(vault, Some(Path::new("/notes/a/b"))).unwrap(), PathBuf::from("a/b"));
+        assert!(note_dir(vault, Some(Path::new("a/../../b"))).is_err());
+        assert!(note_dir(vault, Some(Path::new("/elsewhere"))).is_err());
+    }
+
     #[test]
     fn test_validate_title_null_byte() {
         assert!(validate_title("note\0bad").is_err());
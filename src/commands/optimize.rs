@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use crate::cli::OutputFormat;
+use crate::config::Config;
+use crate::error::SproutError;
+use crate::history;
+use crate::optimize;
+use crate::output;
+
+pub fn run(
+    vault: &Path,
+    target_retention: f64,
+    config: &Config,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    if !(target_retention > 0.0 && target_retention < 1.0) {
+        return Err(SproutError::ParseError(format!(
+            "target retention must be between 0 and 1, got {target_retention}"
+        )));
+    }
+
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+    let records = history::load_reviews(&vault_canonical);
+
+    let result = optimize::optimize(
+        &records,
+        &config.srs_params(),
+        config.link_weight(),
+        config.max_interval(),
+        target_retention,
+    )
+    .ok_or_else(|| {
+        let follow_ups = records.len().saturating_sub(
+            records
+                .iter()
+                .map(|r| &r.path)
+                .collect::<std::collections::HashSet<_>>()
+                .len(),
+        );
+        SproutError::InsufficientHistory(follow_ups, optimize::MIN_SAMPLES)
+    })?;
+
+    output::format_optimize(&result, target_retention, format);
+    Ok(())
+}
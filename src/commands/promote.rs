@@ -1,10 +1,23 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
+use chrono::{Local, NaiveDate};
+
+use crate::bulk::{self, Change};
 use crate::cli::{Maturity, OutputFormat};
+use crate::config::Config;
 use crate::error::SproutError;
-use crate::frontmatter::write_back;
+use crate::frontmatter::{parse_note_at, SproutFrontmatter};
+use crate::history::{self, HistoryEvent, PromoteRecord};
 use crate::note;
-use crate::output;
+use crate::output::{self, BulkEntry};
+use crate::query::Query;
+
+pub struct PromoteOutcome {
+    relative_path: String,
+    previous_maturity: String,
+    new_maturity: String,
+    sprout: SproutFrontmatter,
+}
 
 pub fn run(
     file: &Path,
@@ -12,6 +25,74 @@ pub fn run(
     vault: &Path,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
+    let change = plan(file, maturity, vault)?;
+
+    // Write back (even if same maturity — no-op success with idempotent write)
+    note::write_note(&change.path, &change.content)?;
+    record_history(&change, Local::now().date_naive());
+
+    let o = &change.outcome;
+    output::format_promote(
+        &change.path.to_string_lossy(),
+        &o.relative_path,
+        &o.previous_maturity,
+        &o.new_maturity,
+        o.sprout.review_interval,
+        o.sprout.next_review,
+        o.sprout.ease,
+        format,
+    );
+
+    Ok(())
+}
+
+/// Promote many notes at once (files, `-` for stdin, and/or `--query`).
+pub fn run_bulk(
+    files: &[PathBuf],
+    query: Option<&str>,
+    maturity: &Maturity,
+    atomic: bool,
+    vault: &Path,
+    config: &Config,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let query = query.map(Query::parse).transpose()?;
+    let today = Local::now().date_naive();
+    let targets = bulk::resolve_targets(files, query.as_ref(), vault, &config.scan_options(), today)?;
+
+    let (changes, failed) = bulk::plan_all(&targets, |file| plan(file, maturity, vault));
+    let report = bulk::apply(changes, failed, atomic);
+    for c in &report.succeeded {
+        record_history(c, today);
+    }
+
+    let results: Vec<BulkEntry> = report
+        .succeeded
+        .iter()
+        .map(|c| {
+            let o = &c.outcome;
+            (
+                output::promote_json(
+                    &c.path.to_string_lossy(),
+                    &o.relative_path,
+                    &o.previous_maturity,
+                    &o.new_maturity,
+                    o.sprout.review_interval,
+                    o.sprout.next_review,
+                    o.sprout.ease,
+                ),
+                format!(
+                    "Promoted: {}: {} → {}",
+                    o.relative_path, o.previous_maturity, o.new_maturity
+                ),
+            )
+        })
+        .collect();
+    output::format_bulk(report.applied, &results, &report.failed, format);
+    report.status()
+}
+
+fn plan(file: &Path, maturity: &Maturity, vault: &Path) -> Result<Change<PromoteOutcome>, SproutError> {
     if !file.exists() {
         return Err(SproutError::FileNotFound(file.display().to_string()));
     }
@@ -28,7 +109,9 @@ pub fn run(
         .to_string_lossy()
         .to_string();
 
-    let parsed = note::read_note(file)?;
+    let original = std::fs::read_to_string(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+    let parsed = parse_note_at(file, &original);
 
     let previous_maturity = parsed
         .sprout
@@ -43,21 +126,29 @@ pub fn run(
         .ok_or_else(|| SproutError::NoFrontmatter(file.display().to_string()))?;
 
     let new_maturity = maturity.to_string();
+    let content = parsed.format.write_back(raw_yaml, &parsed.body, &[("maturity", &new_maturity)]);
 
-    // Write back (even if same maturity — no-op success with idempotent write)
-    let content = write_back(raw_yaml, &parsed.body, &[("maturity", &new_maturity)]);
-    note::write_note(file, &content)?;
-
-    output::format_promote(
-        &file_canonical.to_string_lossy(),
-        &relative_path,
-        &previous_maturity,
-        &new_maturity,
-        parsed.sprout.review_interval,
-        parsed.sprout.next_review,
-        parsed.sprout.ease,
-        format,
-    );
+    Ok(Change {
+        path: file_canonical,
+        original,
+        content,
+        outcome: PromoteOutcome {
+            relative_path,
+            previous_maturity,
+            new_maturity,
+            sprout: parsed.sprout,
+        },
+    })
+}
 
-    Ok(())
+fn record_history(change: &Change<PromoteOutcome>, today: NaiveDate) {
+    let o = &change.outcome;
+    if o.previous_maturity != o.new_maturity {
+        history::append(&HistoryEvent::Promote(PromoteRecord {
+            date: today,
+            path: change.path.clone(),
+            from: o.previous_maturity.clone(),
+            to: o.new_maturity.clone(),
+        }));
+    }
 }
@@ -0,0 +1,213 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+
+use crate::cli::OutputFormat;
+use crate::error::SproutError;
+use crate::frontmatter::NoteFormat;
+use crate::index::{LinkTarget, VaultIndex};
+use crate::links;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
+use crate::output;
+use crate::search::{self, SearchIndex, SearchQuery};
+
+// Weights of the three signals, each between 0 and 1, in the combined score
+const CONTENT_WEIGHT: f64 = 0.6;
+const TAG_WEIGHT: f64 = 0.2;
+const GRAPH_WEIGHT: f64 = 0.2;
+
+/// Farthest link distance that still counts as graph proximity.
+const MAX_DISTANCE: usize = 3;
+
+pub struct RelatedNote {
+    pub note: NoteMetaInfo,
+    pub score: f64,
+    /// TF-IDF cosine similarity of the text
+    pub similarity: f64,
+    pub shared_tags: Vec<String>,
+    /// Links between the notes, in either direction; `None` beyond `MAX_DISTANCE`
+    pub distance: Option<usize>,
+}
+
+/// The note's title written in another note without a link.
+pub struct Mention {
+    pub path: PathBuf,
+    pub relative_path: String,
+    /// 1-based line and character column
+    pub line: usize,
+    pub column: usize,
+    pub text: String,
+}
+
+pub struct Related {
+    pub note: NoteMetaInfo,
+    pub related: Vec<RelatedNote>,
+    pub mentions: Vec<Mention>,
+}
+
+pub fn run(
+    file: &Path,
+    vault: &Path,
+    scan_options: &ScanOptions,
+    limit: usize,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    if !file.exists() {
+        return Err(SproutError::FileNotFound(file.display().to_string()));
+    }
+    note::ensure_in_vault(file, vault)?;
+    let file_canonical = std::fs::canonicalize(file)
+        .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
+
+    let index = VaultIndex::build(vault, scan_options, Local::now().date_naive())?;
+    let Some(target) = index.note(&file_canonical).cloned() else {
+        // Excluded from scanning or not a note extension
+        return Err(SproutError::FileNotFound(file.display().to_string()));
+    };
+    let mut search_index = SearchIndex::load(index.vault());
+    let notes = index.notes();
+    let paths: Vec<PathBuf> = notes.iter().map(|n| n.path.clone()).collect();
+    search_index.update(&paths);
+    search_index.save();
+
+    let related = Related {
+        related: rank(&index, &search_index, &target, &notes, limit),
+        mentions: unlinked_mentions(&search_index, &target, &notes),
+        note: target,
+    };
+    output::format_related(&related, format);
+    Ok(())
+}
+
+/// Notes not linked with `target`, by weighted text similarity, shared tags
+/// and graph proximity, best first.
+fn rank(
+    index: &VaultIndex,
+    search_index: &SearchIndex,
+    target: &NoteMetaInfo,
+    notes: &[NoteMetaInfo],
+    limit: usize,
+) -> Vec<RelatedNote> {
+    let similarity: HashMap<PathBuf, f64> = search_index.similar(&target.path).into_iter().collect();
+    let distances = link_distances(index, notes, &target.path);
+    let tags: BTreeSet<&String> = target.content.tags.iter().collect();
+
+    let mut related: Vec<RelatedNote> = notes
+        .iter()
+        .filter(|n| n.path != target.path && distances.get(&n.path) != Some(&1))
+        .filter_map(|n| {
+            let similarity = similarity.get(&n.path).copied().unwrap_or_default();
+            let other: BTreeSet<&String> = n.content.tags.iter().collect();
+            let shared_tags: Vec<String> = tags.intersection(&other).map(|t| t.to_string()).collect();
+            let union = tags.union(&other).count();
+            let tag_score = if union == 0 { 0.0 } else { shared_tags.len() as f64 / union as f64 };
+            let distance = distances.get(&n.path).copied();
+            let proximity = distance.map_or(0.0, |d| 1.0 / (d - 1) as f64);
+            let score = CONTENT_WEIGHT * similarity + TAG_WEIGHT * tag_score + GRAPH_WEIGHT * proximity;
+            (score > 0.0).then(|| RelatedNote {
+                note: n.clone(),
+                score,
+                similarity,
+                shared_tags,
+                distance,
+            })
+        })
+        .collect();
+    related.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.note.path.cmp(&b.note.path)));
+    related.truncate(limit);
+    related
+}
+
+/// Link distance from `start` to notes within `MAX_DISTANCE`, following
+/// links in both directions.
+fn link_distances(index: &VaultIndex, notes: &[NoteMetaInfo], start: &Path) -> HashMap<PathBuf, usize> {
+    let mut neighbours: HashMap<&Path, HashSet<PathBuf>> = HashMap::new();
+    for n in notes {
+        for link in &n.content.links {
+            if let LinkTarget::Note(to) | LinkTarget::MissingAnchor(to) = index.resolve(&n.path, link) {
+                if to != n.path {
+                    neighbours.entry(&n.path).or_default().insert(to.clone());
+                    if let Some(to_note) = index.note(&to) {
+                        neighbours.entry(&to_note.path).or_default().insert(n.path.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    let mut distances: HashMap<PathBuf, usize> = HashMap::new();
+    let mut queue = VecDeque::from([(start.to_path_buf(), 0)]);
+    while let Some((path, d)) = queue.pop_front() {
+        if d == MAX_DISTANCE {
+            continue;
+        }
+        for next in neighbours.get(path.as_path()).into_iter().flatten() {
+            if next != start && !distances.contains_key(next) {
+                distances.insert(next.clone(), d + 1);
+                queue.push_back((next.clone(), d + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// Places in other notes' bodies where `target`'s title (or file name, for
+/// notes without a title) appears outside a link, in path order.
+fn unlinked_mentions(search_index: &SearchIndex, target: &NoteMetaInfo, notes: &[NoteMetaInfo]) -> Vec<Mention> {
+    let name = match &target.content.title {
+        Some(title) => title.clone(),
+        None => target.path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+    };
+    let words = search::tokenize(&name);
+    if words.is_empty() {
+        return Vec::new();
+    }
+    // Only notes containing the words as a phrase can mention the name
+    let Ok(query) = SearchQuery::parse(&format!("\"{}\"", words.join(" "))) else {
+        return Vec::new();
+    };
+    let relative: HashMap<&Path, &str> = notes.iter().map(|n| (n.path.as_path(), n.relative_path.as_str())).collect();
+    let mut candidates: Vec<PathBuf> = search_index
+        .search(&query)
+        .into_iter()
+        .map(|(path, _)| path)
+        .filter(|path| *path != target.path)
+        .collect();
+    candidates.sort();
+
+    let mut mentions = Vec::new();
+    for path in candidates {
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        let Some(relative_path) = relative.get(path.as_path()) else {
+            continue;
+        };
+        let skip = search::body_start(&path, &content);
+        let spans = links::find_links(NoteFormat::from_path(&path), &content);
+        let mut offset = 0;
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            let line_start = offset;
+            offset += line.len();
+            if i < skip {
+                continue;
+            }
+            let line = line.trim_end_matches(['\n', '\r']);
+            for start in links::find_mentions(line, &name) {
+                let start = line_start + start;
+                if spans.iter().any(|l| l.start <= start && start < l.end) {
+                    continue;
+                }
+                mentions.push(Mention {
+                    path: path.clone(),
+                    relative_path: relative_path.to_string(),
+                    line: i + 1,
+                    column: content[line_start..start].chars().count() + 1,
+                    text: search::excerpt(line),
+                });
+            }
+        }
+    }
+    mentions
+}
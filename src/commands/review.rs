@@ -2,44 +2,41 @@ use std::path::Path;
 
 use chrono::Local;
 
-use crate::cli::OutputFormat;
+use crate::cli::{OutputFormat, SortArgs, SortKey};
 use crate::error::SproutError;
-use crate::note;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
 use crate::output;
+use crate::query::{self, Query};
+use crate::sort;
 
 pub fn run(
     vault: &Path,
-    exclude_dirs: &[String],
+    scan_options: &ScanOptions,
+    query: Option<&str>,
+    sort_args: &SortArgs,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
-    let notes = note::scan_vault_metadata(vault, exclude_dirs)
+    // Parse before scanning so syntax errors are reported immediately
+    let query = query.map(Query::parse).transpose()?;
+    let notes = note::scan_vault_metadata(vault, scan_options)
         .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+    run_with(notes, query.as_ref(), sort_args, format)
+}
 
+/// Output the due notes among `notes`, which `sprout serve` takes from its index.
+pub fn run_with(
+    notes: Vec<NoteMetaInfo>,
+    query: Option<&Query>,
+    sort_args: &SortArgs,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
     let today = Local::now().date_naive();
 
-    let mut due: Vec<_> = notes
-        .into_iter()
-        .filter(|n| {
-            // Must be tracked (maturity exists)
-            if n.sprout.maturity.is_none() {
-                return false;
-            }
-            // Skip if next_review is missing
-            let next_review = match n.sprout.next_review {
-                Some(nr) => nr,
-                None => return false,
-            };
-            // Skip if ease or review_interval is missing
-            if n.sprout.ease.is_none() || n.sprout.review_interval.is_none() {
-                return false;
-            }
-            // Due: next_review <= today
-            next_review <= today
-        })
-        .collect();
+    let due: Vec<_> = notes.into_iter().filter(|n| n.is_due(today)).collect();
+    let due = query::filter(due, query, today);
 
-    // Sort by next_review ascending (most overdue first)
-    due.sort_by(|a, b| a.sprout.next_review.cmp(&b.sprout.next_review));
+    // Default: next_review ascending (most overdue first)
+    let due = sort::sort_and_paginate(due, sort_args, SortKey::NextReview, today);
 
     let entries: Vec<_> = due
         .iter()
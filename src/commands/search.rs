@@ -0,0 +1,68 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::cli::{Maturity, OutputFormat};
+use crate::error::SproutError;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
+use crate::output;
+use crate::query;
+use crate::search::{self, SearchIndex, SearchQuery};
+
+/// Matching lines shown per result.
+const SNIPPETS: usize = 3;
+
+pub struct SearchHit {
+    pub note: NoteMetaInfo,
+    pub score: f64,
+    /// (1-based line number, text)
+    pub snippets: Vec<(usize, String)>,
+}
+
+pub fn run(
+    vault: &Path,
+    scan_options: &ScanOptions,
+    terms: &[String],
+    maturity: Option<&Maturity>,
+    tag: Option<&str>,
+    limit: usize,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    // An argument with spaces was quoted on the command line: a phrase
+    let input: Vec<String> = terms
+        .iter()
+        .map(|t| if t.contains(char::is_whitespace) && !t.contains('"') { format!("\"{t}\"") } else { t.clone() })
+        .collect();
+    let query = SearchQuery::parse(&input.join(" "))?;
+
+    let notes = note::scan_vault_metadata(vault, scan_options)
+        .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+    let mut index = SearchIndex::load(&vault_canonical);
+    let paths: Vec<PathBuf> = notes.iter().map(|n| n.path.clone()).collect();
+    index.update(&paths);
+    index.save();
+
+    let tag = tag.map(|t| t.trim_start_matches('#').to_lowercase());
+    let mut by_path: HashMap<PathBuf, NoteMetaInfo> = notes.into_iter().map(|n| (n.path.clone(), n)).collect();
+    let hits: Vec<SearchHit> = index
+        .search(&query)
+        .into_iter()
+        .filter_map(|(path, score)| {
+            let note = by_path.remove(&path)?;
+            let maturity_ok = maturity.is_none_or(|m| note.sprout.maturity.as_deref() == Some(&m.to_string()));
+            let tag_ok = tag.as_deref().is_none_or(|t| query::has_tag(&note, t));
+            (maturity_ok && tag_ok).then_some((note, score))
+        })
+        .take(limit)
+        .map(|(note, score)| {
+            let snippets = std::fs::read_to_string(&note.path)
+                .map(|content| search::snippets(&note.path, &content, &query, SNIPPETS))
+                .unwrap_or_default();
+            SearchHit { note, score, snippets }
+        })
+        .collect();
+
+    output::format_search(&hits, format);
+    Ok(())
+}
@@ -0,0 +1,345 @@
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::cli::{Maturity, OutputFormat, Rating, SortArgs};
+use crate::commands::watch::VaultWatcher;
+use crate::commands::{done, list, promote, review, show, stats};
+use crate::config::Config;
+use crate::error::SproutError;
+use crate::index::VaultIndex;
+use crate::output;
+use crate::query::Query;
+
+// JSON-RPC 2.0 error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// A command failed; `data.error` is the CLI error code
+const COMMAND_FAILED: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+    data: Option<Value>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        RpcError {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    /// `output` is what the command printed before failing, e.g. a bulk report.
+    fn command(error: SproutError, output: Option<Value>) -> Self {
+        let mut data = json!({ "error": error.error_code() });
+        if let Some(output) = output {
+            data["output"] = output;
+        }
+        RpcError {
+            code: COMMAND_FAILED,
+            message: error.to_string(),
+            data: Some(data),
+        }
+    }
+}
+
+impl From<SproutError> for RpcError {
+    fn from(error: SproutError) -> Self {
+        RpcError::command(error, None)
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ReviewParams {
+    query: Option<String>,
+    #[serde(flatten)]
+    sort: SortArgs,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ListParams {
+    maturity: Option<Maturity>,
+    query: Option<String>,
+    #[serde(flatten)]
+    sort: SortArgs,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StatsParams {
+    detailed: bool,
+    calendar: bool,
+    query: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ShowParams {
+    file: PathBuf,
+}
+
+/// Targets of `done` / `promote`: `file`, `files` and/or `query`.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Targets {
+    file: Option<PathBuf>,
+    files: Vec<PathBuf>,
+    query: Option<String>,
+    atomic: bool,
+}
+
+#[derive(Deserialize)]
+struct DoneParams {
+    rating: Rating,
+    #[serde(flatten)]
+    targets: Targets,
+}
+
+#[derive(Deserialize)]
+struct PromoteParams {
+    maturity: Maturity,
+    #[serde(flatten)]
+    targets: Targets,
+}
+
+impl Targets {
+    /// Files resolved against the vault, since the server's working
+    /// directory is unrelated to the editor's.
+    fn files(&self, vault: &Path) -> Result<Vec<PathBuf>, RpcError> {
+        let files: Vec<PathBuf> = self.file.iter().chain(&self.files).map(|f| vault.join(f)).collect();
+        if files.is_empty() && self.query.is_none() {
+            return Err(RpcError::new(INVALID_PARAMS, "invalid params: expected file, files or query"));
+        }
+        Ok(files)
+    }
+
+    /// Anything but a single file goes through the bulk path.
+    fn is_bulk(&self, files: &[PathBuf]) -> bool {
+        files.len() != 1 || self.query.is_some() || self.atomic
+    }
+}
+
+/// Warm state shared by all connections.
+struct Server {
+    index: VaultIndex,
+    watcher: VaultWatcher,
+    config: Config,
+}
+
+impl Server {
+    /// Answer one request line. `None` for notifications (no `id`).
+    fn handle_line(&mut self, line: &str) -> Option<String> {
+        let (id, result) = match serde_json::from_str::<Value>(line) {
+            Err(e) => (Value::Null, Err(RpcError::new(PARSE_ERROR, format!("parse error: {e}")))),
+            Ok(request) => {
+                let id = request.get("id").cloned();
+                let result = match request.get("method").and_then(Value::as_str) {
+                    Some(method) if request.is_object() => {
+                        let params = request.get("params").cloned().unwrap_or(Value::Null);
+                        self.call(method, params)
+                    }
+                    _ => Err(RpcError::new(INVALID_REQUEST, "invalid request: expected an object with a method")),
+                };
+                match id {
+                    Some(id) => (id, result),
+                    None => return None,
+                }
+            }
+        };
+
+        let response = match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => {
+                let mut error = json!({ "code": e.code, "message": e.message });
+                if let Some(data) = e.data {
+                    error["data"] = data;
+                }
+                json!({ "jsonrpc": "2.0", "id": id, "error": error })
+            }
+        };
+        Some(serde_json::to_string(&response).unwrap())
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, RpcError> {
+        // Catch up with edits made since the last request
+        self.watcher.pending().apply(&mut self.index);
+        self.index.tick(Local::now().date_naive());
+
+        let vault = self.index.vault().to_path_buf();
+        let format = &OutputFormat::Json;
+        let (result, output) = match method {
+            "review" => {
+                let p: ReviewParams = parse_params(params)?;
+                let query = p.query.as_deref().map(Query::parse).transpose()?;
+                let notes = self.index.notes();
+                output::capture(|| review::run_with(notes, query.as_ref(), &p.sort, format))
+            }
+            "list" => {
+                let p: ListParams = parse_params(params)?;
+                let query = p.query.as_deref().map(Query::parse).transpose()?;
+                let notes = self.index.notes();
+                output::capture(|| list::run_with(notes, p.maturity.as_ref(), query.as_ref(), &p.sort, format))
+            }
+            "stats" => {
+                let p: StatsParams = parse_params(params)?;
+                let query = p.query.as_deref().map(Query::parse).transpose()?;
+                let notes = self.index.notes();
+                output::capture(|| stats::run_with(&vault, notes, p.detailed, p.calendar, query.as_ref(), format))
+            }
+            "show" => {
+                let p: ShowParams = parse_params(params)?;
+                let index = &self.index;
+                output::capture(|| show::run_with(&vault.join(&p.file), index, format))
+            }
+            "done" => {
+                let p: DoneParams = parse_params(params)?;
+                let files = p.targets.files(&vault)?;
+                let t = &p.targets;
+                let config = &self.config;
+                if t.is_bulk(&files) {
+                    output::capture(|| done::run_bulk(&files, t.query.as_deref(), &p.rating, t.atomic, &vault, config, format))
+                } else {
+                    let index = &self.index;
+                    output::capture(|| done::run_with(&files[0], &p.rating, &vault, config, Some(index), format))
+                }
+            }
+            "promote" => {
+                let p: PromoteParams = parse_params(params)?;
+                let files = p.targets.files(&vault)?;
+                let t = &p.targets;
+                let config = &self.config;
+                if t.is_bulk(&files) {
+                    output::capture(|| promote::run_bulk(&files, t.query.as_deref(), &p.maturity, t.atomic, &vault, config, format))
+                } else {
+                    output::capture(|| promote::run(&files[0], &p.maturity, &vault, format))
+                }
+            }
+            _ => return Err(RpcError::new(METHOD_NOT_FOUND, format!("method not found: {method}"))),
+        };
+        let output = output.into_iter().next();
+
+        // Notes written by done / promote: update the index now rather than
+        // when the watcher reports them
+        if let Some(output) = &output {
+            let written = written_paths(output);
+            if !written.is_empty() {
+                self.index.apply(&written, &[]);
+            }
+        }
+
+        match result {
+            Ok(()) => Ok(output.unwrap_or(Value::Null)),
+            Err(e) => Err(RpcError::command(e, output)),
+        }
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Value) -> Result<T, RpcError> {
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, format!("invalid params: {e}")))
+}
+
+/// `path` of a done / promote result, or of each result in a bulk report.
+fn written_paths(output: &Value) -> Vec<PathBuf> {
+    let results = match output.get("results").and_then(Value::as_array) {
+        Some(results) => results.iter().collect(),
+        None => vec![output],
+    };
+    results
+        .into_iter()
+        .filter_map(|r| r.get("path").and_then(Value::as_str))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Serve until stdin closes, or forever on a socket.
+pub fn run(vault: &Path, config: &Config, socket: Option<&Path>) -> Result<(), SproutError> {
+    let index = VaultIndex::build(vault, &config.scan_options(), Local::now().date_naive())?;
+    let watcher = VaultWatcher::start(index.vault())?;
+    let mut server = Server {
+        index,
+        watcher,
+        config: config.clone(),
+    };
+
+    match socket {
+        None => {
+            let stdin = std::io::stdin();
+            let mut stdout = std::io::stdout();
+            for line in stdin.lock().lines() {
+                let line = line.map_err(|e| SproutError::ServeError(e.to_string()))?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(response) = server.handle_line(&line) {
+                    writeln!(stdout, "{response}").and_then(|_| stdout.flush())
+                        .map_err(|e| SproutError::ServeError(e.to_string()))?;
+                }
+            }
+            Ok(())
+        }
+        Some(path) => serve_socket(server, path),
+    }
+}
+
+#[cfg(unix)]
+fn serve_socket(server: Server, path: &Path) -> Result<(), SproutError> {
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::{Arc, Mutex};
+
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(SproutError::ServeError(format!("{} is in use by another server", path.display())));
+        }
+        // Left behind by a server that did not shut down cleanly
+        let _ = std::fs::remove_file(path);
+    }
+    let listener = UnixListener::bind(path)
+        .map_err(|e| SproutError::ServeError(format!("{}: {e}", path.display())))?;
+
+    // Requests from all connections are handled one at a time
+    let server = Arc::new(Mutex::new(server));
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else { continue };
+        let server = Arc::clone(&server);
+        std::thread::spawn(move || serve_connection(stream, &server));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_server: Server, _path: &Path) -> Result<(), SproutError> {
+    Err(SproutError::ServeError("--socket needs Unix domain sockets; use stdin/stdout on this platform".into()))
+}
+
+#[cfg(unix)]
+fn serve_connection(stream: std::os::unix::net::UnixStream, server: &std::sync::Mutex<Server>) {
+    use std::io::BufReader;
+
+    let Ok(mut writer) = stream.try_clone() else { return };
+    for line in BufReader::new(stream).lines() {
+        let Ok(line) = line else { return };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match server.lock() {
+            Ok(mut server) => server.handle_line(&line),
+            Err(_) => return,
+        };
+        if let Some(response) = response {
+            if writeln!(writer, "{response}").is_err() {
+                return;
+            }
+        }
+    }
+}
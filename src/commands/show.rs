@@ -1,42 +1,66 @@
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use chrono::Local;
 
 use crate::cli::OutputFormat;
 use crate::error::SproutError;
+use crate::index::{LinkTarget, VaultIndex};
 use crate::links;
-use crate::note;
+use crate::note::{self, ScanOptions};
 use crate::output;
 
+/// A heading or `^block-id` of the note that links elsewhere point into.
+pub struct ReferencedSection {
+    /// Heading text, or `^block-id`
+    pub section: String,
+    /// Relative paths of the linking notes
+    pub referenced_by: Vec<String>,
+}
+
 pub fn run(
     file: &Path,
     vault: &Path,
+    scan_options: &ScanOptions,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
+    // Fail before scanning the vault
     if !file.exists() {
         return Err(SproutError::FileNotFound(file.display().to_string()));
     }
+    note::ensure_in_vault(file, vault)?;
 
+    let index = VaultIndex::build(vault, scan_options, Local::now().date_naive())?;
+    run_with(file, &index, format)
+}
+
+/// Show `file` with the links into it taken from `index`, which
+/// `sprout serve` keeps up to date.
+pub fn run_with(file: &Path, index: &VaultIndex, format: &OutputFormat) -> Result<(), SproutError> {
+    if !file.exists() {
+        return Err(SproutError::FileNotFound(file.display().to_string()));
+    }
+    let vault = index.vault();
     note::ensure_in_vault(file, vault)?;
 
     let file_canonical = std::fs::canonicalize(file)
         .map_err(|_| SproutError::FileNotFound(file.display().to_string()))?;
-    let vault_canonical = std::fs::canonicalize(vault)
-        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
     let relative_path = file_canonical
-        .strip_prefix(&vault_canonical)
+        .strip_prefix(vault)
         .unwrap_or(&file_canonical)
         .to_string_lossy()
         .to_string();
 
     let parsed = note::read_note(file)?;
     let path_str = file_canonical.to_string_lossy().to_string();
+    let today = Local::now().date_naive();
+    let sections = referenced_sections(index, &file_canonical);
 
     // Tracked = maturity field exists
     match &parsed.sprout.maturity {
         Some(maturity) => {
-            let today = Local::now().date_naive();
-            let link_count = links::count_links(&parsed.body);
+            // Links through aliases count once per note
+            let link_count = links::count_links(parsed.format, &parsed.body, |t| index.link_key(&file_canonical, t));
 
             let is_due = parsed
                 .sprout
@@ -62,13 +86,56 @@ pub fn run(
                 is_due,
                 days_until_review,
                 link_count,
+                &sections,
                 format,
             );
         }
         None => {
-            output::format_show_untracked(&path_str, &relative_path, format);
+            output::format_show_untracked(&path_str, &relative_path, &sections, format);
         }
     }
 
     Ok(())
 }
+
+/// Sections of the note at `path` linked from other notes, in the order
+/// they appear in the note.
+fn referenced_sections(index: &VaultIndex, path: &Path) -> Vec<ReferencedSection> {
+    let Some(target) = index.note(path) else {
+        return Vec::new();
+    };
+    let mut notes = index.notes();
+    notes.retain(|n| n.path != path);
+    notes.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+    let mut referenced: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for n in &notes {
+        for link in &n.content.links {
+            let Some((_, anchor)) = link.split_once('#') else {
+                continue;
+            };
+            if index.resolve(&n.path, link) != LinkTarget::Note(path.to_path_buf()) {
+                continue;
+            }
+            if let Some(section) = index.section(path, anchor) {
+                let by = referenced.entry(section).or_default();
+                if !by.contains(&n.relative_path) {
+                    by.push(n.relative_path.clone());
+                }
+            }
+        }
+    }
+
+    let content = &target.content;
+    let blocks = content.block_ids.iter().map(|b| format!("^{b}"));
+    content
+        .headings
+        .iter()
+        .cloned()
+        .chain(blocks)
+        .filter_map(|section| {
+            let referenced_by = referenced.remove(&section)?;
+            Some(ReferencedSection { section, referenced_by })
+        })
+        .collect()
+}
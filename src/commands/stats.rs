@@ -1,21 +1,41 @@
+use std::collections::HashSet;
 use std::path::Path;
 
 use chrono::Local;
 
 use crate::cli::OutputFormat;
 use crate::error::SproutError;
-use crate::note;
+use crate::history;
+use crate::note::{self, NoteMetaInfo, ScanOptions};
 use crate::output;
+use crate::query::{self, Query};
+use crate::stats::{detailed_stats, review_calendar};
 
 pub fn run(
     vault: &Path,
-    exclude_dirs: &[String],
+    scan_options: &ScanOptions,
+    detailed: bool,
+    calendar: bool,
+    query: Option<&str>,
     format: &OutputFormat,
 ) -> Result<(), SproutError> {
-    let notes = note::scan_vault_metadata(vault, exclude_dirs)
+    let query = query.map(Query::parse).transpose()?;
+    let notes = note::scan_vault_metadata(vault, scan_options)
         .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+    run_with(vault, notes, detailed, calendar, query.as_ref(), format)
+}
 
+/// Stats over `notes`, which `sprout serve` takes from its index.
+pub fn run_with(
+    vault: &Path,
+    notes: Vec<NoteMetaInfo>,
+    detailed: bool,
+    calendar: bool,
+    query: Option<&Query>,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
     let today = Local::now().date_naive();
+    let notes = query::filter(notes, query, today);
 
     let tracked: Vec<_> = notes
         .iter()
@@ -51,6 +71,36 @@ pub fn run(
         })
         .count();
 
-    output::format_stats(total, seedling, budding, evergreen, due_today, overdue, format);
+    let events = if detailed || calendar {
+        let vault_canonical = std::fs::canonicalize(vault)
+            .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+        let events = history::load(&vault_canonical);
+        if query.is_some() {
+            // Restrict history to the selected notes
+            let selected: HashSet<&Path> = tracked.iter().map(|n| n.path.as_path()).collect();
+            events
+                .into_iter()
+                .filter(|e| selected.contains(e.path()))
+                .collect()
+        } else {
+            events
+        }
+    } else {
+        Vec::new()
+    };
+    let details = detailed.then(|| detailed_stats(&tracked, &events, today));
+    let review_calendar = calendar.then(|| review_calendar(&tracked, &events, today));
+
+    output::format_stats(
+        total,
+        seedling,
+        budding,
+        evergreen,
+        due_today,
+        overdue,
+        details.as_ref(),
+        review_calendar.as_ref(),
+        format,
+    );
     Ok(())
 }
@@ -0,0 +1,130 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+use chrono::Local;
+use notify::event::{EventKind, ModifyKind, RenameMode};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+
+use crate::cli::OutputFormat;
+use crate::error::SproutError;
+use crate::index::{IndexEvent, VaultIndex};
+use crate::note::ScanOptions;
+use crate::output;
+
+/// Events arriving within this window after the first are applied together,
+/// so that an editor's save (temp file, rename, chmod) is one update.
+const DEBOUNCE: Duration = Duration::from_millis(100);
+/// How often to check whether the date has changed when the vault is idle.
+const TICK: Duration = Duration::from_secs(60);
+
+/// Paths touched by a batch of filesystem events.
+#[derive(Default)]
+pub struct Batch {
+    paths: Vec<PathBuf>,
+    renames: Vec<(PathBuf, PathBuf)>,
+}
+
+impl Batch {
+    fn add(&mut self, event: notify::Result<notify::Event>, vault: &Path) {
+        match event {
+            Ok(event) if event.need_rescan() => self.paths.push(vault.to_path_buf()),
+            Ok(event) => match event.kind {
+                EventKind::Access(_) => {}
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                    self.renames.push((event.paths[0].clone(), event.paths[1].clone()));
+                }
+                _ => self.paths.extend(event.paths),
+            },
+            // Dropped events (e.g. queue overflow): resynchronise everything
+            Err(_) => self.paths.push(vault.to_path_buf()),
+        }
+    }
+
+    /// Update `index` with the batch; no-op when empty.
+    pub fn apply(&self, index: &mut VaultIndex) -> Vec<IndexEvent> {
+        if self.paths.is_empty() && self.renames.is_empty() {
+            return Vec::new();
+        }
+        index.apply(&self.paths, &self.renames)
+    }
+}
+
+/// Recursive inotify watch on a vault, delivering events in batches.
+pub struct VaultWatcher {
+    vault: PathBuf,
+    rx: Receiver<notify::Result<notify::Event>>,
+    // Dropping the watcher stops the watch
+    _watcher: RecommendedWatcher,
+}
+
+impl VaultWatcher {
+    /// `vault` must be canonical, as `VaultIndex::vault` is.
+    pub fn start(vault: &Path) -> Result<Self, SproutError> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|e| SproutError::WatchError(e.to_string()))?;
+        watcher
+            .watch(vault, RecursiveMode::Recursive)
+            .map_err(|e| SproutError::WatchError(e.to_string()))?;
+        Ok(VaultWatcher {
+            vault: vault.to_path_buf(),
+            rx,
+            _watcher: watcher,
+        })
+    }
+
+    /// Wait up to `timeout` for a change, then keep collecting for `DEBOUNCE`.
+    fn wait(&self, timeout: Duration) -> Result<Batch, SproutError> {
+        let mut batch = Batch::default();
+        match self.rx.recv_timeout(timeout) {
+            Ok(event) => {
+                batch.add(event, &self.vault);
+                let deadline = Instant::now() + DEBOUNCE;
+                while let Ok(event) = self.rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                    batch.add(event, &self.vault);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(SproutError::WatchError("watcher stopped".into()));
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Events already delivered, without waiting.
+    pub fn pending(&self) -> Batch {
+        let mut batch = Batch::default();
+        while let Ok(event) = self.rx.try_recv() {
+            batch.add(event, &self.vault);
+        }
+        batch
+    }
+}
+
+/// Watch the vault until interrupted. The frontmatter cache is updated on
+/// every change; with `events`, index events are printed one per line.
+pub fn run(
+    vault: &Path,
+    scan_options: &ScanOptions,
+    events: bool,
+    format: &OutputFormat,
+) -> Result<(), SproutError> {
+    let mut index = VaultIndex::build(vault, scan_options, Local::now().date_naive())?;
+    let watcher = VaultWatcher::start(index.vault())?;
+
+    if events {
+        output::format_watch_event(&index.ready_event(), format);
+    }
+
+    loop {
+        let mut changes = watcher.wait(TICK)?.apply(&mut index);
+        changes.extend(index.tick(Local::now().date_naive()));
+        if events {
+            for event in &changes {
+                output::format_watch_event(event, format);
+            }
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use anyhow::Result;
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Deserialize, Default)]
+use crate::naming::{FilenameScheme, IdScheme};
+use crate::note::ScanOptions;
+use crate::srs::SrsParams;
+
+#[derive(Deserialize, Default, Clone)]
 pub struct Config {
     pub vault_path: Option<PathBuf>,
     pub max_interval: Option<u32>,
@@ -14,6 +19,15 @@ pub struct Config {
     pub template_dir: Option<PathBuf>,
     pub default_template: Option<String>,
     pub allow_template_exec: Option<bool>,
+    pub filename_scheme: Option<FilenameScheme>,
+    pub id_scheme: Option<IdScheme>,
+    pub default_note_dir: Option<PathBuf>,
+    pub template_note_dirs: Option<HashMap<String, PathBuf>>,
+    pub hard_ease_delta: Option<f64>,
+    pub easy_ease_delta: Option<f64>,
+    pub good_multiplier: Option<f64>,
+    pub respect_gitignore: Option<bool>,
+    pub extensions: Option<Vec<String>>,
 }
 
 impl Config {
@@ -39,6 +53,26 @@ impl Config {
             .unwrap_or_else(|| vec![".git".into(), ".obsidian".into(), ".trash".into()])
     }
 
+    pub fn respect_gitignore(&self) -> bool {
+        self.respect_gitignore.unwrap_or(false)
+    }
+
+    /// Note file extensions, with any leading dot stripped.
+    pub fn extensions(&self) -> Vec<String> {
+        match &self.extensions {
+            Some(exts) => exts.iter().map(|e| e.trim_start_matches('.').to_string()).collect(),
+            None => vec!["md".into()],
+        }
+    }
+
+    pub fn scan_options(&self) -> ScanOptions {
+        ScanOptions {
+            exclude_dirs: self.exclude_dirs(),
+            respect_gitignore: self.respect_gitignore(),
+            extensions: self.extensions(),
+        }
+    }
+
     pub fn auto_init(&self) -> bool {
         self.auto_init.unwrap_or(true)
     }
@@ -61,6 +95,34 @@ impl Config {
     pub fn allow_template_exec(&self) -> bool {
         self.allow_template_exec.unwrap_or(false)
     }
+
+    pub fn filename_scheme(&self) -> FilenameScheme {
+        self.filename_scheme.unwrap_or_default()
+    }
+
+    pub fn id_scheme(&self) -> IdScheme {
+        self.id_scheme.unwrap_or_default()
+    }
+
+    /// Directory of a new note made from `template`, relative to the vault:
+    /// the template's entry in `template_note_dirs`, else `default_note_dir`.
+    /// `None` for the vault root.
+    pub fn note_dir(&self, template: &str) -> Option<&Path> {
+        self.template_note_dirs
+            .as_ref()
+            .and_then(|dirs| dirs.get(template))
+            .or(self.default_note_dir.as_ref())
+            .map(PathBuf::as_path)
+    }
+
+    pub fn srs_params(&self) -> SrsParams {
+        let defaults = SrsParams::default();
+        SrsParams {
+            hard_ease_delta: self.hard_ease_delta.unwrap_or(defaults.hard_ease_delta),
+            easy_ease_delta: self.easy_ease_delta.unwrap_or(defaults.easy_ease_delta),
+            good_multiplier: self.good_multiplier.unwrap_or(defaults.good_multiplier),
+        }
+    }
 }
 
 pub fn load_config() -> Result<Config> {
@@ -141,6 +203,12 @@ mod tests {
         assert!(config.auto_init());
         assert_eq!(config.default_template(), "default");
         assert!(!config.allow_template_exec());
+        assert_eq!(config.filename_scheme(), FilenameScheme::Title);
+        assert_eq!(config.id_scheme(), IdScheme::Timestamp);
+        assert_eq!(config.note_dir("default"), None);
+        assert_eq!(config.srs_params(), SrsParams::default());
+        assert!(!config.respect_gitignore());
+        assert_eq!(config.extensions(), vec!["md".to_string()]);
     }
 
     #[test]
@@ -156,6 +224,15 @@ mod tests {
             template_dir: Some(PathBuf::from("/templates")),
             default_template: Some("custom".into()),
             allow_template_exec: Some(true),
+            filename_scheme: Some(FilenameScheme::IdSlug),
+            id_scheme: Some(IdScheme::Luhmann),
+            default_note_dir: Some(PathBuf::from("inbox")),
+            template_note_dirs: Some(HashMap::from([("daily".into(), PathBuf::from("journal"))])),
+            hard_ease_delta: Some(0.2),
+            easy_ease_delta: Some(0.1),
+            good_multiplier: Some(0.9),
+            respect_gitignore: Some(true),
+            extensions: Some(vec![".md".into(), "org".into()]),
         };
         assert_eq!(config.max_interval(), 180);
         assert!((config.default_ease() - 3.0).abs() < f64::EPSILON);
@@ -166,6 +243,16 @@ mod tests {
         assert_eq!(config.template_dir(), PathBuf::from("/templates"));
         assert_eq!(config.default_template(), "custom");
         assert!(config.allow_template_exec());
+        assert_eq!(config.filename_scheme(), FilenameScheme::IdSlug);
+        assert_eq!(config.id_scheme(), IdScheme::Luhmann);
+        assert_eq!(config.note_dir("daily"), Some(Path::new("journal")));
+        assert_eq!(config.note_dir("custom"), Some(Path::new("inbox")));
+        let params = config.srs_params();
+        assert!((params.hard_ease_delta - 0.2).abs() < f64::EPSILON);
+        assert!((params.easy_ease_delta - 0.1).abs() < f64::EPSILON);
+        assert!((params.good_multiplier - 0.9).abs() < f64::EPSILON);
+        assert!(config.scan_options().respect_gitignore);
+        assert_eq!(config.extensions(), vec!["md".to_string(), "org".to_string()]);
     }
 
     #[test]
@@ -184,6 +271,12 @@ default_ease = 2.8
 link_weight = 0.15
 load_balance = false
 exclude_dirs = [".git", "archive"]
+filename_scheme = "id-slug"
+id_scheme = "luhmann"
+default_note_dir = "inbox"
+
+[template_note_dirs]
+daily = "journal/daily"
 "#;
         let config = parse_config(toml).unwrap();
         assert_eq!(config.vault_path, Some(PathBuf::from("/home/user/notes")));
@@ -195,6 +288,10 @@ exclude_dirs = [".git", "archive"]
             config.exclude_dirs(),
             vec![".git".to_string(), "archive".to_string()]
         );
+        assert_eq!(config.filename_scheme(), FilenameScheme::IdSlug);
+        assert_eq!(config.id_scheme(), IdScheme::Luhmann);
+        assert_eq!(config.note_dir("daily"), Some(Path::new("journal/daily")));
+        assert_eq!(config.note_dir("default"), Some(Path::new("inbox")));
     }
 
     #[test]
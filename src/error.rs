@@ -23,11 +23,35 @@ pub enum SproutError {
     #[error("{0}: invalid note title")]
     InvalidTitle(String),
 
+    #[error("{{{{{0}}}}}: unknown template variable")]
+    UnknownTemplateVariable(String),
+
     #[error("editor not configured: set $VISUAL or $EDITOR")]
     EditorNotFound,
 
     #[error("fzf failed: {0}")]
     FzfError(String),
+
+    #[error("not enough review history: {0} follow-up reviews (need {1})")]
+    InsufficientHistory(usize, usize),
+
+    #[error("{0} of {1} notes failed")]
+    BulkFailed(usize, usize),
+
+    #[error("{0} broken links")]
+    BrokenLinks(usize),
+
+    #[error("cache: {0}")]
+    CacheError(String),
+
+    #[error("watch failed: {0}")]
+    WatchError(String),
+
+    #[error("serve failed: {0}")]
+    ServeError(String),
+
+    #[error("lsp failed: {0}")]
+    LspError(String),
 }
 
 impl SproutError {
@@ -40,8 +64,16 @@ impl SproutError {
             SproutError::AlreadyInitialized(_) => "already_initialized",
             SproutError::ParseError(_) => "parse_error",
             SproutError::InvalidTitle(_) => "invalid_title",
+            SproutError::UnknownTemplateVariable(_) => "unknown_template_variable",
             SproutError::EditorNotFound => "editor_not_found",
             SproutError::FzfError(_) => "fzf_error",
+            SproutError::InsufficientHistory(..) => "insufficient_history",
+            SproutError::BulkFailed(..) => "bulk_failed",
+            SproutError::BrokenLinks(_) => "broken_links",
+            SproutError::CacheError(_) => "cache_error",
+            SproutError::WatchError(_) => "watch_error",
+            SproutError::ServeError(_) => "serve_error",
+            SproutError::LspError(_) => "lsp_error",
         }
     }
 }
@@ -95,11 +127,25 @@ mod tests {
             SproutError::InvalidTitle("x".into()).error_code(),
             "invalid_title"
         );
+        assert_eq!(
+            SproutError::UnknownTemplateVariable("x".into()).error_code(),
+            "unknown_template_variable"
+        );
         assert_eq!(SproutError::EditorNotFound.error_code(), "editor_not_found");
         assert_eq!(
             SproutError::FzfError("x".into()).error_code(),
             "fzf_error"
         );
+        assert_eq!(
+            SproutError::InsufficientHistory(3, 10).error_code(),
+            "insufficient_history"
+        );
+        assert_eq!(SproutError::BulkFailed(1, 3).error_code(), "bulk_failed");
+        assert_eq!(SproutError::BrokenLinks(2).error_code(), "broken_links");
+        assert_eq!(SproutError::CacheError("x".into()).error_code(), "cache_error");
+        assert_eq!(SproutError::WatchError("x".into()).error_code(), "watch_error");
+        assert_eq!(SproutError::ServeError("x".into()).error_code(), "serve_error");
+        assert_eq!(SproutError::LspError("x".into()).error_code(), "lsp_error");
     }
 
     #[test]
@@ -121,5 +167,8 @@ mod tests {
             e.to_string(),
             "note.md: already initialized with all sprout fields"
         );
+
+        let e = SproutError::UnknownTemplateVariable("auther".into());
+        assert_eq!(e.to_string(), "{{auther}}: unknown template variable");
     }
 }
@@ -2,8 +2,11 @@ use chrono::NaiveDate;
 use gray_matter::{engine::YAML, Matter};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+use crate::org;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct SproutFrontmatter {
     pub maturity: Option<String>,
     pub created: Option<NaiveDate>,
@@ -13,14 +16,211 @@ pub struct SproutFrontmatter {
     pub ease: Option<f64>,
 }
 
+impl SproutFrontmatter {
+    /// Each sprout field's name and whether it is set, in canonical field order.
+    fn presence(&self) -> [(&'static str, bool); 6] {
+        [
+            ("maturity", self.maturity.is_some()),
+            ("created", self.created.is_some()),
+            ("last_review", self.last_review.is_some()),
+            ("review_interval", self.review_interval.is_some()),
+            ("next_review", self.next_review.is_some()),
+            ("ease", self.ease.is_some()),
+        ]
+    }
+
+    /// Names of sprout fields that are absent, in canonical field order.
+    pub fn missing_fields(&self) -> Vec<&'static str> {
+        self.presence()
+            .iter()
+            .filter(|(_, p)| !p)
+            .map(|(name, _)| *name)
+            .collect()
+    }
+
+    /// No sprout field is set: the note is not tracked at all.
+    pub fn is_untracked(&self) -> bool {
+        self.presence().iter().all(|(_, p)| !p)
+    }
+}
+
+/// Where a note keeps its sprout fields, decided by file extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NoteFormat {
+    /// YAML frontmatter between `---` lines (`.md`, `.markdown`, `.mdx`, ...)
+    #[default]
+    Markdown,
+    /// File-level `:PROPERTIES:` drawer (`.org`)
+    Org,
+}
+
+impl NoteFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("org") => NoteFormat::Org,
+            _ => NoteFormat::Markdown,
+        }
+    }
+
+    pub fn parse(self, content: &str) -> ParsedNote {
+        match self {
+            NoteFormat::Markdown => parse_note(content),
+            NoteFormat::Org => org::parse_note(content),
+        }
+    }
+
+    pub fn has_field(self, raw: &str, key: &str) -> bool {
+        match self {
+            NoteFormat::Markdown => has_field(raw, key),
+            NoteFormat::Org => org::has_field(raw, key),
+        }
+    }
+
+    pub fn append_field(self, raw: &str, key: &str, value: &str) -> String {
+        match self {
+            NoteFormat::Markdown => append_field(raw, key, value),
+            NoteFormat::Org => org::append_field(raw, key, value),
+        }
+    }
+
+    pub fn write_back(self, raw: &str, body: &str, updates: &[(&str, &str)]) -> String {
+        match self {
+            NoteFormat::Markdown => write_back(raw, body, updates),
+            NoteFormat::Org => org::write_back(raw, body, updates),
+        }
+    }
+
+    pub fn build_new_frontmatter(self, fields: &[(&str, &str)], body: &str) -> String {
+        match self {
+            NoteFormat::Markdown => build_new_frontmatter(fields, body),
+            NoteFormat::Org => org::build_new_drawer(fields, body),
+        }
+    }
+
+    /// Sprout fields of `content` whose values cannot be read. Such a field
+    /// is ignored in org notes; in markdown the whole frontmatter is.
+    pub fn check_fields(self, content: &str) -> Vec<FieldError> {
+        let content = content.replace("\r\n", "\n");
+        let fields = match self {
+            NoteFormat::Markdown => yaml_fields(&content),
+            NoteFormat::Org => org::drawer_fields(&content),
+        };
+        let mut errors: Vec<FieldError> = fields
+            .into_iter()
+            .filter_map(|(line, key, value)| {
+                let message = self.check_value(&key, &value)?;
+                Some(FieldError { line, message: format!("{key}: {message}") })
+            })
+            .collect();
+        // Anything else gray_matter rejects, such as a YAML syntax error
+        if self == NoteFormat::Markdown
+            && errors.is_empty()
+            && yaml_block(&content).is_some_and(|(_, end)| end > 1)
+            && parse_note(&content).frontmatter_raw.is_none()
+        {
+            errors.push(FieldError { line: 0, message: "frontmatter is not valid YAML".to_string() });
+        }
+        errors
+    }
+
+    fn check_value(self, key: &str, value: &str) -> Option<String> {
+        match key {
+            "maturity" => match value {
+                "seedling" | "budding" | "evergreen" => None,
+                _ => Some(format!("unknown maturity `{value}` (expected seedling, budding or evergreen)")),
+            },
+            "created" | "last_review" | "next_review" => {
+                let valid = match self {
+                    NoteFormat::Markdown => NaiveDate::parse_from_str(value, "%Y-%m-%d").is_ok(),
+                    NoteFormat::Org => org::parse_date(value).is_some(),
+                };
+                (!valid).then(|| format!("invalid date `{value}` (expected YYYY-MM-DD)"))
+            }
+            "review_interval" => value
+                .parse::<u32>()
+                .is_err()
+                .then(|| format!("invalid interval `{value}` (expected a whole number of days)")),
+            "ease" => (!value.parse::<f64>().is_ok_and(f64::is_finite))
+                .then(|| format!("invalid ease `{value}` (expected a number)")),
+            _ => None,
+        }
+    }
+}
+
+/// A sprout field value that cannot be read, found by `NoteFormat::check_fields`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    /// Zero-based line of the field in the note
+    pub line: usize,
+    pub message: String,
+}
+
+/// Sprout field names in canonical order.
+pub const SPROUT_FIELDS: [&str; 6] = ["maturity", "created", "last_review", "review_interval", "next_review", "ease"];
+
+/// Line indices of the opening and closing `---` of a leading YAML block.
+fn yaml_block(content: &str) -> Option<(usize, usize)> {
+    let mut lines = content.lines().enumerate();
+    let (_, first) = lines.next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+    lines.find(|(_, l)| l.trim_end() == "---").map(|(end, _)| (0, end))
+}
+
+/// Top-level sprout keys of the YAML frontmatter with their line and value.
+/// Empty and null values count as absent.
+fn yaml_fields(content: &str) -> Vec<(usize, String, String)> {
+    let Some((start, end)) = yaml_block(content) else {
+        return Vec::new();
+    };
+    let re = Regex::new(r"^([A-Za-z_]+)\s*:[ \t]*(.*)$").unwrap();
+    content
+        .lines()
+        .enumerate()
+        .take(end)
+        .skip(start + 1)
+        .filter_map(|(i, line)| {
+            let cap = re.captures(line)?;
+            let key = SPROUT_FIELDS.iter().find(|k| **k == &cap[1])?;
+            let value = yaml_scalar(&cap[2]);
+            if value.is_empty() || value == "~" || value == "null" {
+                return None;
+            }
+            Some((i, key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+/// The scalar on a `key: value` line, without quotes or a trailing comment.
+fn yaml_scalar(raw: &str) -> &str {
+    let raw = raw.trim();
+    for quote in ['"', '\''] {
+        if let Some(rest) = raw.strip_prefix(quote) {
+            return rest.split(quote).next().unwrap_or("");
+        }
+    }
+    match raw.find(" #") {
+        Some(i) => raw[..i].trim_end(),
+        None => raw,
+    }
+}
+
 #[derive(Debug)]
 pub struct ParsedNote {
-    /// Raw YAML string from gray_matter (for string-based write-back)
+    /// Raw YAML string from gray_matter, or the property drawer lines for
+    /// org notes (for string-based write-back)
     pub frontmatter_raw: Option<String>,
     /// Deserialized sprout fields
     pub sprout: SproutFrontmatter,
     /// Note body (content after frontmatter)
     pub body: String,
+    pub format: NoteFormat,
+}
+
+/// Parse a note using the frontmatter format implied by its path.
+pub fn parse_note_at(path: &Path, content: &str) -> ParsedNote {
+    NoteFormat::from_path(path).parse(content)
 }
 
 /// Parse note content into frontmatter + body.
@@ -40,6 +240,7 @@ pub fn parse_note(content: &str) -> ParsedNote {
                 frontmatter_raw: raw,
                 sprout: parsed.data.unwrap_or_default(),
                 body: parsed.content,
+                format: NoteFormat::Markdown,
             }
         }
         Err(_) => {
@@ -48,11 +249,94 @@ pub fn parse_note(content: &str) -> ParsedNote {
                 frontmatter_raw: None,
                 sprout: SproutFrontmatter::default(),
                 body: content,
+                format: NoteFormat::Markdown,
             }
         }
     }
 }
 
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AliasesField {
+    List(Vec<String>),
+    One(String),
+}
+
+#[derive(Deserialize)]
+struct AliasesFrontmatter {
+    aliases: Option<AliasesField>,
+    alias: Option<AliasesField>,
+}
+
+/// Alternative names of a note: the YAML `aliases` (or `alias`) field, a
+/// list or a comma separated string, or org-roam's `:ROAM_ALIASES:`
+/// property. Trimmed and deduplicated.
+pub fn extract_aliases(format: NoteFormat, content: &str) -> Vec<String> {
+    let found = match format {
+        NoteFormat::Org => org::roam_aliases(content),
+        NoteFormat::Markdown => {
+            let content = content.replace("\r\n", "\n");
+            let matter: Matter<YAML> = Matter::new();
+            let data = matter.parse::<AliasesFrontmatter>(&content).ok().and_then(|p| p.data);
+            data.into_iter()
+                .flat_map(|d| [d.aliases, d.alias])
+                .flatten()
+                .flat_map(|field| match field {
+                    AliasesField::List(list) => list,
+                    AliasesField::One(s) => s.split(',').map(str::to_string).collect(),
+                })
+                .collect()
+        }
+    };
+    let mut aliases: Vec<String> = Vec::new();
+    for alias in found {
+        let alias = alias.trim();
+        if !alias.is_empty() && !aliases.iter().any(|a| a == alias) {
+            aliases.push(alias.to_string());
+        }
+    }
+    aliases
+}
+
+#[derive(Deserialize)]
+struct TitleFrontmatter {
+    title: Option<String>,
+}
+
+/// The YAML `title` field of a markdown note, trimmed, if it is a non-empty
+/// string.
+pub fn extract_title(content: &str) -> Option<String> {
+    let content = content.replace("\r\n", "\n");
+    let matter: Matter<YAML> = Matter::new();
+    let title = matter.parse::<TitleFrontmatter>(&content).ok()?.data?.title?;
+    let title = title.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum IdField {
+    Text(String),
+    Number(u64),
+}
+
+#[derive(Deserialize)]
+struct IdFrontmatter {
+    id: Option<IdField>,
+}
+
+/// The YAML `id` field of a markdown note, trimmed and lowercased, if it is
+/// a non-empty string or a number (`id: 1` for a Luhmann ID).
+pub fn extract_id(content: &str) -> Option<String> {
+    let content = content.replace("\r\n", "\n");
+    let matter: Matter<YAML> = Matter::new();
+    let id = match matter.parse::<IdFrontmatter>(&content).ok()?.data?.id? {
+        IdField::Text(s) => s.trim().to_lowercase(),
+        IdField::Number(n) => n.to_string(),
+    };
+    (!id.is_empty()).then_some(id)
+}
+
 /// Replace the value of an existing YAML key, preserving inline comments.
 /// Pattern: ^(key\s*:\s*)(\S+)(.*)$
 pub fn replace_field(yaml: &str, key: &str, new_value: &str) -> String {
@@ -108,6 +392,44 @@ pub fn build_new_frontmatter(fields: &[(&str, &str)], body: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_aliases() {
+        let md = NoteFormat::Markdown;
+        assert_eq!(extract_aliases(md, "---\naliases: [ML, Machine Learning]\n---\n"), vec!["ML", "Machine Learning"]);
+        assert_eq!(extract_aliases(md, "---\naliases:\n  - ML\n  - ML\nalias: AI, KI\n---\n"), vec!["ML", "AI", "KI"]);
+        assert!(extract_aliases(md, "No frontmatter\n").is_empty());
+        let org = ":PROPERTIES:\n:ID: 1\n:ROAM_ALIASES: ML \"Machine Learning\"\n:END:\n";
+        assert_eq!(extract_aliases(NoteFormat::Org, org), vec!["ML", "Machine Learning"]);
+    }
+
+    #[test]
+    fn test_extract_title() {
+        assert_eq!(extract_title("---\ntitle: \" My Note \"\n---\n# Heading\n").as_deref(), Some("My Note"));
+        assert_eq!(extract_title("---\ntitle: \"\"\n---\n"), None);
+        assert_eq!(extract_title("# Heading\n"), None);
+    }
+
+    #[test]
+    fn test_extract_id() {
+        assert_eq!(extract_id("---\nid: 1A2b\n---\n").as_deref(), Some("1a2b"));
+        assert_eq!(extract_id("---\nid: 202610171530\n---\n").as_deref(), Some("202610171530"));
+        assert_eq!(extract_id("---\nid: \" \"\n---\n"), None);
+        assert_eq!(extract_id("---\ntitle: x\n---\n"), None);
+    }
+
+    #[test]
+    fn test_check_fields() {
+        let content = "---\nmaturity: sapling\ncreated: 2026-01-01\nnext_review: '2026-13-01'\nreview_interval: 1.5 # days\nease: 2.5\ntitle: x\n---\nease: nope\n";
+        let errors = NoteFormat::Markdown.check_fields(content);
+        let lines: Vec<usize> = errors.iter().map(|e| e.line).collect();
+        assert_eq!(lines, vec![1, 3, 4]);
+        assert!(errors[1].message.starts_with("next_review: invalid date `2026-13-01`"));
+        assert!(errors[2].message.contains("`1.5`"));
+
+        assert!(NoteFormat::Markdown.check_fields("---\nmaturity: seedling\nease: ~\n---\n").is_empty());
+        assert_eq!(NoteFormat::Markdown.check_fields("---\ntags: [a\n---\n")[0].line, 0);
+    }
+
     #[test]
     fn test_parse_note_with_frontmatter() {
         let content = "---\nmaturity: seedling\nease: 2.5\n---\nHello world\n";
@@ -134,6 +456,18 @@ mod tests {
         assert_eq!(parsed.sprout.maturity.as_deref(), Some("seedling"));
     }
 
+    #[test]
+    fn test_missing_fields() {
+        let parsed = parse_note("---\nmaturity: seedling\nease: 2.5\n---\n");
+        assert_eq!(
+            parsed.sprout.missing_fields(),
+            vec!["created", "last_review", "review_interval", "next_review"]
+        );
+        assert_eq!(SproutFrontmatter::default().missing_fields().len(), 6);
+        assert!(SproutFrontmatter::default().is_untracked());
+        assert!(!parsed.sprout.is_untracked());
+    }
+
     #[test]
     fn test_replace_field_preserves_comment() {
         let yaml = "review_interval: 3  # days\nease: 2.50\n";
@@ -270,6 +604,16 @@ mod tests {
         assert!((parsed.sprout.ease.unwrap() - 2.65).abs() < 0.001);
     }
 
+    #[test]
+    fn test_note_format_from_path() {
+        assert_eq!(NoteFormat::from_path(Path::new("a.md")), NoteFormat::Markdown);
+        assert_eq!(NoteFormat::from_path(Path::new("a.mdx")), NoteFormat::Markdown);
+        assert_eq!(NoteFormat::from_path(Path::new("dir/a.ORG")), NoteFormat::Org);
+        let parsed = parse_note_at(Path::new("a.org"), ":PROPERTIES:\n:MATURITY: seedling\n:END:\n");
+        assert_eq!(parsed.format, NoteFormat::Org);
+        assert_eq!(parsed.sprout.maturity.as_deref(), Some("seedling"));
+    }
+
     #[test]
     fn test_parse_note_with_unknown_keys() {
         let content = "---\ntags: [rust, zettelkasten]\nmaturity: budding\ncssclasses: note\n---\nBody\n";
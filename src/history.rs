@@ -0,0 +1,222 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::Rating;
+
+/// A single `sprout done` outcome, with the scheduler inputs that produced it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewRecord {
+    pub date: NaiveDate,
+    pub path: PathBuf,
+    pub rating: Rating,
+    pub maturity: String,
+    /// Interval before this review
+    pub interval: u32,
+    /// Ease before this review
+    pub ease: f64,
+    /// Days past `next_review` at review time
+    pub delayed: i64,
+    pub link_count: usize,
+    pub new_interval: u32,
+    pub new_ease: f64,
+}
+
+/// A maturity change made with `sprout promote`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromoteRecord {
+    pub date: NaiveDate,
+    pub path: PathBuf,
+    pub from: String,
+    pub to: String,
+}
+
+/// One line of the history log. Tagged so new event kinds can be added
+/// without breaking older logs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+pub enum HistoryEvent {
+    Review(ReviewRecord),
+    Promote(PromoteRecord),
+}
+
+impl HistoryEvent {
+    pub fn date(&self) -> NaiveDate {
+        match self {
+            HistoryEvent::Review(r) => r.date,
+            HistoryEvent::Promote(p) => p.date,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            HistoryEvent::Review(r) => &r.path,
+            HistoryEvent::Promote(p) => &p.path,
+        }
+    }
+}
+
+/// Append an event to the history log. Failures are reported as warnings:
+/// the note itself has already been updated and must not be rolled back.
+pub fn append(event: &HistoryEvent) {
+    let path = match history_path() {
+        Some(p) => p,
+        None => return,
+    };
+    if let Err(e) = append_to(&path, event) {
+        eprintln!("warning: failed to write history {}: {e}", path.display());
+    }
+}
+
+fn append_to(path: &Path, event: &HistoryEvent) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(event).map_err(std::io::Error::other)?;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    // Single write so concurrent appenders never interleave within a line
+    file.write_all(format!("{line}\n").as_bytes())
+}
+
+/// Load all events for notes inside `vault`, oldest first.
+/// Unparseable lines are skipped.
+pub fn load(vault: &Path) -> Vec<HistoryEvent> {
+    match history_path() {
+        Some(p) => load_from(&p, vault),
+        None => Vec::new(),
+    }
+}
+
+/// Load only the review records for notes inside `vault`, oldest first.
+pub fn load_reviews(vault: &Path) -> Vec<ReviewRecord> {
+    reviews(load(vault))
+}
+
+pub fn reviews(events: Vec<HistoryEvent>) -> Vec<ReviewRecord> {
+    events
+        .into_iter()
+        .filter_map(|event| match event {
+            HistoryEvent::Review(r) => Some(r),
+            _ => None,
+        })
+        .collect()
+}
+
+fn load_from(path: &Path, vault: &Path) -> Vec<HistoryEvent> {
+    let data = match std::fs::read_to_string(path) {
+        Ok(d) => d,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut events: Vec<HistoryEvent> = data
+        .lines()
+        .filter_map(|line| serde_json::from_str::<HistoryEvent>(line).ok())
+        .filter(|e| e.path().starts_with(vault))
+        .collect();
+
+    // Stable sort keeps same-day events in log order
+    events.sort_by_key(|e| e.date());
+    events
+}
+
+fn history_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("sprout").join("history.jsonl"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn record(path: &str, date: NaiveDate, rating: Rating) -> ReviewRecord {
+        ReviewRecord {
+            date,
+            path: PathBuf::from(path),
+            rating,
+            maturity: "seedling".into(),
+            interval: 3,
+            ease: 2.5,
+            delayed: 0,
+            link_count: 1,
+            new_interval: 6,
+            new_ease: 2.5,
+        }
+    }
+
+    #[test]
+    fn test_append_and_load_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("history.jsonl");
+        let d1 = NaiveDate::from_ymd_opt(2026, 3, 2).unwrap();
+        let d0 = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+
+        append_to(&log, &HistoryEvent::Review(record("/vault/b.md", d1, Rating::Hard))).unwrap();
+        append_to(&log, &HistoryEvent::Review(record("/vault/a.md", d0, Rating::Good))).unwrap();
+
+        let loaded = reviews(load_from(&log, Path::new("/vault")));
+        assert_eq!(loaded.len(), 2);
+        // Sorted by date
+        assert_eq!(loaded[0].path, PathBuf::from("/vault/a.md"));
+        assert_eq!(loaded[1].rating, Rating::Hard);
+    }
+
+    #[test]
+    fn test_load_filters_other_vaults() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("history.jsonl");
+        let d = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+
+        append_to(&log, &HistoryEvent::Review(record("/vault/a.md", d, Rating::Good))).unwrap();
+        append_to(&log, &HistoryEvent::Review(record("/other/a.md", d, Rating::Good))).unwrap();
+
+        let loaded = load_from(&log, Path::new("/vault"));
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn test_load_skips_corrupt_lines() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("history.jsonl");
+        let d = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        append_to(&log, &HistoryEvent::Review(record("/vault/a.md", d, Rating::Easy))).unwrap();
+        let mut f = std::fs::OpenOptions::new().append(true).open(&log).unwrap();
+        writeln!(f, "not json").unwrap();
+
+        let loaded = load_from(&log, Path::new("/vault"));
+        assert_eq!(loaded.len(), 1);
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let loaded = load_from(Path::new("/nonexistent/history.jsonl"), Path::new("/"));
+        assert!(loaded.is_empty());
+    }
+
+    #[test]
+    fn test_promote_events_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("history.jsonl");
+        let d = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        append_to(&log, &HistoryEvent::Review(record("/vault/a.md", d, Rating::Good))).unwrap();
+        append_to(
+            &log,
+            &HistoryEvent::Promote(PromoteRecord {
+                date: d,
+                path: PathBuf::from("/vault/a.md"),
+                from: "seedling".into(),
+                to: "budding".into(),
+            }),
+        )
+        .unwrap();
+
+        let events = load_from(&log, Path::new("/vault"));
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[1], HistoryEvent::Promote(p) if p.to == "budding"));
+        assert_eq!(reviews(events).len(), 1);
+    }
+}
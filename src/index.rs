@@ -0,0 +1,747 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+use crate::cache::FrontmatterCache;
+use crate::error::SproutError;
+use crate::note::{self, NoteMetaInfo, ScanOptions, SPROUTIGNORE};
+
+/// A change to the vault observed by `VaultIndex::apply` or `VaultIndex::tick`.
+/// Paths are absolute; `relative_path` is relative to the vault.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IndexEvent {
+    /// The initial scan finished
+    Ready { notes: usize, due: usize, broken_links: usize },
+    Added { path: String, relative_path: String },
+    Changed { path: String, relative_path: String },
+    Removed { path: String, relative_path: String },
+    Renamed { from: String, path: String, relative_path: String },
+    /// A note was not due before and is now, after an edit or at midnight
+    Due { path: String, relative_path: String, next_review: Option<NaiveDate> },
+    LinkBroken { path: String, relative_path: String, target: String },
+    LinkFixed { path: String, relative_path: String, target: String },
+}
+
+/// Where a link target points.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Note(PathBuf),
+    /// An existing file that is not a note, such as an image or PDF
+    File(PathBuf),
+    /// A URL or an org link type other than `file:`
+    External,
+    Missing,
+    /// A note without the `#heading` or `#^block-id` the link points into
+    MissingAnchor(PathBuf),
+}
+
+/// In-memory metadata and link index of a vault, kept up to date by applying
+/// filesystem changes. Reads go through the vault's frontmatter cache, which
+/// is saved after every change.
+pub struct VaultIndex {
+    vault: PathBuf,
+    options: ScanOptions,
+    cache: FrontmatterCache,
+    notes: HashMap<PathBuf, NoteMetaInfo>,
+    /// Lowercased relative path and file stem, both without extension → notes
+    names: HashMap<String, BTreeSet<PathBuf>>,
+    /// Lowercased aliases → notes declaring them
+    aliases: HashMap<String, BTreeSet<PathBuf>>,
+    /// Unresolved link targets per note
+    broken: HashMap<PathBuf, BTreeSet<String>>,
+    today: NaiveDate,
+}
+
+impl VaultIndex {
+    pub fn build(vault: &Path, options: &ScanOptions, today: NaiveDate) -> Result<Self, SproutError> {
+        let vault = std::fs::canonicalize(vault)
+            .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+        let cache = FrontmatterCache::load(&vault);
+        Self::build_with(vault, options, cache, today)
+    }
+
+    fn build_with(
+        vault: PathBuf,
+        options: &ScanOptions,
+        mut cache: FrontmatterCache,
+        today: NaiveDate,
+    ) -> Result<Self, SproutError> {
+        let entries = note::scan_vault_paths(&vault, options)
+            .map_err(|e| SproutError::VaultNotFound(e.to_string()))?;
+        let scanned: HashSet<PathBuf> = entries.iter().map(|e| e.path.clone()).collect();
+        cache.prune(&scanned);
+        let metas = note::read_metadata(entries, &mut cache);
+        cache.flush();
+
+        let mut index = VaultIndex {
+            vault,
+            options: options.clone(),
+            cache,
+            notes: HashMap::new(),
+            names: HashMap::new(),
+            aliases: HashMap::new(),
+            broken: HashMap::new(),
+            today,
+        };
+        for meta in metas {
+            index.add_names(&meta);
+            index.notes.insert(meta.path.clone(), meta);
+        }
+        let paths: Vec<PathBuf> = index.notes.keys().cloned().collect();
+        for path in paths {
+            let broken = index.broken_targets(&path);
+            if !broken.is_empty() {
+                index.broken.insert(path, broken);
+            }
+        }
+        Ok(index)
+    }
+
+    pub fn vault(&self) -> &Path {
+        &self.vault
+    }
+
+    /// All indexed notes, in no particular order.
+    pub fn notes(&self) -> Vec<NoteMetaInfo> {
+        self.notes.values().cloned().collect()
+    }
+
+    pub fn note(&self, path: &Path) -> Option<&NoteMetaInfo> {
+        self.notes.get(path)
+    }
+
+    /// Shortest wiki-link target that resolves to `path`: the file stem,
+    /// or the relative path when another note shares the stem.
+    pub fn link_name(&self, path: &Path) -> Option<String> {
+        let meta = self.notes.get(path)?;
+        let [_, stem] = self.name_keys(meta);
+        let name = if self.names.get(&stem).and_then(|set| set.iter().next()) == Some(&meta.path) {
+            meta.path.file_stem()?.to_string_lossy().to_string()
+        } else {
+            self.strip_extension(&meta.relative_path)
+        };
+        Some(name)
+    }
+
+    pub fn ready_event(&self) -> IndexEvent {
+        IndexEvent::Ready {
+            notes: self.notes.len(),
+            due: self.notes.values().filter(|n| n.is_due(self.today)).count(),
+            broken_links: self.broken.values().map(BTreeSet::len).sum(),
+        }
+    }
+
+    /// Re-read the given files and directories (absolute paths, existing or
+    /// not) and report what changed. `renames` pairs old and new paths of
+    /// moves reported by the watcher; their paths need not be repeated in
+    /// `paths`. A change to an ignore file rescans the whole vault.
+    pub fn apply(&mut self, paths: &[PathBuf], renames: &[(PathBuf, PathBuf)]) -> Vec<IndexEvent> {
+        let mut roots: Vec<PathBuf> = paths
+            .iter()
+            .chain(renames.iter().flat_map(|(from, to)| [from, to]))
+            .filter(|p| p.starts_with(&self.vault))
+            .cloned()
+            .collect();
+        if roots.iter().any(|p| self.is_ignore_file(p)) {
+            roots = vec![self.vault.clone()];
+        }
+        if roots.is_empty() {
+            return Vec::new();
+        }
+
+        let present = match note::scan_vault_paths_under(&self.vault, &self.options, &roots) {
+            Ok(p) => p,
+            Err(_) => return Vec::new(),
+        };
+        let present_set: HashSet<PathBuf> = present.iter().map(|p| p.path.clone()).collect();
+        let mut removed: Vec<NoteMetaInfo> = Vec::new();
+        let gone: Vec<PathBuf> = self
+            .notes
+            .keys()
+            .filter(|k| !present_set.contains(*k) && roots.iter().any(|r| k.starts_with(r)))
+            .cloned()
+            .collect();
+        for path in gone {
+            let meta = self.notes.remove(&path).unwrap();
+            self.remove_names(&meta);
+            self.broken.remove(&path);
+            self.cache.remove(&path);
+            removed.push(meta);
+        }
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        let mut due = Vec::new();
+        let mut aliases_changed = false;
+        let mut anchors_changed = false;
+        for meta in note::read_metadata(present, &mut self.cache) {
+            let path = meta.path.clone();
+            let old_aliases = self
+                .notes
+                .get(&path)
+                .filter(|n| n.content.aliases != meta.content.aliases)
+                .map(|n| n.content.aliases.clone());
+            if let Some(old) = old_aliases {
+                for alias in &old {
+                    remove_from(&mut self.aliases, &name_key(alias), &path);
+                }
+                for alias in &meta.content.aliases {
+                    self.aliases.entry(name_key(alias)).or_default().insert(path.clone());
+                }
+                aliases_changed = true;
+            }
+            anchors_changed |= self.notes.get(&path).is_some_and(|n| {
+                n.content.headings != meta.content.headings || n.content.block_ids != meta.content.block_ids
+            });
+            let was_due = self.notes.get(&path).is_some_and(|n| n.is_due(self.today));
+            if meta.is_due(self.today) && !was_due {
+                due.push(path.clone());
+            }
+            match self.notes.get(&path) {
+                None => {
+                    self.add_names(&meta);
+                    added.push(path.clone());
+                }
+                Some(prev) if prev.sprout != meta.sprout || prev.content != meta.content => {
+                    changed.push(path.clone());
+                }
+                Some(_) => {}
+            }
+            self.notes.insert(path, meta);
+        }
+        self.cache.flush();
+
+        // Added or removed notes, new aliases and edited headings can change
+        // how links anywhere resolve
+        let relink: Vec<PathBuf> = if removed.is_empty() && added.is_empty() && !aliases_changed && !anchors_changed {
+            changed.clone()
+        } else {
+            self.notes.keys().cloned().collect()
+        };
+
+        let mut events = Vec::new();
+        removed.sort_by(|a, b| a.path.cmp(&b.path));
+        added.sort();
+        changed.sort();
+        due.sort();
+        for meta in &removed {
+            // Moving a directory renames every note under it
+            let rename = renames.iter().find_map(|(from, to)| {
+                let to = to.join(meta.path.strip_prefix(from).ok()?);
+                added.iter().find(|p| **p == to).cloned()
+            });
+            match rename {
+                Some(to) => {
+                    added.retain(|p| *p != to);
+                    if meta.is_due(self.today) {
+                        due.retain(|p| *p != to);
+                    }
+                    let n = &self.notes[&to];
+                    events.push(IndexEvent::Renamed {
+                        from: path_str(&meta.path),
+                        path: path_str(&to),
+                        relative_path: n.relative_path.clone(),
+                    });
+                }
+                None => events.push(IndexEvent::Removed {
+                    path: path_str(&meta.path),
+                    relative_path: meta.relative_path.clone(),
+                }),
+            }
+        }
+        for path in &added {
+            let (path, relative_path) = self.describe(path);
+            events.push(IndexEvent::Added { path, relative_path });
+        }
+        for path in &changed {
+            let (path, relative_path) = self.describe(path);
+            events.push(IndexEvent::Changed { path, relative_path });
+        }
+        for p in &due {
+            let (path, relative_path) = self.describe(p);
+            let next_review = self.notes[p].sprout.next_review;
+            events.push(IndexEvent::Due { path, relative_path, next_review });
+        }
+        events.extend(self.relink(relink));
+        events
+    }
+
+    /// Report notes that became due because the date changed.
+    pub fn tick(&mut self, today: NaiveDate) -> Vec<IndexEvent> {
+        if today == self.today {
+            return Vec::new();
+        }
+        let previous = std::mem::replace(&mut self.today, today);
+        let mut due: Vec<&NoteMetaInfo> = self
+            .notes
+            .values()
+            .filter(|n| n.is_due(today) && !n.is_due(previous))
+            .collect();
+        due.sort_by(|a, b| a.path.cmp(&b.path));
+        due.into_iter()
+            .map(|n| IndexEvent::Due {
+                path: path_str(&n.path),
+                relative_path: n.relative_path.clone(),
+                next_review: n.sprout.next_review,
+            })
+            .collect()
+    }
+
+    /// Resolve a link target as written in `source`.
+    ///
+    /// Path-like targets are tried against the note's directory and then the
+    /// vault root, with and without a note extension; otherwise the target is
+    /// looked up by relative path or file name, then by alias,
+    /// case-insensitively, as Obsidian does for wiki links. When several notes
+    /// share a name the first by path wins. An anchor (`#heading` or
+    /// `#^block-id`) must name a section of the note, see `section`.
+    pub fn resolve(&self, source: &Path, target: &str) -> LinkTarget {
+        let (path, anchor) = target.split_once('#').unwrap_or((target, ""));
+        match self.resolve_path(source, path) {
+            LinkTarget::Note(note) if !anchor.is_empty() && self.notes.contains_key(&note) => {
+                match self.section(&note, anchor) {
+                    Some(_) => LinkTarget::Note(note),
+                    None => LinkTarget::MissingAnchor(note),
+                }
+            }
+            resolved => resolved,
+        }
+    }
+
+    /// What a link from `source` counts as for `links::count_links`: the note
+    /// or file it resolves to, whatever name, alias or anchor it uses, or the
+    /// name of a missing note. External links do not count.
+    pub fn link_key(&self, source: &Path, target: &str) -> Option<String> {
+        match self.resolve(source, target) {
+            LinkTarget::Note(path) | LinkTarget::MissingAnchor(path) | LinkTarget::File(path) => Some(path_str(&path)),
+            LinkTarget::External => None,
+            LinkTarget::Missing => {
+                let name = target.split('#').next().unwrap_or_default().trim();
+                let name = name.strip_prefix("file:").unwrap_or(name);
+                Some(name_key(&self.strip_extension(&percent_decode(name))))
+            }
+        }
+    }
+
+    /// The heading text, or `^block-id`, of `path` that `anchor` points to.
+    /// Headings match by text or by their GitHub-style slug, ignoring case
+    /// and `%XX` escapes (`Setup Guide`, `setup-guide`); of nested anchors
+    /// (`Install#Linux`) the last heading counts.
+    pub fn section(&self, path: &Path, anchor: &str) -> Option<String> {
+        let content = &self.notes.get(path)?.content;
+        let anchor = percent_decode(anchor);
+        if let Some(id) = anchor.strip_prefix('^') {
+            return content
+                .block_ids
+                .iter()
+                .find(|b| b.eq_ignore_ascii_case(id))
+                .map(|b| format!("^{b}"));
+        }
+        let key = anchor_key(anchor.rsplit('#').next().unwrap_or_default());
+        content.headings.iter().find(|h| anchor_key(h) == key).cloned()
+    }
+
+    fn resolve_path(&self, source: &Path, target: &str) -> LinkTarget {
+        let target = target.trim();
+        let target = target.strip_prefix("file:").unwrap_or(target);
+        if target.is_empty() {
+            return LinkTarget::Note(source.to_path_buf());
+        }
+        if has_scheme(target) {
+            return LinkTarget::External;
+        }
+        let target = percent_decode(target);
+
+        let dir = source.parent().unwrap_or(&self.vault);
+        let bases = [dir.join(&target), self.vault.join(target.trim_start_matches('/'))];
+        for base in &bases {
+            let base = normalize(base);
+            if self.notes.contains_key(&base) {
+                return LinkTarget::Note(base);
+            }
+            for ext in &self.options.extensions {
+                let mut with_ext = base.clone().into_os_string();
+                with_ext.push(format!(".{ext}"));
+                let with_ext = PathBuf::from(with_ext);
+                if self.notes.contains_key(&with_ext) {
+                    return LinkTarget::Note(with_ext);
+                }
+            }
+        }
+
+        let key = name_key(&self.strip_extension(&target));
+        if let Some(path) = self.names.get(&key).and_then(|set| set.iter().next()) {
+            return LinkTarget::Note(path.clone());
+        }
+        if let Some(path) = self.aliases.get(&name_key(&target)).and_then(|set| set.iter().next()) {
+            return LinkTarget::Note(path.clone());
+        }
+        for base in &bases {
+            let base = normalize(base);
+            if base.is_file() {
+                return LinkTarget::File(base);
+            }
+        }
+        LinkTarget::Missing
+    }
+
+    /// Recompute unresolved links for `paths` and report the differences.
+    fn relink(&mut self, mut paths: Vec<PathBuf>) -> Vec<IndexEvent> {
+        paths.sort();
+        let mut events = Vec::new();
+        for path in paths {
+            let now = self.broken_targets(&path);
+            let before = self.broken.remove(&path).unwrap_or_default();
+            let (abs, relative_path) = self.describe(&path);
+            for target in now.difference(&before) {
+                events.push(IndexEvent::LinkBroken {
+                    path: abs.clone(),
+                    relative_path: relative_path.clone(),
+                    target: target.clone(),
+                });
+            }
+            for target in before.difference(&now) {
+                events.push(IndexEvent::LinkFixed {
+                    path: abs.clone(),
+                    relative_path: relative_path.clone(),
+                    target: target.clone(),
+                });
+            }
+            if !now.is_empty() {
+                self.broken.insert(path, now);
+            }
+        }
+        events
+    }
+
+    fn broken_targets(&self, path: &Path) -> BTreeSet<String> {
+        let Some(meta) = self.notes.get(path) else {
+            return BTreeSet::new();
+        };
+        meta.content
+            .links
+            .iter()
+            .filter(|t| matches!(self.resolve(path, t), LinkTarget::Missing | LinkTarget::MissingAnchor(_)))
+            .cloned()
+            .collect()
+    }
+
+    fn describe(&self, path: &Path) -> (String, String) {
+        let relative = self.notes.get(path).map(|n| n.relative_path.clone()).unwrap_or_default();
+        (path_str(path), relative)
+    }
+
+    fn is_ignore_file(&self, path: &Path) -> bool {
+        let name = path.file_name().and_then(|n| n.to_str());
+        name == Some(SPROUTIGNORE) || (self.options.respect_gitignore && name == Some(".gitignore"))
+    }
+
+    fn strip_extension(&self, target: &str) -> String {
+        let path = Path::new(target);
+        if self.options.is_note(path) {
+            path.with_extension("").to_string_lossy().to_string()
+        } else {
+            target.to_string()
+        }
+    }
+
+    fn name_keys(&self, meta: &NoteMetaInfo) -> [String; 2] {
+        let relative = self.strip_extension(&meta.relative_path);
+        let stem = meta.path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        [name_key(&relative), name_key(&stem)]
+    }
+
+    fn add_names(&mut self, meta: &NoteMetaInfo) {
+        for key in self.name_keys(meta) {
+            self.names.entry(key).or_default().insert(meta.path.clone());
+        }
+        for alias in &meta.content.aliases {
+            self.aliases.entry(name_key(alias)).or_default().insert(meta.path.clone());
+        }
+    }
+
+    fn remove_names(&mut self, meta: &NoteMetaInfo) {
+        for key in self.name_keys(meta) {
+            remove_from(&mut self.names, &key, &meta.path);
+        }
+        for alias in &meta.content.aliases {
+            remove_from(&mut self.aliases, &name_key(alias), &meta.path);
+        }
+    }
+}
+
+fn path_str(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+fn remove_from(map: &mut HashMap<String, BTreeSet<PathBuf>>, key: &str, path: &Path) {
+    if let Some(set) = map.get_mut(key) {
+        set.remove(path);
+        if set.is_empty() {
+            map.remove(key);
+        }
+    }
+}
+
+fn name_key(name: &str) -> String {
+    name.trim_start_matches("./").trim_start_matches('/').to_lowercase()
+}
+
+/// Lowercase, with whitespace as `-` and other punctuation dropped.
+fn anchor_key(heading: &str) -> String {
+    heading
+        .trim()
+        .chars()
+        .filter_map(|c| match c {
+            c if c.is_whitespace() || c == '-' => Some('-'),
+            c if c.is_alphanumeric() || c == '_' => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// `scheme:` prefixes such as `https:`, `mailto:` or org's `id:`.
+fn has_scheme(target: &str) -> bool {
+    match target.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+        }
+        None => false,
+    }
+}
+
+/// Decode `%XX` escapes, as in `[x](My%20Note.md)`.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(b) = s.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                out.push(b);
+                i += 3;
+                continue;
+            }
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Resolve `.` and `..` without touching the filesystem.
+fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                out.pop();
+            }
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::NoteFormat;
+    use crate::links;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const DUE: &str = "---\nmaturity: seedling\nreview_interval: 1\nnext_review: 2026-03-01\nease: 2.5\n---\n";
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 3, 1).unwrap()
+    }
+
+    fn index(dir: &TempDir) -> VaultIndex {
+        let vault = fs::canonicalize(dir.path()).unwrap();
+        let cache = FrontmatterCache::in_memory(&vault);
+        VaultIndex::build_with(vault, &ScanOptions::default(), cache, today()).unwrap()
+    }
+
+    fn kinds(events: &[IndexEvent]) -> Vec<String> {
+        events
+            .iter()
+            .map(|e| {
+                let v = serde_json::to_value(e).unwrap();
+                let target = v.get("target").and_then(|t| t.as_str()).map(|t| format!(" {t}"));
+                format!("{} {}{}", v["event"].as_str().unwrap(), v["relative_path"].as_str().unwrap(), target.unwrap_or_default())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.md"), "# Top\n").unwrap();
+        fs::write(dir.path().join("sub/My Note.md"), "# Heading\n").unwrap();
+        fs::write(dir.path().join("image.png"), "").unwrap();
+        let index = index(&dir);
+        let a = index.vault().join("a.md");
+        let note = index.vault().join("sub/My Note.md");
+
+        assert_eq!(index.resolve(&a, "my note"), LinkTarget::Note(note.clone()));
+        assert_eq!(index.resolve(&a, "sub/My Note#Heading"), LinkTarget::Note(note.clone()));
+        assert_eq!(index.resolve(&a, "sub/My%20Note.md"), LinkTarget::Note(note.clone()));
+        assert_eq!(index.resolve(&note, "../a.md"), LinkTarget::Note(a.clone()));
+        assert_eq!(index.resolve(&note, "file:../a.md"), LinkTarget::Note(a.clone()));
+        assert_eq!(index.resolve(&a, "#top"), LinkTarget::Note(a.clone()));
+        assert_eq!(index.resolve(&a, "image.png"), LinkTarget::File(index.vault().join("image.png")));
+        assert_eq!(index.resolve(&a, "id:1234"), LinkTarget::External);
+        assert_eq!(index.resolve(&a, "mailto:x@example.com"), LinkTarget::External);
+        assert_eq!(index.resolve(&a, "missing"), LinkTarget::Missing);
+    }
+
+    #[test]
+    fn test_count_links_by_resolved_note() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/machine-learning.md"), "---\naliases: [ML]\n---\n# Intro\n").unwrap();
+        fs::write(dir.path().join("a.md"), "").unwrap();
+        let index = index(&dir);
+        let a = index.vault().join("a.md");
+
+        let body = "[[ML]] [[sub/machine-learning]] [ml](sub/machine-learning.md#intro) [[machine-learning#Gone]]\n\
+                    [[idea]] [idea](idea.md) [[Idea#Later]] [site](https://example.com) [[rust]]";
+        let count = links::count_links(NoteFormat::Markdown, body, |t| index.link_key(&a, t));
+        // machine-learning, idea and rust
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn test_resolve_anchors() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.md"), "# Setup Guide\n## Linux\nSteps ^steps\n```\n# Not a heading\n```\n").unwrap();
+        fs::write(dir.path().join("b.md"), "").unwrap();
+        let index = index(&dir);
+        let a = index.vault().join("a.md");
+        let b = index.vault().join("b.md");
+
+        for target in ["a#Setup Guide", "a#setup-guide", "a.md#Setup%20Guide", "a#Setup Guide#Linux", "a#^steps", "a#^STEPS"] {
+            assert_eq!(index.resolve(&b, target), LinkTarget::Note(a.clone()), "{target}");
+        }
+        assert_eq!(index.resolve(&b, "a#Not a heading"), LinkTarget::MissingAnchor(a.clone()));
+        assert_eq!(index.resolve(&b, "a#^gone"), LinkTarget::MissingAnchor(a.clone()));
+        assert_eq!(index.resolve(&a, "#linux"), LinkTarget::Note(a.clone()));
+        assert_eq!(index.section(&a, "setup-guide#linux").as_deref(), Some("Linux"));
+        assert_eq!(index.section(&a, "^steps").as_deref(), Some("^steps"));
+    }
+
+    #[test]
+    fn test_apply_reports_anchors_broken_by_an_edit() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.md"), "# Setup\n").unwrap();
+        fs::write(dir.path().join("b.md"), "[[a#Setup]]\n").unwrap();
+        let mut index = index(&dir);
+        let a = index.vault().join("a.md");
+
+        fs::write(&a, "# Install\n").unwrap();
+        assert_eq!(kinds(&index.apply(std::slice::from_ref(&a), &[])), vec!["changed a.md", "link_broken b.md a#Setup"]);
+    }
+
+    #[test]
+    fn test_resolve_through_aliases() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("machine-learning.md"), "---\naliases: [ML, Rust]\n---\n").unwrap();
+        fs::write(dir.path().join("rust.md"), "").unwrap();
+        let mut index = index(&dir);
+        let ml = index.vault().join("machine-learning.md");
+        let rust = index.vault().join("rust.md");
+
+        assert_eq!(index.resolve(&rust, "ml"), LinkTarget::Note(ml.clone()));
+        // File names win over aliases
+        assert_eq!(index.resolve(&ml, "Rust"), LinkTarget::Note(rust.clone()));
+
+        fs::write(&ml, "---\naliases: [AI]\n---\n").unwrap();
+        index.apply(std::slice::from_ref(&ml), &[]);
+        assert_eq!(index.resolve(&rust, "ML"), LinkTarget::Missing);
+        assert_eq!(index.resolve(&rust, "ai"), LinkTarget::Note(ml));
+    }
+
+    #[test]
+    fn test_link_name() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("a.md"), "").unwrap();
+        fs::write(dir.path().join("sub/a.md"), "").unwrap();
+        fs::write(dir.path().join("sub/My Note.md"), "").unwrap();
+        let index = index(&dir);
+
+        assert_eq!(index.link_name(&index.vault().join("a.md")).as_deref(), Some("a"));
+        assert_eq!(index.link_name(&index.vault().join("sub/a.md")).as_deref(), Some("sub/a"));
+        assert_eq!(index.link_name(&index.vault().join("sub/My Note.md")).as_deref(), Some("My Note"));
+        assert_eq!(index.link_name(&index.vault().join("missing.md")), None);
+    }
+
+    #[test]
+    fn test_apply_add_change_remove() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.md"), "Links to [[b]]\n").unwrap();
+        let mut index = index(&dir);
+        assert_eq!(index.ready_event(), IndexEvent::Ready { notes: 1, due: 0, broken_links: 1 });
+        let vault = index.vault().to_path_buf();
+
+        fs::write(vault.join("b.md"), DUE).unwrap();
+        let events = index.apply(&[vault.join("b.md")], &[]);
+        assert_eq!(kinds(&events), vec!["added b.md", "due b.md", "link_fixed a.md b"]);
+
+        fs::write(vault.join("b.md"), format!("{DUE}See [[c]]\n")).unwrap();
+        let events = index.apply(&[vault.join("b.md")], &[]);
+        assert_eq!(kinds(&events), vec!["changed b.md", "link_broken b.md c"]);
+
+        // Unchanged content: nothing to report
+        assert!(index.apply(&[vault.join("b.md")], &[]).is_empty());
+
+        fs::remove_file(vault.join("b.md")).unwrap();
+        let events = index.apply(&[vault.join("b.md")], &[]);
+        assert_eq!(kinds(&events), vec!["removed b.md", "link_broken a.md b"]);
+    }
+
+    #[test]
+    fn test_apply_rename_and_directories() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir(dir.path().join("old")).unwrap();
+        fs::write(dir.path().join("old/b.md"), "").unwrap();
+        fs::write(dir.path().join("a.md"), "[x](old/b.md)\n").unwrap();
+        let mut index = index(&dir);
+        let vault = index.vault().to_path_buf();
+
+        fs::rename(vault.join("old"), vault.join("new")).unwrap();
+        let events = index.apply(&[], &[(vault.join("old"), vault.join("new"))]);
+        assert_eq!(kinds(&events), vec!["renamed new/b.md", "link_broken a.md old/b.md"]);
+
+        fs::rename(vault.join("new/b.md"), vault.join("new/c.md")).unwrap();
+        let events = index.apply(&[], &[(vault.join("new/b.md"), vault.join("new/c.md"))]);
+        assert_eq!(kinds(&events), vec!["renamed new/c.md"]);
+        assert!(matches!(&events[0], IndexEvent::Renamed { path, .. } if path.ends_with("new/c.md")));
+
+        // A new ignore rule drops the note
+        fs::write(vault.join(SPROUTIGNORE), "new/\n").unwrap();
+        let events = index.apply(&[vault.join(SPROUTIGNORE)], &[]);
+        assert_eq!(kinds(&events), vec!["removed new/c.md"]);
+    }
+
+    #[test]
+    fn test_tick_reports_notes_due_at_midnight() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("today.md"), DUE).unwrap();
+        fs::write(dir.path().join("tomorrow.md"), DUE.replace("2026-03-01", "2026-03-02")).unwrap();
+        let mut index = index(&dir);
+
+        assert!(index.tick(today()).is_empty());
+        let events = index.tick(today().succ_opt().unwrap());
+        assert_eq!(kinds(&events), vec!["due tomorrow.md"]);
+    }
+}
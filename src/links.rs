@@ -1,31 +1,233 @@
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag, TagEnd};
 use regex::Regex;
 use std::collections::HashSet;
+use std::ops::Range;
 
-/// Count unique internal links in the note body.
-/// Supports [[wiki-link]] and [text](path) formats.
-/// Excludes external URLs (http:// or https://) and image links (![...](path)).
-pub fn count_links(body: &str) -> usize {
-    let mut targets = HashSet::new();
-
-    // [[wiki-link]] — extract target before optional |display text
-    let wiki_re = Regex::new(r"\[\[([^\]|]+)(?:\|[^\]]+)?\]\]").unwrap();
-    for cap in wiki_re.captures_iter(body) {
-        let target = cap[1].trim();
-        if !target.starts_with("http://") && !target.starts_with("https://") {
-            targets.insert(target.to_string());
+use crate::frontmatter::NoteFormat;
+use crate::search;
+
+/// Unique internal link targets in the note body, in order of first appearance.
+/// Same rules as `find_links`.
+pub fn extract_links(format: NoteFormat, body: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    find_links(format, body)
+        .into_iter()
+        .map(|l| l.target)
+        .filter(|t| seen.insert(t.clone()))
+        .collect()
+}
+
+/// Count unique internal links in the note body, as in `extract_links`.
+/// Links count once per `key`, so a note linked by file name and by alias
+/// (`[[machine-learning]]`, `[[ML]]`) counts once when `key` resolves both
+/// to it, see `VaultIndex::link_key`. Links without a key are not counted.
+pub fn count_links(format: NoteFormat, body: &str, key: impl Fn(&str) -> Option<String>) -> usize {
+    extract_links(format, body)
+        .iter()
+        .filter_map(|t| key(t))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// How a link is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkKind {
+    /// `[[note]]`, `[[note|text]]`, or an org `[[note][text]]`
+    Wiki,
+    /// `[text](note.md)`, `[text](<with spaces.md>)` or `[text][ref]`
+    Markdown,
+    /// `![[note]]` transclusion
+    Embed,
+    /// `[[note#Heading]]` or `[text](note.md#heading)`
+    Heading,
+    /// `[[note#^block-id]]`
+    Block,
+}
+
+/// An internal link and where it appears in the text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkSpan {
+    pub kind: LinkKind,
+    /// As written, anchor included: `note#Heading`
+    pub target: String,
+    /// Byte range of the whole link, brackets included
+    pub start: usize,
+    pub end: usize,
+}
+
+impl LinkSpan {
+    /// The heading or `^block-id` after `#`, if any.
+    pub fn anchor(&self) -> Option<&str> {
+        self.target.split_once('#').map(|(_, anchor)| anchor).filter(|a| !a.is_empty())
+    }
+}
+
+/// Every internal link in the text, repeats included, in textual order.
+/// Markdown is tokenized as CommonMark with wiki links, so links in code
+/// spans, code blocks, HTML and YAML frontmatter are not links; org notes
+/// only have `[[target][description]]` links, outside `#+begin_` blocks.
+/// External URLs and images other than `![[embeds]]` are left out.
+pub fn find_links(format: NoteFormat, text: &str) -> Vec<LinkSpan> {
+    let mut found = match format {
+        NoteFormat::Markdown => find_markdown_links(text),
+        NoteFormat::Org => find_org_links(text),
+    };
+    found.retain(|l| !l.target.is_empty() && !l.target.starts_with("http://") && !l.target.starts_with("https://"));
+    found
+}
+
+fn find_markdown_links(text: &str) -> Vec<LinkSpan> {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_WIKILINKS);
+    options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    let mut found = Vec::new();
+    for (event, range) in Parser::new_ext(text, options).into_offset_iter() {
+        let (link_type, dest_url, embed) = match event {
+            Event::Start(Tag::Link { link_type, dest_url, .. }) => (link_type, dest_url, false),
+            Event::Start(Tag::Image { link_type: link_type @ LinkType::WikiLink { .. }, dest_url, .. }) => {
+                (link_type, dest_url, true)
+            }
+            _ => continue,
+        };
+        let kind = match link_type {
+            LinkType::Autolink | LinkType::Email => continue,
+            _ if embed => LinkKind::Embed,
+            _ if dest_url.contains("#^") => LinkKind::Block,
+            _ if dest_url.contains('#') => LinkKind::Heading,
+            LinkType::WikiLink { .. } => LinkKind::Wiki,
+            _ => LinkKind::Markdown,
+        };
+        found.push(LinkSpan {
+            kind,
+            target: dest_url.trim().to_string(),
+            start: range.start,
+            end: range.end,
+        });
+    }
+    found
+}
+
+fn find_org_links(text: &str) -> Vec<LinkSpan> {
+    // [[target]] or [[target][description]]
+    let re = Regex::new(r"\[\[([^\]]+)\](?:\[[^\]]*\])?\]").unwrap();
+    let mut found = Vec::new();
+    let mut in_block = false;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let lower = line.trim_start().to_lowercase();
+        if lower.starts_with("#+begin_") {
+            in_block = true;
+        } else if lower.starts_with("#+end_") {
+            in_block = false;
+        } else if !in_block {
+            for cap in re.captures_iter(line) {
+                let whole = cap.get(0).unwrap();
+                found.push(LinkSpan {
+                    kind: LinkKind::Wiki,
+                    target: cap[1].trim().to_string(),
+                    start: offset + whole.start(),
+                    end: offset + whole.end(),
+                });
+            }
         }
+        offset += line.len();
+    }
+    found
+}
+
+/// Heading texts and `^block-id` markers (without the `^`) that links can
+/// point into, in textual order. Code blocks, HTML and frontmatter are
+/// skipped; org notes have headings only.
+pub fn extract_anchors(format: NoteFormat, body: &str) -> (Vec<String>, Vec<String>) {
+    match format {
+        NoteFormat::Markdown => markdown_anchors(body),
+        NoteFormat::Org => (org_headings(body), Vec::new()),
     }
+}
 
-    // [text](path) — exclude images (preceded by !) and external URLs
-    let md_re = Regex::new(r"(?:^|[^!])\[([^\]]*)\]\(([^)]+)\)").unwrap();
-    for cap in md_re.captures_iter(body) {
-        let path = cap[2].trim();
-        if !path.starts_with("http://") && !path.starts_with("https://") {
-            targets.insert(path.to_string());
+fn markdown_anchors(body: &str) -> (Vec<String>, Vec<String>) {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_YAML_STYLE_METADATA_BLOCKS);
+    let mut headings = Vec::new();
+    let mut heading: Option<String> = None;
+    let mut skipped: Vec<Range<usize>> = Vec::new();
+    for (event, range) in Parser::new_ext(body, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { .. }) => heading = Some(String::new()),
+            Event::End(TagEnd::Heading(_)) => {
+                if let Some(text) = heading.take() {
+                    headings.push(text.trim().to_string());
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(h) = heading.as_mut() {
+                    h.push_str(&text);
+                }
+            }
+            Event::Start(Tag::CodeBlock(_) | Tag::HtmlBlock | Tag::MetadataBlock(_)) => skipped.push(range),
+            _ => {}
         }
     }
 
-    targets.len()
+    // `text ^block-id` at the end of a line, or alone on one
+    let block_re = Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)\s*$").unwrap();
+    let mut block_ids = Vec::new();
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        if !skipped.iter().any(|r| r.contains(&offset)) {
+            if let Some(cap) = block_re.captures(line) {
+                block_ids.push(cap[1].to_string());
+            }
+        }
+        offset += line.len();
+    }
+    (headings, block_ids)
+}
+
+fn org_headings(body: &str) -> Vec<String> {
+    // `** Heading :tag1:tag2:`
+    let re = Regex::new(r"^\*+[ \t]+(.+?)(?:[ \t]+:[\w@#%:]+:)?[ \t]*$").unwrap();
+    let mut headings = Vec::new();
+    let mut in_block = false;
+    for line in body.lines() {
+        let lower = line.trim_start().to_lowercase();
+        if lower.starts_with("#+begin_") {
+            in_block = true;
+        } else if lower.starts_with("#+end_") {
+            in_block = false;
+        } else if let Some(cap) = re.captures(line).filter(|_| !in_block) {
+            headings.push(cap[1].to_string());
+        }
+    }
+    headings
+}
+
+/// Byte offsets where `name` appears in `line`, ignoring case, as a whole
+/// word: it may not continue a word of letters or digits. CJK text has no
+/// word boundaries, so CJK characters next to the match are allowed.
+pub fn find_mentions(line: &str, name: &str) -> Vec<usize> {
+    let is_word = |c: char| c.is_alphanumeric() && !search::is_cjk(c);
+    let name: Vec<char> = name.chars().collect();
+    let (Some(&first), Some(&last)) = (name.first(), name.last()) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    let mut previous: Option<char> = None;
+    for (start, c) in line.char_indices() {
+        let before = previous;
+        previous = Some(c);
+        if before.is_some_and(|b| is_word(b) && is_word(first)) {
+            continue;
+        }
+        let mut rest = line[start..].chars();
+        let matched = name
+            .iter()
+            .all(|n| rest.next().is_some_and(|c| c.to_lowercase().eq(n.to_lowercase())));
+        if matched && !rest.next().is_some_and(|a| is_word(a) && is_word(last)) {
+            found.push(start);
+        }
+    }
+    found
 }
 
 /// Calculate link factor: normalized 0.0-1.0 value based on link count.
@@ -39,40 +241,109 @@ pub fn link_factor(link_count: usize) -> f64 {
 mod tests {
     use super::*;
 
+    /// Every target as written counts once
+    fn raw(target: &str) -> Option<String> {
+        Some(target.to_string())
+    }
+
     #[test]
     fn test_wiki_links() {
         let body = "See [[note1]] and [[note2|Display Name]].";
-        assert_eq!(count_links(body), 2);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 2);
     }
 
     #[test]
     fn test_markdown_links() {
         let body = "See [link](path/to/note.md) and [other](another.md).";
-        assert_eq!(count_links(body), 2);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 2);
+    }
+
+    #[test]
+    fn test_extract_links_order() {
+        let body = "[b](b.md) then [[a]] and [[b.md]] and [[a|again]]";
+        assert_eq!(extract_links(NoteFormat::Markdown, body), vec!["b.md", "a"]);
+    }
+
+    #[test]
+    fn test_find_links_spans() {
+        let text = "x [[a|A]] [b](b.md)\n![i](i.png) [[a]]";
+        let spans: Vec<_> = find_links(NoteFormat::Markdown, text).into_iter().map(|l| (l.target, &text[l.start..l.end])).collect();
+        assert_eq!(
+            spans,
+            vec![("a".to_string(), "[[a|A]]"), ("b.md".to_string(), "[b](b.md)"), ("a".to_string(), "[[a]]")]
+        );
+    }
+
+    #[test]
+    fn test_find_links_kinds() {
+        let text = "[[a]] [t](<with spaces.md>) ![[b]] [[c#Intro]] [u](d.md#setup) [[e#^p1]] [r][ref]\n\n[ref]: f.md\n";
+        let kinds: Vec<_> = find_links(NoteFormat::Markdown, text).into_iter().map(|l| (l.kind, l.target)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (LinkKind::Wiki, "a".to_string()),
+                (LinkKind::Markdown, "with spaces.md".to_string()),
+                (LinkKind::Embed, "b".to_string()),
+                (LinkKind::Heading, "c#Intro".to_string()),
+                (LinkKind::Heading, "d.md#setup".to_string()),
+                (LinkKind::Block, "e#^p1".to_string()),
+                (LinkKind::Markdown, "f.md".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skip_code_comments_and_frontmatter() {
+        let text = "---\nup: \"[[fm]]\"\n---\n`[[inline]]` <!-- [[comment]] -->\n\n```\n[[fenced]]\n```\n\n    [[indented]]\n\n<div>[[html]]</div>\n\n[[real]]\n";
+        assert_eq!(extract_links(NoteFormat::Markdown, text), vec!["real"]);
+    }
+
+    #[test]
+    fn test_org_links_skip_blocks() {
+        let body = "[[file:a.org][A]]\n#+begin_src\n[[file:b.org]]\n#+END_SRC\n[[c]] and [d](d.md)\n";
+        let spans: Vec<_> = find_links(NoteFormat::Org, body).into_iter().map(|l| &body[l.start..l.end]).collect();
+        assert_eq!(spans, vec!["[[file:a.org][A]]", "[[c]]"]);
+    }
+
+    #[test]
+    fn test_extract_anchors() {
+        let body = "---\nx: ^fm\n---\n# Setup `cargo`\nText ^p1\n\nSetext\n------\n\n^quote-2\n```\n# Code ^code\n```\n[[n#^ref]] and [^1]\n";
+        let (headings, blocks) = extract_anchors(NoteFormat::Markdown, body);
+        assert_eq!(headings, vec!["Setup cargo", "Setext"]);
+        assert_eq!(blocks, vec!["p1", "quote-2"]);
+
+        let org = "* Intro :draft:\n#+begin_src\n* Code\n#+end_src\n** Details\n";
+        assert_eq!(extract_anchors(NoteFormat::Org, org), (vec!["Intro".to_string(), "Details".to_string()], Vec::new()));
+    }
+
+    #[test]
+    fn test_org_links() {
+        let body = "See [[id:1234][Zettel]], [[file:other.org]] and [[https://example.com][site]].";
+        assert_eq!(count_links(NoteFormat::Org, body, raw), 2);
     }
 
     #[test]
     fn test_deduplication() {
         let body = "See [[note1]] and [[note1]] again, plus [[note1|alias]].";
-        assert_eq!(count_links(body), 1);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 1);
     }
 
     #[test]
     fn test_exclude_external_urls() {
         let body = "See [google](https://google.com) and [[https://example.com]].";
-        assert_eq!(count_links(body), 0);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 0);
     }
 
     #[test]
     fn test_exclude_images() {
         let body = "![alt](image.png) but [link](note.md)";
-        assert_eq!(count_links(body), 1);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 1);
     }
 
     #[test]
     fn test_mixed_links() {
         let body = "[[wiki]] and [md](path.md) and ![img](pic.png) and [ext](https://x.com)";
-        assert_eq!(count_links(body), 2);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 2);
     }
 
     #[test]
@@ -95,24 +366,24 @@ mod tests {
 
     #[test]
     fn test_no_links() {
-        assert_eq!(count_links("No links here at all."), 0);
+        assert_eq!(count_links(NoteFormat::Markdown, "No links here at all.", raw), 0);
     }
 
     #[test]
     fn test_empty_body() {
-        assert_eq!(count_links(""), 0);
+        assert_eq!(count_links(NoteFormat::Markdown, "", raw), 0);
     }
 
     #[test]
     fn test_multiline_links() {
         let body = "First [[note1]]\nSecond [[note2]]\nThird [link](path.md)\n";
-        assert_eq!(count_links(body), 3);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 3);
     }
 
     #[test]
     fn test_wiki_link_with_heading() {
         let body = "See [[note#heading]].";
-        assert_eq!(count_links(body), 1);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 1);
     }
 
     #[test]
@@ -120,19 +391,19 @@ mod tests {
         // Same target via wiki-link and markdown link should count as separate
         // (wiki target = "note", md target = "note.md")
         let body = "[[note]] and [link](note.md)";
-        assert_eq!(count_links(body), 2);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 2);
     }
 
     #[test]
     fn test_http_wiki_link_excluded() {
         let body = "[[http://example.com]]";
-        assert_eq!(count_links(body), 0);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 0);
     }
 
     #[test]
     fn test_image_at_start_of_line() {
         let body = "![alt](image.png)";
-        assert_eq!(count_links(body), 0);
+        assert_eq!(count_links(NoteFormat::Markdown, body, raw), 0);
     }
 
     #[test]
@@ -158,4 +429,13 @@ mod tests {
         let f = link_factor(1000);
         assert!(f <= 1.0);
     }
+
+    #[test]
+    fn test_find_mentions() {
+        assert_eq!(find_mentions("Ownership and ownership", "ownership"), vec![0, 14]);
+        assert_eq!(find_mentions("co-ownership, ownerships", "ownership"), vec![3]);
+        assert_eq!(find_mentions("the Borrow Checker!", "borrow checker"), vec![4]);
+        assert_eq!(find_mentions("これは所有権の話", "所有権"), vec![9]);
+        assert!(find_mentions("anything", "").is_empty());
+    }
 }
@@ -1,13 +1,23 @@
+mod bulk;
 mod cache;
 mod cli;
 mod commands;
 mod config;
 mod error;
 mod frontmatter;
+mod history;
+mod index;
 mod links;
+mod naming;
 mod note;
+mod optimize;
+mod org;
 mod output;
+mod query;
+mod search;
+mod sort;
 mod srs;
+mod stats;
 mod template;
 
 use clap::Parser;
@@ -41,38 +51,61 @@ fn run_command(cli: &Cli, config: &config::Config) -> Result<(), SproutError> {
     let format = &cli.format;
 
     match &cli.command {
-        Commands::Init { file } => {
-            let vault = resolve_vault_for_file(file, cli, config)?;
-            commands::init::run(file, &vault, config, format)
+        Commands::Init { files, all, glob, atomic } => {
+            if bulk::is_bulk(files) || *all || glob.is_some() || *atomic {
+                let vault = resolve_vault_for_files(files, cli, config)?;
+                commands::init::run_bulk(files, *all, glob.as_deref(), *atomic, &vault, config, format)
+            } else {
+                let vault = resolve_vault_for_file(&files[0], cli, config)?;
+                commands::init::run(&files[0], &vault, config, format)
+            }
         }
         Commands::Show { file } => {
             let vault = resolve_vault_for_file(file, cli, config)?;
-            commands::show::run(file, &vault, format)
+            commands::show::run(file, &vault, &config.scan_options(), format)
         }
-        Commands::Done { file, rating } => {
-            let vault = resolve_vault_for_file(file, cli, config)?;
-            commands::done::run(file, rating, &vault, config, format)
+        Commands::Done { files, rating, query, atomic } => {
+            if bulk::is_bulk(files) || query.is_some() || *atomic {
+                let vault = resolve_vault_for_files(files, cli, config)?;
+                commands::done::run_bulk(files, query.as_deref(), rating, *atomic, &vault, config, format)
+            } else {
+                let vault = resolve_vault_for_file(&files[0], cli, config)?;
+                commands::done::run(&files[0], rating, &vault, config, format)
+            }
         }
-        Commands::Promote { file, maturity } => {
-            let vault = resolve_vault_for_file(file, cli, config)?;
-            commands::promote::run(file, maturity, &vault, format)
+        Commands::Promote { files, maturity, query, atomic } => {
+            if bulk::is_bulk(files) || query.is_some() || *atomic {
+                let vault = resolve_vault_for_files(files, cli, config)?;
+                commands::promote::run_bulk(files, query.as_deref(), maturity, *atomic, &vault, config, format)
+            } else {
+                let vault = resolve_vault_for_file(&files[0], cli, config)?;
+                commands::promote::run(&files[0], maturity, &vault, format)
+            }
         }
-        Commands::Review => {
+        Commands::Review { query, sort } => {
             let vault = resolve_vault_safe(cli, config)?;
-            commands::review::run(&vault, &config.exclude_dirs(), format)
+            commands::review::run(&vault, &config.scan_options(), query.as_deref(), sort, format)
         }
-        Commands::List { maturity } => {
+        Commands::List { maturity, query, sort } => {
             let vault = resolve_vault_safe(cli, config)?;
-            commands::list::run(&vault, maturity.as_ref(), &config.exclude_dirs(), format)
+            commands::list::run(&vault, maturity.as_ref(), &config.scan_options(), query.as_deref(), sort, format)
         }
-        Commands::Stats => {
+        Commands::Stats { detailed, calendar, query } => {
             let vault = resolve_vault_safe(cli, config)?;
-            commands::stats::run(&vault, &config.exclude_dirs(), format)
+            commands::stats::run(&vault, &config.scan_options(), *detailed, *calendar, query.as_deref(), format)
         }
-        Commands::Note { title, template } => {
+        Commands::Note { title, template, parent, dir, vars } => {
             let vault = resolve_vault_safe(cli, config)?;
             match title {
-                Some(t) => commands::note::run_create(t, &vault, config, template.as_deref(), format),
+                Some(t) => {
+                    let options = commands::note::CreateOptions {
+                        template: template.as_deref(),
+                        parent: parent.as_deref(),
+                        dir: dir.as_deref(),
+                        vars,
+                    };
+                    commands::note::run_create(t, &vault, config, &options, format)
+                }
                 None => {
                     use std::io::IsTerminal;
                     if *format == cli::OutputFormat::Human && std::io::stdout().is_terminal() {
@@ -83,6 +116,46 @@ fn run_command(cli: &Cli, config: &config::Config) -> Result<(), SproutError> {
                 }
             }
         }
+        Commands::Search { terms, maturity, tag, limit } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::search::run(&vault, &config.scan_options(), terms, maturity.as_ref(), tag.as_deref(), *limit, format)
+        }
+        Commands::Related { file, limit } => {
+            let vault = resolve_vault_for_file(file, cli, config)?;
+            commands::related::run(file, &vault, &config.scan_options(), *limit, format)
+        }
+        Commands::Autolink { file, yes, dry_run } => {
+            let vault = resolve_vault_for_file(file, cli, config)?;
+            commands::autolink::run(file, &vault, &config.scan_options(), *yes, *dry_run, format)
+        }
+        Commands::Check { action } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::check::run(action, &vault, &config.scan_options(), format)
+        }
+        Commands::Agenda { stale_days } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::agenda::run(&vault, &config.scan_options(), *stale_days, format)
+        }
+        Commands::Optimize { target_retention } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::optimize::run(&vault, *target_retention, config, format)
+        }
+        Commands::Cache { action } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::cache::run(action, &vault, &config.scan_options(), format)
+        }
+        Commands::Watch { events } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::watch::run(&vault, &config.scan_options(), *events, format)
+        }
+        Commands::Serve { socket } => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::serve::run(&vault, config, socket.as_deref())
+        }
+        Commands::Lsp => {
+            let vault = resolve_vault_safe(cli, config)?;
+            commands::lsp::run(&vault, config)
+        }
     }
 }
 
@@ -98,6 +171,18 @@ fn resolve_vault_for_file(
         .map_err(|e: anyhow::Error| SproutError::VaultNotFound(e.to_string()))
 }
 
+/// Resolve vault for bulk runs of file-based commands: the first file named on
+/// the command line stands in for the whole selection.
+fn resolve_vault_for_files(
+    files: &[std::path::PathBuf],
+    cli: &Cli,
+    config: &config::Config,
+) -> Result<std::path::PathBuf, SproutError> {
+    let first = files.iter().find(|f| f.as_path() != std::path::Path::new("-"));
+    resolve_vault_with_file(cli.vault.as_ref(), config, first.map(|f| f.as_path()))
+        .map_err(|e: anyhow::Error| SproutError::VaultNotFound(e.to_string()))
+}
+
 /// Resolve vault for vault-wide commands (review, list, stats).
 fn resolve_vault_safe(
     cli: &Cli,
@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDateTime};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::SproutError;
+
+/// How `sprout note <title>` names the file of a new note.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FilenameScheme {
+    /// `My Note.md`
+    #[default]
+    Title,
+    /// `202610171530.md` or `1a2b.md`, see `IdScheme`
+    Id,
+    /// `my-note.md`
+    Slug,
+    /// `202610171530-my-note.md`
+    IdSlug,
+}
+
+/// How the `{{id}}` of a new note is chosen.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IdScheme {
+    /// Creation time to the minute, `YYYYMMDDHHMM`
+    #[default]
+    Timestamp,
+    /// Folgezettel: `1`, `1a`, `1a1`, `1a2b`, alternating numbers and letters
+    Luhmann,
+}
+
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M";
+
+impl IdScheme {
+    /// Whether `id` has this scheme's shape: twelve digits, or digits and
+    /// lowercase letters alternating from a number (`1a2b`).
+    pub fn matches(self, id: &str) -> bool {
+        let re = match self {
+            IdScheme::Timestamp => Regex::new(r"^[0-9]{12}$"),
+            IdScheme::Luhmann => Regex::new(r"^[0-9]+(?:[a-z]+[0-9]+)*[a-z]*$"),
+        };
+        re.unwrap().is_match(id)
+    }
+}
+
+/// File stem of a new note. `title` has been validated; `slug` may be
+/// empty, for titles without letters or digits.
+pub fn file_stem(scheme: FilenameScheme, title: &str, id: &str, slug: &str) -> Result<String, SproutError> {
+    if slug.is_empty() && matches!(scheme, FilenameScheme::Slug | FilenameScheme::IdSlug) {
+        return Err(SproutError::InvalidTitle(title.into()));
+    }
+    Ok(match scheme {
+        FilenameScheme::Title => title.to_string(),
+        FilenameScheme::Id => id.to_string(),
+        FilenameScheme::Slug => slug.to_string(),
+        FilenameScheme::IdSlug => format!("{id}-{slug}"),
+    })
+}
+
+/// `stem`, or `stem-2`, `stem-3`, ... if a note in the vault already has
+/// that stem. `used` holds lowercased stems.
+pub fn unique_stem(stem: &str, used: &HashSet<String>) -> String {
+    let mut candidate = stem.to_string();
+    let mut n = 2;
+    while used.contains(&candidate.to_lowercase()) {
+        candidate = format!("{stem}-{n}");
+        n += 1;
+    }
+    candidate
+}
+
+/// Lowercase words of the title joined by `-`. Letters and digits of any
+/// script are kept, everything else separates words.
+pub fn slugify(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// The ID in a file stem named by `scheme`, lowercased: the whole stem for
+/// `Id`, the part before the first `-` for `IdSlug`. `None` when that part
+/// does not have `id_scheme`'s shape, so that `2026-10-18` is not taken for
+/// the Luhmann ID `2026` under `Id`; the other schemes have no ID in the name.
+pub fn id_of(stem: &str, scheme: FilenameScheme, id_scheme: IdScheme) -> Option<String> {
+    let stem = stem.to_lowercase();
+    let id = match scheme {
+        FilenameScheme::Id => stem,
+        FilenameScheme::IdSlug => {
+            let (id, slug) = stem.split_once('-')?;
+            if slug.is_empty() {
+                return None;
+            }
+            id.to_string()
+        }
+        FilenameScheme::Title | FilenameScheme::Slug => return None,
+    };
+    id_scheme.matches(&id).then_some(id)
+}
+
+/// `now` as a timestamp ID, moved on by a minute at a time past IDs already
+/// in `used`.
+pub fn timestamp_id(now: NaiveDateTime, used: &HashSet<String>) -> String {
+    let mut time = now;
+    loop {
+        let id = time.format(TIMESTAMP_FORMAT).to_string();
+        if !used.contains(&id) {
+            return id;
+        }
+        time += Duration::minutes(1);
+    }
+}
+
+/// The next Luhmann ID after those in `used`: the next top-level number, or
+/// the next child of `parent` (`1a` → `1a1`, `1a1` → `1a2` → ...,
+/// `1` → `1a`, `1z` → `1aa`).
+pub fn luhmann_id(parent: Option<&str>, used: &HashSet<String>) -> Result<String, SproutError> {
+    let parent = parent.map(str::to_lowercase);
+    let parent = parent.as_deref().unwrap_or("");
+    if !parent.is_empty() && !IdScheme::Luhmann.matches(parent) {
+        return Err(SproutError::ParseError(format!("{parent}: not a Luhmann ID like 1a2b")));
+    }
+
+    let letters = parent.ends_with(|c: char| c.is_ascii_digit());
+    let last = used
+        .iter()
+        .filter_map(|id| id.strip_prefix(parent))
+        .filter_map(|rest| {
+            if letters {
+                (!rest.is_empty() && rest.chars().all(|c| c.is_ascii_lowercase())).then(|| letters_value(rest))
+            } else {
+                rest.parse::<u64>().ok().filter(|_| rest.chars().all(|c| c.is_ascii_digit()))
+            }
+        })
+        .max()
+        .unwrap_or(0);
+    let next = if letters { value_letters(last + 1) } else { (last + 1).to_string() };
+    Ok(format!("{parent}{next}"))
+}
+
+/// `a` = 1, `z` = 26, `aa` = 27, like spreadsheet columns.
+fn letters_value(s: &str) -> u64 {
+    s.bytes().fold(0, |n, b| n * 26 + u64::from(b - b'a' + 1))
+}
+
+fn value_letters(mut n: u64) -> String {
+    let mut out = Vec::new();
+    while n > 0 {
+        n -= 1;
+        out.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    out.reverse();
+    String::from_utf8(out).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(ids: &[&str]) -> HashSet<String> {
+        ids.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Note"), "my-note");
+        assert_eq!(slugify("  Rust: ownership & borrowing!  "), "rust-ownership-borrowing");
+        assert_eq!(slugify("所有権 と Rust"), "所有権-と-rust");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn test_file_stem() {
+        let stem = |scheme| file_stem(scheme, "My Note", "202610171530", "my-note").unwrap();
+        assert_eq!(stem(FilenameScheme::Title), "My Note");
+        assert_eq!(stem(FilenameScheme::Id), "202610171530");
+        assert_eq!(stem(FilenameScheme::Slug), "my-note");
+        assert_eq!(stem(FilenameScheme::IdSlug), "202610171530-my-note");
+        assert!(file_stem(FilenameScheme::Slug, "!!!", "1", "").is_err());
+        assert_eq!(file_stem(FilenameScheme::Id, "!!!", "1", "").unwrap(), "1");
+    }
+
+    #[test]
+    fn test_unique_stem() {
+        assert_eq!(unique_stem("my-note", &set(&[])), "my-note");
+        assert_eq!(unique_stem("my-note", &set(&["my-note", "my-note-2"])), "my-note-3");
+        assert_eq!(unique_stem("My-Note", &set(&["my-note"])), "My-Note-2");
+    }
+
+    #[test]
+    fn test_id_of() {
+        use FilenameScheme::{Id, IdSlug, Slug, Title};
+        use IdScheme::{Luhmann, Timestamp};
+        assert_eq!(id_of("202610171530-my-note", IdSlug, Timestamp).as_deref(), Some("202610171530"));
+        assert_eq!(id_of("202610171530", Id, Timestamp).as_deref(), Some("202610171530"));
+        assert_eq!(id_of("1A2b", Id, Luhmann).as_deref(), Some("1a2b"));
+        assert_eq!(id_of("1a-my-note", IdSlug, Luhmann).as_deref(), Some("1a"));
+        // Daily notes and other names that only start like an ID
+        assert_eq!(id_of("2026-10-18", Id, Luhmann), None);
+        assert_eq!(id_of("2026-10-18", IdSlug, Timestamp), None);
+        assert_eq!(id_of("1a My Note", Id, Luhmann), None);
+        assert_eq!(id_of("1a-", IdSlug, Luhmann), None);
+        assert_eq!(id_of("20261017", Id, Timestamp), None);
+        assert_eq!(id_of("1", Title, Luhmann), None);
+        assert_eq!(id_of("1-x", Slug, Luhmann), None);
+    }
+
+    #[test]
+    fn test_timestamp_id_skips_used_minutes() {
+        let now = NaiveDateTime::parse_from_str("2026-10-17 15:30:45", "%Y-%m-%d %H:%M:%S").unwrap();
+        assert_eq!(timestamp_id(now, &set(&[])), "202610171530");
+        assert_eq!(timestamp_id(now, &set(&["202610171530", "202610171531"])), "202610171532");
+    }
+
+    #[test]
+    fn test_luhmann_id() {
+        let used = set(&["1", "2", "1a", "1b", "1a1", "1a2", "1z", "202610171530-x"]);
+        assert_eq!(luhmann_id(None, &set(&[])).unwrap(), "1");
+        assert_eq!(luhmann_id(None, &set(&["1", "2", "1a"])).unwrap(), "3");
+        assert_eq!(luhmann_id(Some("1"), &used).unwrap(), "1aa");
+        assert_eq!(luhmann_id(Some("1a"), &used).unwrap(), "1a3");
+        assert_eq!(luhmann_id(Some("1A2"), &used).unwrap(), "1a2a");
+        assert_eq!(luhmann_id(Some("2"), &used).unwrap(), "2a");
+        assert!(luhmann_id(Some("a1"), &used).is_err());
+    }
+}
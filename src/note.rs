@@ -1,14 +1,20 @@
 use std::collections::HashSet;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::time::UNIX_EPOCH;
 
 use anyhow::Result;
+use chrono::NaiveDate;
 use rayon::prelude::*;
-use walkdir::WalkDir;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use ignore::WalkBuilder;
 
 use crate::cache::FrontmatterCache;
 use crate::error::SproutError;
-use crate::frontmatter::{parse_note, ParsedNote, SproutFrontmatter};
+use crate::frontmatter::{self, parse_note_at, NoteFormat, ParsedNote, SproutFrontmatter};
+use crate::links;
+use crate::query;
 
 #[cfg(test)]
 #[allow(dead_code)]
@@ -23,10 +29,90 @@ pub struct NotePath {
     pub relative_path: String,
 }
 
+#[derive(Clone)]
 pub struct NoteMetaInfo {
     pub path: PathBuf,
     pub relative_path: String,
     pub sprout: SproutFrontmatter,
+    pub content: NoteContent,
+}
+
+impl NoteMetaInfo {
+    /// Tracked with complete scheduling fields and `next_review <= today`.
+    pub fn is_due(&self, today: NaiveDate) -> bool {
+        let s = &self.sprout;
+        s.maturity.is_some()
+            && s.ease.is_some()
+            && s.review_interval.is_some()
+            && s.next_review.is_some_and(|d| d <= today)
+    }
+}
+
+/// Data derived from the note body, cached alongside the frontmatter so that
+/// vault-wide features do not have to re-read every file.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteContent {
+    /// Unique internal link targets, in order of first appearance
+    pub links: Vec<String>,
+    /// Lowercased tags without the leading `#`
+    pub tags: Vec<String>,
+    /// `#+title:` for org notes, the frontmatter `title` for markdown notes,
+    /// otherwise the first heading
+    pub title: Option<String>,
+    pub word_count: usize,
+    /// Alternative names from the frontmatter `aliases` or `:ROAM_ALIASES:`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Heading texts, in order, for `[[note#heading]]` links
+    #[serde(default)]
+    pub headings: Vec<String>,
+    /// `^block-id` markers without the `^`, for `[[note#^block-id]]` links
+    #[serde(default)]
+    pub block_ids: Vec<String>,
+    /// Lowercased frontmatter `id` of a markdown note, which `sprout note`
+    /// records for the ID filename schemes
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+impl NoteContent {
+    /// `content` is the whole file, `parsed` its parse.
+    pub fn extract(content: &str, parsed: &ParsedNote) -> Self {
+        let (headings, block_ids) = links::extract_anchors(parsed.format, &parsed.body);
+        NoteContent {
+            links: links::extract_links(parsed.format, &parsed.body),
+            tags: query::extract_tags(content, &parsed.body),
+            title: extract_title(content, parsed),
+            word_count: parsed.body.split_whitespace().count(),
+            aliases: frontmatter::extract_aliases(parsed.format, content),
+            headings,
+            block_ids,
+            id: match parsed.format {
+                NoteFormat::Markdown => frontmatter::extract_id(content),
+                NoteFormat::Org => None,
+            },
+        }
+    }
+}
+
+fn extract_title(content: &str, parsed: &ParsedNote) -> Option<String> {
+    let has_title = parsed.frontmatter_raw.as_deref().is_some_and(|raw| frontmatter::has_field(raw, "title"));
+    if parsed.format == NoteFormat::Markdown && has_title {
+        if let Some(title) = frontmatter::extract_title(content) {
+            return Some(title);
+        }
+    }
+    let re = match parsed.format {
+        NoteFormat::Org => Regex::new(r"(?mi)^#\+title:[ \t]*(.+?)[ \t]*$|^\*+[ \t]+(.+?)[ \t]*$"),
+        NoteFormat::Markdown => Regex::new(r"(?m)^#{1,6}[ \t]+(.+?)[ \t#]*$"),
+    }
+    .unwrap();
+    let cap = re.captures(&parsed.body)?;
+    cap.iter()
+        .skip(1)
+        .flatten()
+        .next()
+        .map(|m| m.as_str().to_string())
 }
 
 struct MdEntry {
@@ -34,38 +120,89 @@ struct MdEntry {
     relative: String,
 }
 
-/// Walk the vault and collect canonical+relative paths for all `.md` files.
+/// File name of the vault's sprout-specific ignore files.
+pub const SPROUTIGNORE: &str = ".sproutignore";
+
+/// Which files a vault scan visits.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// Directory names skipped anywhere in the tree
+    pub exclude_dirs: Vec<String>,
+    /// Also honour `.gitignore` files inside the vault
+    pub respect_gitignore: bool,
+    /// Note file extensions without the dot, matched case-insensitively
+    pub extensions: Vec<String>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            exclude_dirs: Vec::new(),
+            respect_gitignore: false,
+            extensions: vec!["md".into()],
+        }
+    }
+}
+
+impl ScanOptions {
+    pub fn is_note(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| self.extensions.iter().any(|x| x.eq_ignore_ascii_case(ext)))
+    }
+}
+
+/// Walk the vault and collect canonical+relative paths for all note files
+/// (`.md` unless `extensions` says otherwise).
 /// Symlinks are followed; cycles are skipped. Duplicate paths (via canonicalize) are deduplicated.
-fn collect_md_paths(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<MdEntry>> {
+/// `.sproutignore` files (gitignore syntax) at the vault root and in subdirectories are honoured,
+/// as is `.gitignore` when `respect_gitignore` is set.
+/// With `only`, the walk descends just far enough to reach those paths (files or
+/// directories under the vault), so ignore rules apply to them exactly as in a full scan.
+fn collect_note_paths(vault: &Path, options: &ScanOptions, only: Option<&[PathBuf]>) -> Result<Vec<MdEntry>> {
     let vault_canonical = std::fs::canonicalize(vault)
         .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
 
     let mut seen = HashSet::new();
     let mut entries = Vec::new();
 
-    let walker = WalkDir::new(&vault_canonical)
+    let exclude_dirs = options.exclude_dirs.clone();
+    let only: Option<Vec<PathBuf>> = only.map(|o| o.to_vec());
+    let walker = WalkBuilder::new(&vault_canonical)
+        .standard_filters(false)
         .follow_links(true)
-        .into_iter();
-
-    for entry in walker.filter_entry(|e| {
-        if e.file_type().is_dir() {
-            let name = e.file_name().to_string_lossy();
-            !exclude_dirs.iter().any(|d| d == name.as_ref())
-        } else {
-            true
-        }
-    }) {
+        .add_custom_ignore_filename(SPROUTIGNORE)
+        .git_ignore(options.respect_gitignore)
+        // Vaults are often not git repositories themselves
+        .require_git(false)
+        .filter_entry(move |e| {
+            if let Some(only) = &only {
+                let path = e.path();
+                if !only.iter().any(|p| p.starts_with(path) || path.starts_with(p)) {
+                    return false;
+                }
+            }
+            if e.file_type().is_some_and(|t| t.is_dir()) {
+                let name = e.file_name().to_string_lossy();
+                !exclude_dirs.iter().any(|d| d == name.as_ref())
+            } else {
+                true
+            }
+        })
+        .build();
+
+    for entry in walker {
         let entry = match entry {
             Ok(e) => e,
             Err(_) => continue, // skip cycle errors, permission errors, etc.
         };
 
-        if !entry.file_type().is_file() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
             continue;
         }
 
         let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) != Some("md") {
+        if !options.is_note(path) {
             continue;
         }
 
@@ -90,10 +227,21 @@ fn collect_md_paths(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<MdEntry
     Ok(entries)
 }
 
-/// Scan the vault for `.md` file paths only (no file content is read).
+/// Scan the vault for note file paths only (no file content is read).
 /// Symlinks are followed; cycles are skipped. Duplicate paths (via canonicalize) are deduplicated.
-pub fn scan_vault_paths(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<NotePath>> {
-    let entries = collect_md_paths(vault, exclude_dirs)?;
+pub fn scan_vault_paths(vault: &Path, options: &ScanOptions) -> Result<Vec<NotePath>> {
+    scan_paths(vault, options, None)
+}
+
+/// Like `scan_vault_paths`, restricted to the given files and directories
+/// (absolute paths under the canonical vault). Paths that no longer exist or
+/// are excluded yield nothing.
+pub fn scan_vault_paths_under(vault: &Path, options: &ScanOptions, paths: &[PathBuf]) -> Result<Vec<NotePath>> {
+    scan_paths(vault, options, Some(paths))
+}
+
+fn scan_paths(vault: &Path, options: &ScanOptions, only: Option<&[PathBuf]>) -> Result<Vec<NotePath>> {
+    let entries = collect_note_paths(vault, options, only)?;
     Ok(entries
         .into_iter()
         .map(|e| NotePath {
@@ -104,8 +252,8 @@ pub fn scan_vault_paths(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<Not
 }
 
 #[cfg(test)]
-fn scan_vault(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<NoteInfo>> {
-    let entries = collect_md_paths(vault, exclude_dirs)?;
+fn scan_vault(vault: &Path, options: &ScanOptions) -> Result<Vec<NoteInfo>> {
+    let entries = collect_note_paths(vault, options, None)?;
     let mut notes = Vec::new();
 
     for entry in entries {
@@ -121,7 +269,7 @@ fn scan_vault(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<NoteInfo>> {
             }
         };
 
-        let parsed = parse_note(&content);
+        let parsed = parse_note_at(&entry.canonical, &content);
 
         notes.push(NoteInfo {
             path: entry.canonical,
@@ -133,23 +281,45 @@ fn scan_vault(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<NoteInfo>> {
     Ok(notes)
 }
 
-/// Scan the vault for .md files, returning only frontmatter metadata.
-/// Uses a local cache keyed by mtime+size to avoid reading unchanged files.
-pub fn scan_vault_metadata(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<NoteMetaInfo>> {
-    let entries = collect_md_paths(vault, exclude_dirs)?;
-    let mut cache = FrontmatterCache::load();
+/// Scan the vault for note files, returning frontmatter metadata and the
+/// body-derived `NoteContent`, computed in the same parallel read pass.
+/// Uses the vault's cache keyed by mtime+size to avoid reading unchanged files;
+/// entries for files that no longer exist are pruned.
+pub fn scan_vault_metadata(vault: &Path, options: &ScanOptions) -> Result<Vec<NoteMetaInfo>> {
+    let entries = collect_note_paths(vault, options, None)?;
+    let vault_canonical = std::fs::canonicalize(vault)
+        .map_err(|_| SproutError::VaultNotFound(vault.display().to_string()))?;
+    let mut cache = FrontmatterCache::load(&vault_canonical);
+    let scanned: HashSet<PathBuf> = entries.iter().map(|e| e.canonical.clone()).collect();
+    cache.prune(&scanned);
 
+    let entries = entries
+        .into_iter()
+        .map(|e| NotePath {
+            path: e.canonical,
+            relative_path: e.relative,
+        })
+        .collect();
+    let results = read_metadata(entries, &mut cache);
+    cache.save();
+    Ok(results)
+}
+
+/// Metadata for the given notes, served from `cache` when mtime+size match and
+/// read in parallel otherwise. Fresh reads are inserted into `cache`; saving
+/// is left to the caller.
+pub fn read_metadata(entries: Vec<NotePath>, cache: &mut FrontmatterCache) -> Vec<NoteMetaInfo> {
     // Phase 1: sequential stat + cache check
     let mut hits: Vec<NoteMetaInfo> = Vec::new();
-    let mut misses: Vec<MdEntry> = Vec::new();
+    let mut misses: Vec<NotePath> = Vec::new();
 
     for entry in entries {
-        let meta = match std::fs::metadata(&entry.canonical) {
+        let meta = match std::fs::metadata(&entry.path) {
             Ok(m) => m,
             Err(e) => {
                 eprintln!(
                     "warning: skipping {}: {}",
-                    entry.canonical.display(),
+                    entry.path.display(),
                     e
                 );
                 continue;
@@ -165,11 +335,13 @@ pub fn scan_vault_metadata(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<
         if let Some(d) = mtime {
             let secs = d.as_secs() as i64;
             let nanos = d.subsec_nanos();
-            if let Some(cached) = cache.get(&entry.canonical, secs, nanos, size) {
+            // Entries migrated from older cache formats lack body data
+            if let Some((sprout, Some(content))) = cache.get(&entry.path, secs, nanos, size) {
                 hits.push(NoteMetaInfo {
-                    path: entry.canonical,
-                    relative_path: entry.relative,
-                    sprout: cached.clone(),
+                    path: entry.path,
+                    relative_path: entry.relative_path,
+                    sprout: sprout.clone(),
+                    content: content.clone(),
                 });
                 continue;
             }
@@ -181,26 +353,27 @@ pub fn scan_vault_metadata(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<
     let reads: Vec<_> = misses
         .into_par_iter()
         .filter_map(|entry| {
-            let content = match std::fs::read_to_string(&entry.canonical) {
+            let content = match std::fs::read_to_string(&entry.path) {
                 Ok(c) => c,
                 Err(e) => {
                     eprintln!(
                         "warning: skipping {}: {}",
-                        entry.canonical.display(),
+                        entry.path.display(),
                         e
                     );
                     return None;
                 }
             };
-            let parsed = parse_note(&content);
+            let parsed = parse_note_at(&entry.path, &content);
+            let note_content = NoteContent::extract(&content, &parsed);
             // Re-stat after read for TOCTOU safety
-            let post_meta = std::fs::metadata(&entry.canonical).ok();
+            let post_meta = std::fs::metadata(&entry.path).ok();
             let post_mtime = post_meta
                 .as_ref()
                 .and_then(|m| m.modified().ok())
                 .and_then(|t| t.duration_since(UNIX_EPOCH).ok());
             let post_size = post_meta.map(|m| m.len()).unwrap_or(0);
-            Some((entry.canonical, entry.relative, parsed.sprout, post_size, post_mtime))
+            Some((entry.path, entry.relative_path, parsed.sprout, note_content, post_size, post_mtime))
         })
         .collect();
 
@@ -208,7 +381,7 @@ pub fn scan_vault_metadata(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<
     let mut results = Vec::with_capacity(hits.len() + reads.len());
     results.append(&mut hits);
 
-    for (canonical, relative, sprout, size, mtime) in reads {
+    for (canonical, relative, sprout, content, size, mtime) in reads {
         if let Some(d) = mtime {
             cache.insert(
                 canonical.clone(),
@@ -216,32 +389,69 @@ pub fn scan_vault_metadata(vault: &Path, exclude_dirs: &[String]) -> Result<Vec<
                 d.subsec_nanos(),
                 size,
                 sprout.clone(),
+                content.clone(),
             );
         }
         results.push(NoteMetaInfo {
             path: canonical,
             relative_path: relative,
             sprout,
+            content,
         });
     }
 
-    cache.save();
-    Ok(results)
+    results
 }
 
 /// Read and parse a single note file.
 pub fn read_note(path: &Path) -> Result<ParsedNote, SproutError> {
     let content = std::fs::read_to_string(path)
         .map_err(|_| SproutError::FileNotFound(path.display().to_string()))?;
-    Ok(parse_note(&content))
+    Ok(parse_note_at(path, &content))
 }
 
-/// Write content to a note file.
+/// Write content to a note file atomically (see `write_atomic`).
 pub fn write_note(path: &Path, content: &str) -> Result<(), SproutError> {
-    std::fs::write(path, content)
+    write_atomic(path, content.as_bytes())
         .map_err(|e| SproutError::ParseError(format!("failed to write {}: {e}", path.display())))
 }
 
+/// Write `data` to a uniquely named temp file next to `path`, fsync it and
+/// rename it over `path`, so that readers and crashes see either the old or
+/// the new content, never a truncated file. An existing file keeps its
+/// permissions; a symlink keeps pointing at the replaced file.
+pub fn write_atomic(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let dir = match path.parent() {
+        Some(d) if !d.as_os_str().is_empty() => d,
+        _ => Path::new("."),
+    };
+
+    let existing = std::fs::metadata(&path).ok().map(|m| m.permissions());
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".sprout-").suffix(".tmp");
+    #[cfg(unix)]
+    if existing.is_none() {
+        use std::os::unix::fs::PermissionsExt;
+        // Same mode as `fs::write` would give a new file (0666 minus umask)
+        builder.permissions(std::fs::Permissions::from_mode(0o666));
+    }
+    let mut tmp = builder.tempfile_in(dir)?;
+    if let Some(permissions) = existing {
+        tmp.as_file().set_permissions(permissions)?;
+    }
+    tmp.write_all(data)?;
+    tmp.as_file().sync_all()?;
+    tmp.persist(&path).map_err(|e| e.error)?;
+
+    // Make the rename itself durable
+    #[cfg(unix)]
+    if let Ok(d) = std::fs::File::open(dir) {
+        let _ = d.sync_all();
+    }
+    Ok(())
+}
+
 /// Ensure the file is inside the vault directory.
 /// Both paths are canonicalized before comparison.
 pub fn ensure_in_vault(file: &Path, vault: &Path) -> Result<(), SproutError> {
@@ -265,6 +475,13 @@ mod tests {
     use std::fs;
     use tempfile::TempDir;
 
+    fn excluding(dir: &str) -> ScanOptions {
+        ScanOptions {
+            exclude_dirs: vec![dir.into()],
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_read_note_valid() {
         let dir = TempDir::new().unwrap();
@@ -332,7 +549,7 @@ mod tests {
         fs::write(dir.path().join("plain.md"), "No frontmatter\n").unwrap();
         fs::write(dir.path().join("not_md.txt"), "ignored").unwrap();
 
-        let notes = scan_vault(dir.path(), &[]).unwrap();
+        let notes = scan_vault(dir.path(), &ScanOptions::default()).unwrap();
         assert_eq!(notes.len(), 2); // only .md files
         let paths: Vec<_> = notes.iter().map(|n| n.relative_path.as_str()).collect();
         assert!(paths.contains(&"tracked.md"));
@@ -347,7 +564,7 @@ mod tests {
         fs::create_dir(&subdir).unwrap();
         fs::write(subdir.join("hidden.md"), "---\nmaturity: seedling\n---\n").unwrap();
 
-        let notes = scan_vault(dir.path(), &[".obsidian".into()]).unwrap();
+        let notes = scan_vault(dir.path(), &excluding(".obsidian")).unwrap();
         assert_eq!(notes.len(), 1);
         assert_eq!(notes[0].relative_path, "root.md");
     }
@@ -359,14 +576,14 @@ mod tests {
         fs::create_dir(&subdir).unwrap();
         fs::write(subdir.join("deep.md"), "---\nmaturity: budding\n---\n").unwrap();
 
-        let notes = scan_vault(dir.path(), &[]).unwrap();
+        let notes = scan_vault(dir.path(), &ScanOptions::default()).unwrap();
         assert_eq!(notes.len(), 1);
         assert!(notes[0].relative_path.contains("deep.md"));
     }
 
     #[test]
     fn test_scan_vault_nonexistent() {
-        let result = scan_vault(Path::new("/nonexistent/vault"), &[]);
+        let result = scan_vault(Path::new("/nonexistent/vault"), &ScanOptions::default());
         assert!(result.is_err());
     }
 
@@ -377,7 +594,7 @@ mod tests {
         fs::write(dir.path().join("b.md"), "content").unwrap();
         fs::write(dir.path().join("c.txt"), "content").unwrap();
 
-        let paths = scan_vault_paths(dir.path(), &[]).unwrap();
+        let paths = scan_vault_paths(dir.path(), &ScanOptions::default()).unwrap();
         assert_eq!(paths.len(), 2);
         let rel: Vec<_> = paths.iter().map(|p| p.relative_path.as_str()).collect();
         assert!(rel.contains(&"a.md"));
@@ -392,8 +609,140 @@ mod tests {
         fs::create_dir(&hidden).unwrap();
         fs::write(hidden.join("hidden.md"), "content").unwrap();
 
-        let paths = scan_vault_paths(dir.path(), &[".obsidian".into()]).unwrap();
+        let paths = scan_vault_paths(dir.path(), &excluding(".obsidian")).unwrap();
         assert_eq!(paths.len(), 1);
         assert_eq!(paths[0].relative_path, "root.md");
     }
+
+    fn scanned(dir: &Path, options: &ScanOptions) -> Vec<String> {
+        let mut rel: Vec<_> = scan_vault_paths(dir, options)
+            .unwrap()
+            .into_iter()
+            .map(|p| p.relative_path)
+            .collect();
+        rel.sort();
+        rel
+    }
+
+    #[test]
+    fn test_sproutignore_patterns() {
+        let dir = TempDir::new().unwrap();
+        for sub in ["drafts", "archive", "projects/archive", "journal"] {
+            fs::create_dir_all(dir.path().join(sub)).unwrap();
+        }
+        for file in [
+            "keep.md",
+            "scratch-1.md",
+            "drafts/a.md",
+            "archive/old.md",
+            "projects/archive/nested.md",
+            "journal/2026.md",
+            "journal/important.md",
+        ] {
+            fs::write(dir.path().join(file), "content").unwrap();
+        }
+        // Glob, anchored directory, negation
+        fs::write(
+            dir.path().join(SPROUTIGNORE),
+            "scratch-*.md\n/archive/\njournal/*\n!journal/important.md\n",
+        )
+        .unwrap();
+        // Nested ignore file applies relative to its own directory
+        fs::write(dir.path().join("drafts").join(SPROUTIGNORE), "a.md\n").unwrap();
+
+        assert_eq!(
+            scanned(dir.path(), &ScanOptions::default()),
+            vec!["journal/important.md", "keep.md", "projects/archive/nested.md"]
+        );
+    }
+
+    #[test]
+    fn test_gitignore_only_when_enabled() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("a.md"), "content").unwrap();
+        fs::write(dir.path().join("build.md"), "content").unwrap();
+        fs::write(dir.path().join(".gitignore"), "build.md\n").unwrap();
+
+        assert_eq!(scanned(dir.path(), &ScanOptions::default()), vec!["a.md", "build.md"]);
+        let options = ScanOptions {
+            respect_gitignore: true,
+            ..Default::default()
+        };
+        assert_eq!(scanned(dir.path(), &options), vec!["a.md"]);
+    }
+
+    #[test]
+    fn test_configured_extensions() {
+        let dir = TempDir::new().unwrap();
+        for file in ["a.md", "b.markdown", "c.MDX", "d.org", "e.txt"] {
+            fs::write(dir.path().join(file), "content").unwrap();
+        }
+
+        assert_eq!(scanned(dir.path(), &ScanOptions::default()), vec!["a.md"]);
+        let options = ScanOptions {
+            extensions: vec!["md".into(), "markdown".into(), "mdx".into(), "org".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            scanned(dir.path(), &options),
+            vec!["a.md", "b.markdown", "c.MDX", "d.org"]
+        );
+    }
+
+    #[test]
+    fn test_note_content_extract() {
+        let md = "---\ntags: [Rust]\n---\nIntro #idea\n\n## First heading ##\nSee [[a]] and [b](b.md).\n# Later\n";
+        let content = NoteContent::extract(md, &parse_note_at(Path::new("n.md"), md));
+        assert_eq!(content.links, vec!["a", "b.md"]);
+        assert_eq!(content.tags, vec!["rust", "idea"]);
+        assert_eq!(content.title.as_deref(), Some("First heading"));
+        assert_eq!(content.word_count, 12);
+
+        let md = "---\ntitle: Zettel\nid: 1a\n---\n# Heading\n";
+        let content = NoteContent::extract(md, &parse_note_at(Path::new("n.md"), md));
+        assert_eq!(content.title.as_deref(), Some("Zettel"));
+        assert_eq!(content.id.as_deref(), Some("1a"));
+
+        // An org-roam `:ID:` is not a note ID
+        let org = ":PROPERTIES:\n:ID: x\n:END:\n#+title: Zettel\n* Heading\nBody\n";
+        let content = NoteContent::extract(org, &parse_note_at(Path::new("n.org"), org));
+        assert_eq!(content.title.as_deref(), Some("Zettel"));
+        assert_eq!(content.id, None);
+        assert!(content.links.is_empty());
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_content_in_place() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("note.md");
+        fs::write(&file, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+        let link = dir.path().join("link.md");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        #[cfg(unix)]
+        write_atomic(&link, b"new").unwrap();
+        #[cfg(not(unix))]
+        write_atomic(&file, b"new").unwrap();
+
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+            assert_eq!(fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o640);
+        }
+        // No temp files are left behind
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+    }
 }
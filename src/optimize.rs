@@ -0,0 +1,330 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::cli::Rating;
+use crate::history::ReviewRecord;
+use crate::links::link_factor;
+use crate::srs::{calculate_with, SrsInput, SrsParams};
+
+/// Minimum number of follow-up reviews a group needs before its parameter is refitted.
+pub const MIN_SAMPLES: usize = 10;
+
+pub struct ParamFit {
+    pub name: &'static str,
+    pub current: f64,
+    pub recommended: f64,
+    pub samples: usize,
+}
+
+pub struct OptimizeResult {
+    pub reviews: usize,
+    pub follow_ups: usize,
+    pub fits: Vec<ParamFit>,
+    /// Share of follow-up reviews not rated hard under the current parameters
+    pub current_retention: f64,
+    pub predicted_retention: f64,
+    /// Relative change in reviews per day (e.g. -0.1 = 10% fewer reviews)
+    pub workload_change: f64,
+}
+
+/// A review paired with the outcome of the next review of the same note.
+struct FollowUp<'a> {
+    review: &'a ReviewRecord,
+    recalled: bool,
+}
+
+/// Fit scheduler parameters to the review history.
+///
+/// Each review is paired with the next review of the same note. The share of
+/// follow-ups not rated hard is the observed retention for the interval the
+/// review produced. Assuming exponential forgetting, scaling an interval by
+/// `α` turns retention `R` into `R^α`, so `α = ln(target) / ln(R)` is the
+/// factor that would have hit the target. Each parameter is scaled by the `α`
+/// of the reviews it controls; groups with fewer than `MIN_SAMPLES`
+/// follow-ups keep their current value.
+///
+/// Returns `None` when there are fewer than `MIN_SAMPLES` follow-ups overall.
+pub fn optimize(
+    records: &[ReviewRecord],
+    current: &SrsParams,
+    link_weight: f64,
+    max_interval: u32,
+    target_retention: f64,
+) -> Option<OptimizeResult> {
+    let by_note = group_by_note(records);
+    let follow_ups: Vec<FollowUp> = by_note
+        .values()
+        .flat_map(|reviews| {
+            reviews.windows(2).map(|w| FollowUp {
+                review: w[0],
+                recalled: w[1].rating != Rating::Hard,
+            })
+        })
+        .collect();
+
+    if follow_ups.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let overall = retention(follow_ups.iter());
+    let after = |rating: Rating| {
+        follow_ups
+            .iter()
+            .filter(|f| f.review.rating == rating)
+            .collect::<Vec<_>>()
+    };
+    let after_hard = after(Rating::Hard);
+    let after_good = after(Rating::Good);
+    let after_easy = after(Rating::Easy);
+
+    // good_multiplier scales Good intervals directly
+    let good_multiplier = match scale(&after_good, target_retention) {
+        Some(a) => (current.good_multiplier * a).clamp(0.5, 1.5),
+        None => current.good_multiplier,
+    };
+
+    // Ease deltas act through the ease the review leaves behind: pick the
+    // delta that would have scaled that ease by α.
+    let fit_delta = |group: &[&FollowUp], current_delta: f64, sign: f64| match scale(group, target_retention) {
+        Some(a) => {
+            let mean = group
+                .iter()
+                .map(|f| sign * (a * f.review.new_ease - f.review.ease))
+                .sum::<f64>()
+                / group.len() as f64;
+            mean.clamp(0.0, 0.5)
+        }
+        None => current_delta,
+    };
+    let hard_ease_delta = fit_delta(&after_hard, current.hard_ease_delta, -1.0);
+    let easy_ease_delta = fit_delta(&after_easy, current.easy_ease_delta, 1.0);
+
+    // link_weight: compare well-linked and sparsely linked notes after a
+    // successful review, and solve for the weight that equalises their α.
+    let recalled_reviews: Vec<&FollowUp> = follow_ups
+        .iter()
+        .filter(|f| f.review.rating != Rating::Hard)
+        .collect();
+    let (linked, unlinked): (Vec<&FollowUp>, Vec<&FollowUp>) = recalled_reviews
+        .iter()
+        .partition(|f| link_factor(f.review.link_count) >= 0.5);
+    let fitted_link_weight = match (scale(&linked, target_retention), scale(&unlinked, target_retention)) {
+        (Some(a_hi), Some(a_lo)) => {
+            let lf_hi = mean_link_factor(&linked);
+            let lf_lo = mean_link_factor(&unlinked);
+            let ratio = (1.0 + link_weight * lf_hi) / (1.0 + link_weight * lf_lo) * a_hi / a_lo;
+            let denom = lf_hi - ratio * lf_lo;
+            if denom.abs() < f64::EPSILON {
+                link_weight
+            } else {
+                ((ratio - 1.0) / denom).clamp(0.0, 0.5)
+            }
+        }
+        _ => link_weight,
+    };
+
+    let recommended = SrsParams {
+        hard_ease_delta,
+        easy_ease_delta,
+        good_multiplier,
+    };
+
+    // Replay every note's history with the recommended parameters, letting
+    // ease evolve under the new deltas, and compare the resulting intervals.
+    let mut load_current = 0.0;
+    let mut load_recommended = 0.0;
+    let mut ratios: BTreeMap<(&PathBuf, usize), f64> = BTreeMap::new();
+    for (path, reviews) in &by_note {
+        let mut ease = reviews[0].ease;
+        for (i, r) in reviews.iter().enumerate() {
+            let out = calculate_with(
+                &SrsInput {
+                    interval: r.interval,
+                    ease,
+                    next_review: r.date - chrono::Duration::days(r.delayed),
+                    today: r.date,
+                    rating: r.rating.clone(),
+                    link_count: r.link_count,
+                    link_weight: fitted_link_weight,
+                    max_interval,
+                },
+                &recommended,
+            );
+            ease = out.new_ease;
+            load_current += 1.0 / r.new_interval.max(1) as f64;
+            load_recommended += 1.0 / out.new_interval as f64;
+            ratios.insert((path, i), out.new_interval as f64 / r.new_interval.max(1) as f64);
+        }
+    }
+
+    let group_retention = |rating: &Rating| {
+        let group = match rating {
+            Rating::Hard => &after_hard,
+            Rating::Good => &after_good,
+            Rating::Easy => &after_easy,
+        };
+        if group.len() >= MIN_SAMPLES {
+            retention(group.iter().copied())
+        } else {
+            overall
+        }
+    };
+    let mut predicted = 0.0;
+    for (path, reviews) in &by_note {
+        for (i, r) in reviews.iter().enumerate().take(reviews.len().saturating_sub(1)) {
+            let base = group_retention(&r.rating).clamp(0.5, 0.99);
+            predicted += base.powf(ratios[&(*path, i)]);
+        }
+    }
+
+    let fits = vec![
+        ParamFit {
+            name: "hard_ease_delta",
+            current: current.hard_ease_delta,
+            recommended: hard_ease_delta,
+            samples: after_hard.len(),
+        },
+        ParamFit {
+            name: "easy_ease_delta",
+            current: current.easy_ease_delta,
+            recommended: easy_ease_delta,
+            samples: after_easy.len(),
+        },
+        ParamFit {
+            name: "good_multiplier",
+            current: current.good_multiplier,
+            recommended: good_multiplier,
+            samples: after_good.len(),
+        },
+        ParamFit {
+            name: "link_weight",
+            current: link_weight,
+            recommended: fitted_link_weight,
+            samples: linked.len().min(unlinked.len()),
+        },
+    ];
+
+    Some(OptimizeResult {
+        reviews: records.len(),
+        follow_ups: follow_ups.len(),
+        fits,
+        current_retention: overall,
+        predicted_retention: predicted / follow_ups.len() as f64,
+        workload_change: load_recommended / load_current - 1.0,
+    })
+}
+
+fn group_by_note(records: &[ReviewRecord]) -> BTreeMap<&PathBuf, Vec<&ReviewRecord>> {
+    let mut by_note: BTreeMap<&PathBuf, Vec<&ReviewRecord>> = BTreeMap::new();
+    for r in records {
+        by_note.entry(&r.path).or_default().push(r);
+    }
+    for reviews in by_note.values_mut() {
+        reviews.sort_by_key(|r| r.date);
+    }
+    by_note
+}
+
+fn retention<'a>(group: impl Iterator<Item = &'a FollowUp<'a>>) -> f64 {
+    let (mut total, mut recalled) = (0usize, 0usize);
+    for f in group {
+        total += 1;
+        if f.recalled {
+            recalled += 1;
+        }
+    }
+    if total == 0 {
+        0.0
+    } else {
+        recalled as f64 / total as f64
+    }
+}
+
+/// Interval scale factor that would move the group's retention to `target`.
+fn scale(group: &[&FollowUp], target: f64) -> Option<f64> {
+    if group.len() < MIN_SAMPLES {
+        return None;
+    }
+    // Clamp so that perfect or zero recall still yields a finite factor
+    let observed = retention(group.iter().copied()).clamp(0.5, 0.99);
+    Some(target.ln() / observed.ln())
+}
+
+fn mean_link_factor(group: &[&FollowUp]) -> f64 {
+    group
+        .iter()
+        .map(|f| link_factor(f.review.link_count))
+        .sum::<f64>()
+        / group.len().max(1) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Build `n` notes, each reviewed `first` then `second`.
+    fn history(n: usize, first: Rating, second: Rating) -> Vec<ReviewRecord> {
+        let d0 = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        let mut records = Vec::new();
+        for i in 0..n {
+            let path = PathBuf::from(format!("/vault/{i}.md"));
+            for (day, rating) in [(0, first.clone()), (10, second.clone())] {
+                records.push(ReviewRecord {
+                    date: d0 + chrono::Duration::days(day),
+                    path: path.clone(),
+                    rating,
+                    maturity: "seedling".into(),
+                    interval: 10,
+                    ease: 2.5,
+                    delayed: 0,
+                    link_count: 0,
+                    new_interval: 20,
+                    new_ease: 2.5,
+                });
+            }
+        }
+        records
+    }
+
+    #[test]
+    fn test_insufficient_history() {
+        let records = history(MIN_SAMPLES - 1, Rating::Good, Rating::Good);
+        assert!(optimize(&records, &SrsParams::default(), 0.1, 90, 0.9).is_none());
+    }
+
+    #[test]
+    fn test_perfect_recall_lengthens_good_intervals() {
+        let records = history(20, Rating::Good, Rating::Good);
+        let result = optimize(&records, &SrsParams::default(), 0.1, 90, 0.9).unwrap();
+        let good = result.fits.iter().find(|f| f.name == "good_multiplier").unwrap();
+        assert!(good.recommended > good.current);
+        assert!(result.workload_change < 0.0);
+        assert_eq!(result.follow_ups, 20);
+        assert!((result.current_retention - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_poor_recall_shortens_good_intervals() {
+        let mut records = history(10, Rating::Good, Rating::Hard);
+        records.extend(history(10, Rating::Good, Rating::Good).into_iter().map(|mut r| {
+            r.path = PathBuf::from(format!("/vault/ok-{}", r.path.display()));
+            r
+        }));
+        let result = optimize(&records, &SrsParams::default(), 0.1, 90, 0.9).unwrap();
+        let good = result.fits.iter().find(|f| f.name == "good_multiplier").unwrap();
+        assert!(good.recommended < good.current);
+        assert!(result.workload_change > 0.0);
+        assert!(result.predicted_retention > result.current_retention);
+    }
+
+    #[test]
+    fn test_small_groups_keep_current_value() {
+        let records = history(20, Rating::Good, Rating::Good);
+        let params = SrsParams::default();
+        let result = optimize(&records, &params, 0.1, 90, 0.9).unwrap();
+        let hard = result.fits.iter().find(|f| f.name == "hard_ease_delta").unwrap();
+        assert_eq!(hard.samples, 0);
+        assert_eq!(hard.recommended, params.hard_ease_delta);
+    }
+}
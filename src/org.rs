@@ -0,0 +1,258 @@
+use chrono::NaiveDate;
+use regex::Regex;
+
+use crate::frontmatter::{NoteFormat, ParsedNote, SproutFrontmatter, SPROUT_FIELDS};
+
+/// Org-mode backend: sprout fields live in the file-level property drawer,
+/// the `:PROPERTIES:` … `:END:` block before any other content, as org-roam
+/// writes it. Keys are written upper-case and read case-insensitively.
+///
+/// `frontmatter_raw` holds the lines between `:PROPERTIES:` and `:END:`.
+pub fn parse_note(content: &str) -> ParsedNote {
+    let content = content.replace("\r\n", "\n");
+    match split_drawer(&content) {
+        Some((raw, body)) => ParsedNote {
+            sprout: sprout_fields(&raw),
+            frontmatter_raw: Some(raw),
+            body,
+            format: NoteFormat::Org,
+        },
+        None => ParsedNote {
+            frontmatter_raw: None,
+            sprout: SproutFrontmatter::default(),
+            body: content,
+            format: NoteFormat::Org,
+        },
+    }
+}
+
+/// Split a leading property drawer from the rest of the file.
+/// Blank lines before the drawer are dropped. `None` if there is no
+/// drawer or it is never closed.
+fn split_drawer(content: &str) -> Option<(String, String)> {
+    let rest = content.trim_start_matches(['\n', ' ', '\t']);
+    let (first, mut remaining) = rest.split_once('\n')?;
+    if !first.trim().eq_ignore_ascii_case(":PROPERTIES:") {
+        return None;
+    }
+
+    let mut raw = String::new();
+    loop {
+        let (line, next) = match remaining.split_once('\n') {
+            Some((l, n)) => (l, n),
+            None => (remaining, ""),
+        };
+        if line.trim().eq_ignore_ascii_case(":END:") {
+            return Some((raw, next.to_string()));
+        }
+        if next.is_empty() && line == remaining {
+            return None;
+        }
+        raw.push_str(line);
+        raw.push('\n');
+        remaining = next;
+    }
+}
+
+fn property_re() -> Regex {
+    Regex::new(r"(?m)^\s*:([^:\s]+):[ \t]*(.*?)[ \t]*$").unwrap()
+}
+
+fn sprout_fields(raw: &str) -> SproutFrontmatter {
+    let mut sprout = SproutFrontmatter::default();
+    for cap in property_re().captures_iter(raw) {
+        let value = &cap[2];
+        match cap[1].to_ascii_lowercase().as_str() {
+            "maturity" => sprout.maturity = Some(value.to_string()),
+            "created" => sprout.created = parse_date(value),
+            "last_review" => sprout.last_review = parse_date(value),
+            "review_interval" => sprout.review_interval = value.parse().ok(),
+            "next_review" => sprout.next_review = parse_date(value),
+            "ease" => sprout.ease = value.parse().ok(),
+            _ => {}
+        }
+    }
+    sprout
+}
+
+/// Sprout properties of the file-level drawer with their zero-based line
+/// and value, for `NoteFormat::check_fields`. Empty values count as absent.
+pub fn drawer_fields(content: &str) -> Vec<(usize, String, String)> {
+    let mut lines = content.lines().enumerate().skip_while(|(_, l)| l.trim().is_empty());
+    match lines.next() {
+        Some((_, l)) if l.trim().eq_ignore_ascii_case(":PROPERTIES:") => {}
+        _ => return Vec::new(),
+    }
+    let re = property_re();
+    lines
+        .take_while(|(_, l)| !l.trim().eq_ignore_ascii_case(":END:"))
+        .filter_map(|(i, line)| {
+            let cap = re.captures(line)?;
+            let key = cap[1].to_ascii_lowercase();
+            (SPROUT_FIELDS.contains(&key.as_str()) && !cap[2].is_empty()).then(|| (i, key, cap[2].to_string()))
+        })
+        .collect()
+}
+
+/// Names in the drawer's `:ROAM_ALIASES:` property, space separated, with
+/// multi-word names in double quotes as org-roam writes them.
+pub fn roam_aliases(content: &str) -> Vec<String> {
+    let content = content.replace("\r\n", "\n");
+    let Some((raw, _)) = split_drawer(&content) else {
+        return Vec::new();
+    };
+    let value_re = Regex::new(r#""([^"]*)"|(\S+)"#).unwrap();
+    property_re()
+        .captures_iter(&raw)
+        .filter(|cap| cap[1].eq_ignore_ascii_case("roam_aliases"))
+        .flat_map(|cap| {
+            value_re
+                .captures_iter(&cap[2])
+                .filter_map(|v| v.get(1).or(v.get(2)).map(|m| m.as_str().to_string()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Accepts `2026-03-01` as well as org timestamps such as `[2026-03-01 Sun]`.
+pub fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim_start_matches(['[', '<']);
+    NaiveDate::parse_from_str(value.get(..10)?, "%Y-%m-%d").ok()
+}
+
+fn key_pattern(key: &str) -> String {
+    format!(r"(?mi)^(\s*:{}:)[ \t]*(.*)$", regex::escape(key))
+}
+
+pub fn has_field(raw: &str, key: &str) -> bool {
+    Regex::new(&key_pattern(key)).unwrap().is_match(raw)
+}
+
+/// Replace the value of an existing property, keeping the key as written.
+pub fn replace_field(raw: &str, key: &str, new_value: &str) -> String {
+    let re = Regex::new(&key_pattern(key)).unwrap();
+    re.replace(raw, format!("${{1}} {new_value}")).to_string()
+}
+
+/// Append a `:KEY: value` line at the end of the drawer.
+pub fn append_field(raw: &str, key: &str, value: &str) -> String {
+    let trimmed = raw.trim_end_matches('\n');
+    let line = format!(":{}: {value}", key.to_ascii_uppercase());
+    if trimmed.is_empty() {
+        format!("{line}\n")
+    } else {
+        format!("{trimmed}\n{line}\n")
+    }
+}
+
+/// Update multiple properties: replace if present, append if not.
+/// Returns the reconstructed full file content.
+pub fn write_back(raw: &str, body: &str, updates: &[(&str, &str)]) -> String {
+    let mut drawer = raw.to_string();
+    for &(key, value) in updates {
+        if has_field(&drawer, key) {
+            drawer = replace_field(&drawer, key, value);
+        } else {
+            drawer = append_field(&drawer, key, value);
+        }
+    }
+    let drawer = drawer.trim_end_matches('\n');
+    format!(":PROPERTIES:\n{drawer}\n:END:\n{body}")
+}
+
+/// Build a new property drawer for a file that has none.
+pub fn build_new_drawer(fields: &[(&str, &str)], body: &str) -> String {
+    let lines: Vec<String> = fields
+        .iter()
+        .map(|&(key, value)| format!(":{}: {value}", key.to_ascii_uppercase()))
+        .collect();
+    format!(":PROPERTIES:\n{}\n:END:\n{body}", lines.join("\n"))
+}
+
+/// Tags from `#+filetags: :a:b:` lines.
+pub fn filetags(content: &str) -> Vec<String> {
+    let re = Regex::new(r"(?mi)^#\+filetags:[ \t]*(.*)$").unwrap();
+    re.captures_iter(content)
+        .flat_map(|cap| {
+            cap[1]
+                .split([':', ' ', '\t'])
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_fields() {
+        assert!(NoteFormat::Org.check_fields(NOTE).is_empty());
+        let note = "\n:PROPERTIES:\n:MATURITY: budding\n:NEXT_REVIEW: soon\n:EASE:\n:END:\n:EASE: x\n";
+        let errors = NoteFormat::Org.check_fields(note);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert!(errors[0].message.starts_with("next_review: invalid date `soon`"));
+    }
+
+    const NOTE: &str = ":PROPERTIES:\n:ID:       1234-abcd\n:MATURITY: budding\n:created: [2026-01-01 Thu]\n:LAST_REVIEW: 2026-03-01\n:REVIEW_INTERVAL: 6\n:NEXT_REVIEW: <2026-03-07 Sat>\n:EASE: 2.35\n:END:\n#+title: Zettel\n#+filetags: :rust:cli:\n\nBody\n";
+
+    #[test]
+    fn test_parse_drawer() {
+        let parsed = parse_note(NOTE);
+        assert_eq!(parsed.format, NoteFormat::Org);
+        assert_eq!(parsed.sprout.maturity.as_deref(), Some("budding"));
+        assert_eq!(parsed.sprout.created, NaiveDate::from_ymd_opt(2026, 1, 1));
+        assert_eq!(parsed.sprout.review_interval, Some(6));
+        assert_eq!(parsed.sprout.next_review, NaiveDate::from_ymd_opt(2026, 3, 7));
+        assert_eq!(parsed.sprout.ease, Some(2.35));
+        assert!(parsed.body.starts_with("#+title: Zettel\n"));
+        assert!(parsed.frontmatter_raw.unwrap().contains(":ID:"));
+    }
+
+    #[test]
+    fn test_parse_without_drawer() {
+        let parsed = parse_note("#+title: Plain\n\nBody\n");
+        assert!(parsed.frontmatter_raw.is_none());
+        assert!(parsed.sprout.maturity.is_none());
+        assert_eq!(parsed.body, "#+title: Plain\n\nBody\n");
+
+        // Unclosed drawer is not a drawer
+        let parsed = parse_note(":PROPERTIES:\n:MATURITY: seedling\n");
+        assert!(parsed.frontmatter_raw.is_none());
+    }
+
+    #[test]
+    fn test_write_back_roundtrip() {
+        let parsed = parse_note(NOTE);
+        let raw = parsed.frontmatter_raw.as_deref().unwrap();
+        let content = write_back(raw, &parsed.body, &[("next_review", "2026-03-20"), ("review_interval", "13")]);
+        assert!(content.starts_with(":PROPERTIES:\n:ID:       1234-abcd\n"));
+        assert!(content.contains(":NEXT_REVIEW: 2026-03-20\n"));
+        assert!(content.contains(":REVIEW_INTERVAL: 13\n"));
+        assert!(content.contains(":END:\n#+title: Zettel\n"));
+
+        let reparsed = parse_note(&content);
+        assert_eq!(reparsed.sprout.review_interval, Some(13));
+        assert_eq!(reparsed.body, parsed.body);
+    }
+
+    #[test]
+    fn test_append_and_build() {
+        let raw = ":ID: x\n";
+        assert!(!has_field(raw, "maturity"));
+        let raw = append_field(raw, "maturity", "seedling");
+        assert_eq!(raw, ":ID: x\n:MATURITY: seedling\n");
+        assert!(has_field(&raw, "maturity"));
+
+        let content = build_new_drawer(&[("maturity", "seedling"), ("ease", "2.50")], "Body\n");
+        assert_eq!(content, ":PROPERTIES:\n:MATURITY: seedling\n:EASE: 2.50\n:END:\nBody\n");
+    }
+
+    #[test]
+    fn test_filetags() {
+        assert_eq!(filetags(NOTE), vec!["rust", "cli"]);
+    }
+}
@@ -1,12 +1,59 @@
+use std::cell::RefCell;
+
 use chrono::NaiveDate;
 use colored::Colorize;
+use serde::Serialize;
 use serde_json::{json, Value};
+use crate::bulk::Failure;
+use crate::cache::CacheStats;
 use crate::cli::OutputFormat;
+use crate::commands::agenda::Agenda;
+use crate::commands::autolink::Autolink;
+use crate::commands::check::{BrokenLink, Missing};
+use crate::commands::related::Related;
+use crate::commands::search::SearchHit;
+use crate::commands::show::ReferencedSection;
+use crate::index::IndexEvent;
+use crate::note::NoteMetaInfo;
+use crate::optimize::{self, OptimizeResult};
+use crate::stats::{DetailedStats, ReviewCalendar};
+
+thread_local! {
+    /// JSON documents collected by `capture` instead of being printed.
+    static CAPTURED: RefCell<Option<Vec<Value>>> = const { RefCell::new(None) };
+}
+
+/// Print a JSON document on one line, or collect it when inside `capture`.
+fn print_json<T: Serialize + ?Sized>(value: &T) {
+    let captured = CAPTURED.with(|c| match c.borrow_mut().as_mut() {
+        Some(docs) => {
+            docs.push(serde_json::to_value(value).unwrap());
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        println!("{}", serde_json::to_string(value).unwrap());
+    }
+}
+
+/// Run `f` and return the JSON documents it would have printed, so that
+/// `sprout serve` can answer with the same output as the CLI. Only JSON
+/// output is collected; call the command with `OutputFormat::Json`.
+pub fn capture<T>(f: impl FnOnce() -> T) -> (T, Vec<Value>) {
+    let previous = CAPTURED.with(|c| c.replace(Some(Vec::new())));
+    let result = f();
+    let docs = CAPTURED.with(|c| c.replace(previous)).unwrap_or_default();
+    (result, docs)
+}
 
 // ── review / list ──────────────────────────────────────────────────
 
+/// (path, relative_path, maturity, review_interval, next_review, ease)
+pub type NoteListEntry = (String, String, Option<String>, Option<u32>, Option<NaiveDate>, Option<f64>);
+
 pub fn format_note_list(
-    notes: &[(String, String, Option<String>, Option<u32>, Option<NaiveDate>, Option<f64>)],
+    notes: &[NoteListEntry],
     format: &OutputFormat,
 ) {
     match format {
@@ -42,7 +89,7 @@ pub fn format_note_list(
                     Value::Object(obj)
                 })
                 .collect();
-            println!("{}", serde_json::to_string(&arr).unwrap());
+            print_json(&arr);
         }
         OutputFormat::Human => {
             if notes.is_empty() {
@@ -80,15 +127,8 @@ pub fn format_done(
 ) {
     match format {
         OutputFormat::Json => {
-            let obj = json!({
-                "path": path,
-                "maturity": maturity,
-                "last_review": last_review.to_string(),
-                "new_interval": new_interval,
-                "next_review": next_review.to_string(),
-                "ease": ease,
-            });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            let obj = done_json(path, maturity, last_review, new_interval, next_review, ease);
+            print_json(&obj);
         }
         OutputFormat::Human => {
             println!(
@@ -99,8 +139,27 @@ pub fn format_done(
     }
 }
 
+pub fn done_json(
+    path: &str,
+    maturity: &str,
+    last_review: NaiveDate,
+    new_interval: u32,
+    next_review: NaiveDate,
+    ease: f64,
+) -> Value {
+    json!({
+        "path": path,
+        "maturity": maturity,
+        "last_review": last_review.to_string(),
+        "new_interval": new_interval,
+        "next_review": next_review.to_string(),
+        "ease": ease,
+    })
+}
+
 // ── stats ──────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 pub fn format_stats(
     total: usize,
     seedling: usize,
@@ -108,11 +167,13 @@ pub fn format_stats(
     evergreen: usize,
     due_today: usize,
     overdue: usize,
+    details: Option<&DetailedStats>,
+    calendar: Option<&ReviewCalendar>,
     format: &OutputFormat,
 ) {
     match format {
         OutputFormat::Json => {
-            let obj = json!({
+            let mut obj = json!({
                 "total": total,
                 "seedling": seedling,
                 "budding": budding,
@@ -120,17 +181,199 @@ pub fn format_stats(
                 "due_today": due_today,
                 "overdue": overdue,
             });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            if let Some(d) = details {
+                let by_maturity: serde_json::Map<String, Value> = d
+                    .by_maturity
+                    .iter()
+                    .map(|m| {
+                        (
+                            m.maturity.to_string(),
+                            json!({
+                                "count": m.count,
+                                "avg_ease": m.avg_ease,
+                                "avg_interval": m.avg_interval,
+                            }),
+                        )
+                    })
+                    .collect();
+                let growth: Vec<Value> = d
+                    .growth
+                    .iter()
+                    .map(|g| json!({"month": g.month, "added": g.added, "total": g.total}))
+                    .collect();
+                let transitions: Vec<Value> = d
+                    .transitions
+                    .iter()
+                    .map(|t| json!({"from": t.from, "to": t.to, "count": t.count}))
+                    .collect();
+                obj["ratings"] = json!({
+                    "hard": d.hard,
+                    "good": d.good,
+                    "easy": d.easy,
+                });
+                obj["true_retention"] = json!(d.true_retention);
+                obj["by_maturity"] = Value::Object(by_maturity);
+                obj["growth"] = json!(growth);
+                obj["reviews_per_day"] = json!({
+                    "last_30": d.reviews_30d as f64 / 30.0,
+                    "last_90": d.reviews_90d as f64 / 90.0,
+                });
+                obj["transitions"] = json!(transitions);
+            }
+            if let Some(c) = calendar {
+                let days: Vec<Value> = c
+                    .days
+                    .iter()
+                    .map(|(date, count)| json!({"date": date.to_string(), "count": count}))
+                    .collect();
+                obj["streak"] = json!({
+                    "current": c.current_streak,
+                    "longest": c.longest_streak,
+                });
+                obj["calendar"] = json!(days);
+            }
+            print_json(&obj);
         }
         OutputFormat::Human => {
             println!("Total: {total} (seedling: {seedling}, budding: {budding}, evergreen: {evergreen})");
             println!("Due today: {due_today}, Overdue: {overdue}");
+            if let Some(d) = details {
+                print_stats_details(d);
+            }
+            if let Some(c) = calendar {
+                print_calendar(c);
+            }
         }
     }
 }
 
+fn print_stats_details(details: &DetailedStats) {
+    println!();
+    println!("Ratings: hard {}, good {}, easy {}", details.hard, details.good, details.easy);
+    match details.true_retention {
+        Some(r) => println!("True retention: {:.1}%", r * 100.0),
+        None => println!("True retention: - (no review history)"),
+    }
+    println!(
+        "Reviews per day: {:.2} (30d), {:.2} (90d)",
+        details.reviews_30d as f64 / 30.0,
+        details.reviews_90d as f64 / 90.0
+    );
+
+    println!();
+    println!("By maturity:");
+    for m in &details.by_maturity {
+        let ease = m.avg_ease.map(|e| format!("{e:.2}")).unwrap_or_else(|| "-".into());
+        let interval = m
+            .avg_interval
+            .map(|i| format!("{i:.1}d"))
+            .unwrap_or_else(|| "-".into());
+        println!(
+            "  {:<10} {:>5}  avg ease: {ease}  avg interval: {interval}",
+            m.maturity, m.count
+        );
+    }
+
+    if !details.growth.is_empty() {
+        println!();
+        println!("Growth (by created):");
+        for g in &details.growth {
+            println!("  {}  +{:<4} total {}", g.month, g.added, g.total);
+        }
+    }
+
+    if !details.transitions.is_empty() {
+        println!();
+        println!("Maturity transitions:");
+        for t in &details.transitions {
+            println!("  {} → {}: {}", t.from, t.to, t.count);
+        }
+    }
+}
+
+/// GitHub-style heatmap: one column per week (Sunday first), one row per weekday.
+fn print_calendar(calendar: &ReviewCalendar) {
+    use chrono::Datelike;
+
+    println!();
+    println!(
+        "Streak: {} day(s) current, {} day(s) longest",
+        calendar.current_streak, calendar.longest_streak
+    );
+
+    let (first, last) = match (calendar.days.first(), calendar.days.last()) {
+        (Some(f), Some(l)) => (f.0, l.0),
+        _ => return,
+    };
+    let max = calendar.days.iter().map(|&(_, c)| c).max().unwrap_or(0);
+    let grid_start = first - chrono::Duration::days(first.weekday().num_days_from_sunday() as i64);
+    let weeks = ((last - grid_start).num_days() / 7 + 1) as usize;
+
+    // Month labels above the first week that contains the 1st of a month
+    let mut header = String::from("    ");
+    let mut col = 0;
+    while col < weeks {
+        let week_start = grid_start + chrono::Duration::days(col as i64 * 7);
+        let label_day = (0..7)
+            .map(|d| week_start + chrono::Duration::days(d))
+            .find(|d| d.day() == 1 && *d >= first && *d <= last);
+        match label_day {
+            Some(d) if col + 2 <= weeks => {
+                header.push_str(&d.format("%b").to_string());
+                col += 2; // a 3-letter label spans ~2 columns
+                header.push(' ');
+            }
+            _ => {
+                header.push_str("  ");
+                col += 1;
+            }
+        }
+    }
+    println!();
+    println!("{}", header.trim_end());
+
+    let counts: std::collections::HashMap<NaiveDate, usize> =
+        calendar.days.iter().copied().collect();
+    for weekday in 0..7 {
+        let label = match weekday {
+            1 => "Mon",
+            3 => "Wed",
+            5 => "Fri",
+            _ => "",
+        };
+        let mut line = format!("{label:<4}");
+        for week in 0..weeks {
+            let day = grid_start + chrono::Duration::days((week * 7 + weekday) as i64);
+            if day < first || day > last {
+                line.push_str("  ");
+                continue;
+            }
+            let count = counts.get(&day).copied().unwrap_or(0);
+            let cell = match heat_level(count, max) {
+                0 => "■".bright_black(),
+                1 => "■".truecolor(155, 233, 168),
+                2 => "■".truecolor(64, 196, 99),
+                3 => "■".truecolor(48, 161, 78),
+                _ => "■".truecolor(33, 110, 57),
+            };
+            line.push_str(&format!("{cell} "));
+        }
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Bucket a day's review count into 0 (none) through 4 (busiest) relative to `max`.
+fn heat_level(count: usize, max: usize) -> usize {
+    if count == 0 || max == 0 {
+        0
+    } else {
+        (count * 4).div_ceil(max).clamp(1, 4)
+    }
+}
+
 // ── promote ────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 pub fn format_promote(
     path: &str,
     relative_path: &str,
@@ -143,16 +386,16 @@ pub fn format_promote(
 ) {
     match format {
         OutputFormat::Json => {
-            let obj = json!({
-                "path": path,
-                "relative_path": relative_path,
-                "previous_maturity": previous_maturity,
-                "new_maturity": new_maturity,
-                "review_interval": review_interval,
-                "next_review": next_review.map(|d| d.to_string()),
-                "ease": ease,
-            });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            let obj = promote_json(
+                path,
+                relative_path,
+                previous_maturity,
+                new_maturity,
+                review_interval,
+                next_review,
+                ease,
+            );
+            print_json(&obj);
         }
         OutputFormat::Human => {
             println!("Promoted: {previous_maturity} → {new_maturity}");
@@ -160,8 +403,29 @@ pub fn format_promote(
     }
 }
 
+pub fn promote_json(
+    path: &str,
+    relative_path: &str,
+    previous_maturity: &str,
+    new_maturity: &str,
+    review_interval: Option<u32>,
+    next_review: Option<NaiveDate>,
+    ease: Option<f64>,
+) -> Value {
+    json!({
+        "path": path,
+        "relative_path": relative_path,
+        "previous_maturity": previous_maturity,
+        "new_maturity": new_maturity,
+        "review_interval": review_interval,
+        "next_review": next_review.map(|d| d.to_string()),
+        "ease": ease,
+    })
+}
+
 // ── init ───────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 pub fn format_init(
     path: &str,
     relative_path: &str,
@@ -175,18 +439,17 @@ pub fn format_init(
 ) {
     match format {
         OutputFormat::Json => {
-            let mut obj = serde_json::Map::new();
-            obj.insert("path".into(), json!(path));
-            obj.insert("relative_path".into(), json!(relative_path));
-            obj.insert("maturity".into(), json!(maturity));
-            obj.insert("review_interval".into(), json!(review_interval));
-            obj.insert("next_review".into(), json!(next_review.to_string()));
-            obj.insert("ease".into(), json!(ease));
-            obj.insert("created".into(), json!(created.to_string()));
-            if let Some(fields) = fields_added {
-                obj.insert("fields_added".into(), json!(fields));
-            }
-            println!("{}", serde_json::to_string(&Value::Object(obj)).unwrap());
+            let obj = init_json(
+                path,
+                relative_path,
+                maturity,
+                review_interval,
+                next_review,
+                ease,
+                created,
+                fields_added,
+            );
+            print_json(&obj);
         }
         OutputFormat::Human => {
             println!("Initialized: {relative_path} [{maturity}]");
@@ -197,8 +460,82 @@ pub fn format_init(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+pub fn init_json(
+    path: &str,
+    relative_path: &str,
+    maturity: &str,
+    review_interval: u32,
+    next_review: NaiveDate,
+    ease: f64,
+    created: NaiveDate,
+    fields_added: Option<&[String]>,
+) -> Value {
+    let mut obj = serde_json::Map::new();
+    obj.insert("path".into(), json!(path));
+    obj.insert("relative_path".into(), json!(relative_path));
+    obj.insert("maturity".into(), json!(maturity));
+    obj.insert("review_interval".into(), json!(review_interval));
+    obj.insert("next_review".into(), json!(next_review.to_string()));
+    obj.insert("ease".into(), json!(ease));
+    obj.insert("created".into(), json!(created.to_string()));
+    if let Some(fields) = fields_added {
+        obj.insert("fields_added".into(), json!(fields));
+    }
+    Value::Object(obj)
+}
+
+// ── bulk ───────────────────────────────────────────────────────────
+
+/// Per-note result of a bulk command: (JSON object, human-readable line)
+pub type BulkEntry = (Value, String);
+
+pub fn format_bulk(
+    applied: bool,
+    results: &[BulkEntry],
+    errors: &[Failure],
+    format: &OutputFormat,
+) {
+    match format {
+        OutputFormat::Json => {
+            let errors: Vec<Value> = errors
+                .iter()
+                .map(|f| {
+                    json!({
+                        "file": f.file,
+                        "error": f.error.error_code(),
+                        "message": f.error.to_string(),
+                    })
+                })
+                .collect();
+            let obj = json!({
+                "applied": applied,
+                "succeeded": results.len(),
+                "failed": errors.len(),
+                "results": results.iter().map(|(v, _)| v).collect::<Vec<_>>(),
+                "errors": errors,
+            });
+            print_json(&obj);
+        }
+        OutputFormat::Human => {
+            for (_, line) in results {
+                println!("{line}");
+            }
+            for f in errors {
+                println!("{} {}", "failed:".red(), f.error);
+            }
+            if !applied && !errors.is_empty() {
+                println!("{}", "No notes were changed (--atomic)".yellow());
+            } else {
+                println!("{} succeeded, {} failed", results.len(), errors.len());
+            }
+        }
+    }
+}
+
 // ── show ───────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 pub fn format_show_tracked(
     path: &str,
     relative_path: &str,
@@ -211,6 +548,7 @@ pub fn format_show_tracked(
     is_due: bool,
     days_until_review: i64,
     link_count: usize,
+    sections: &[ReferencedSection],
     format: &OutputFormat,
 ) {
     match format {
@@ -228,8 +566,9 @@ pub fn format_show_tracked(
                 "is_due": is_due,
                 "days_until_review": days_until_review,
                 "link_count": link_count,
+                "referenced_sections": referenced_sections_json(sections),
             });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            print_json(&obj);
         }
         OutputFormat::Human => {
             let mat_colored = match maturity {
@@ -257,10 +596,28 @@ pub fn format_show_tracked(
             let due_str = if is_due { "YES".red().to_string() } else { "no".to_string() };
             println!("  Due: {due_str} ({days_until_review}d)");
             println!("  Links: {link_count}");
+            print_referenced_sections(sections);
         }
     }
 }
 
+fn referenced_sections_json(sections: &[ReferencedSection]) -> Vec<Value> {
+    sections
+        .iter()
+        .map(|s| json!({ "section": s.section, "referenced_by": s.referenced_by }))
+        .collect()
+}
+
+fn print_referenced_sections(sections: &[ReferencedSection]) {
+    if sections.is_empty() {
+        return;
+    }
+    println!("  Referenced sections:");
+    for s in sections {
+        println!("    #{} {}", s.section, format!("← {}", s.referenced_by.join(", ")).dimmed());
+    }
+}
+
 // ── note candidates (list all .md) ────────────────────────────────
 
 pub fn format_note_candidates(
@@ -278,7 +635,7 @@ pub fn format_note_candidates(
                     })
                 })
                 .collect();
-            println!("{}", serde_json::to_string(&arr).unwrap());
+            print_json(&arr);
         }
         OutputFormat::Human => {
             if notes.is_empty() {
@@ -292,6 +649,209 @@ pub fn format_note_candidates(
     }
 }
 
+// ── search ────────────────────────────────────────────────────────
+
+pub fn format_search(hits: &[SearchHit], format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let arr: Vec<Value> = hits
+                .iter()
+                .map(|h| {
+                    json!({
+                        "path": h.note.path.to_string_lossy(),
+                        "relative_path": h.note.relative_path,
+                        "title": h.note.content.title,
+                        "maturity": h.note.sprout.maturity,
+                        "tags": h.note.content.tags,
+                        "score": (h.score * 1000.0).round() / 1000.0,
+                        "snippets": h
+                            .snippets
+                            .iter()
+                            .map(|(line, text)| json!({"line": line, "text": text}))
+                            .collect::<Vec<_>>(),
+                    })
+                })
+                .collect();
+            print_json(&arr);
+        }
+        OutputFormat::Human => {
+            if hits.is_empty() {
+                println!("No notes found.");
+                return;
+            }
+            for h in hits {
+                let mut header = h.note.relative_path.bold().to_string();
+                if let Some(title) = &h.note.content.title {
+                    header.push_str(&format!("  {title}"));
+                }
+                if let Some(maturity) = &h.note.sprout.maturity {
+                    header.push_str(&format!(" [{maturity}]"));
+                }
+                println!("{header}");
+                for (line, text) in &h.snippets {
+                    println!("  {}: {text}", line.to_string().dimmed());
+                }
+            }
+        }
+    }
+}
+
+// ── related ───────────────────────────────────────────────────────
+
+pub fn format_related(related: &Related, format: &OutputFormat) {
+    let round = |x: f64| (x * 1000.0).round() / 1000.0;
+    match format {
+        OutputFormat::Json => {
+            let json = json!({
+                "path": related.note.path.to_string_lossy(),
+                "relative_path": related.note.relative_path,
+                "title": related.note.content.title,
+                "related": related.related.iter().map(|r| json!({
+                    "path": r.note.path.to_string_lossy(),
+                    "relative_path": r.note.relative_path,
+                    "title": r.note.content.title,
+                    "maturity": r.note.sprout.maturity,
+                    "score": round(r.score),
+                    "similarity": round(r.similarity),
+                    "shared_tags": r.shared_tags,
+                    "distance": r.distance,
+                })).collect::<Vec<_>>(),
+                "unlinked_mentions": related.mentions.iter().map(|m| json!({
+                    "path": m.path.to_string_lossy(),
+                    "relative_path": m.relative_path,
+                    "line": m.line,
+                    "column": m.column,
+                    "text": m.text,
+                })).collect::<Vec<_>>(),
+            });
+            print_json(&json);
+        }
+        OutputFormat::Human => {
+            println!("{}", format!("Related to {}", related.note.relative_path).bold());
+            if related.related.is_empty() {
+                println!("  No related notes found.");
+            }
+            for r in &related.related {
+                let mut line = format!("  {:.2}  {}", r.score, r.note.relative_path);
+                if let Some(title) = &r.note.content.title {
+                    line.push_str(&format!("  {title}"));
+                }
+                if let Some(maturity) = &r.note.sprout.maturity {
+                    line.push_str(&format!(" [{maturity}]"));
+                }
+                let mut reasons = vec![format!("similarity {:.2}", r.similarity)];
+                if !r.shared_tags.is_empty() {
+                    reasons.push(format!("tags: {}", r.shared_tags.join(", ")));
+                }
+                if let Some(d) = r.distance {
+                    reasons.push(format!("{d} links away"));
+                }
+                println!("{line}  {}", format!("({})", reasons.join("; ")).dimmed());
+            }
+
+            println!();
+            println!("{}", "Unlinked mentions".bold());
+            if related.mentions.is_empty() {
+                println!("  None.");
+            }
+            for m in &related.mentions {
+                println!("  {}: {}", format!("{}:{}:{}", m.relative_path, m.line, m.column).dimmed(), m.text);
+            }
+        }
+    }
+}
+
+// ── autolink ──────────────────────────────────────────────────────
+
+/// The proposed links as a unified diff, printed before anything is written.
+/// JSON carries the diff in `format_autolink` instead.
+pub fn format_autolink_diff(autolink: &Autolink, format: &OutputFormat) {
+    if *format == OutputFormat::Json || autolink.diff.is_empty() {
+        return;
+    }
+    println!("{}", format!("--- a/{}", autolink.relative_path).bold());
+    println!("{}", format!("+++ b/{}", autolink.relative_path).bold());
+    for change in &autolink.diff {
+        println!("{}", format!("@@ -{0} +{0} @@", change.line).cyan());
+        println!("{}", format!("-{}", change.old).red());
+        println!("{}", format!("+{}", change.new).green());
+    }
+}
+
+pub fn format_autolink(autolink: &Autolink, format: &OutputFormat) {
+    let applied = autolink.proposals.iter().filter(|p| p.applied).count();
+    match format {
+        OutputFormat::Json => {
+            let json = json!({
+                "path": autolink.path.to_string_lossy(),
+                "relative_path": autolink.relative_path,
+                "dry_run": autolink.dry_run,
+                "applied": applied,
+                "links": autolink.proposals.iter().map(|p| json!({
+                    "line": p.line,
+                    "column": p.column,
+                    "text": p.text,
+                    "target": p.relative_target,
+                    "link": p.link,
+                    "applied": p.applied,
+                })).collect::<Vec<_>>(),
+                "diff": autolink.diff.iter().map(|c| json!({
+                    "line": c.line,
+                    "old": c.old,
+                    "new": c.new,
+                })).collect::<Vec<_>>(),
+            });
+            print_json(&json);
+        }
+        OutputFormat::Human => {
+            let total = autolink.proposals.len();
+            if total == 0 {
+                println!("No unlinked mentions in {}.", autolink.relative_path);
+            } else if autolink.dry_run {
+                println!("{total} mentions can be linked in {} (run with --yes to link them).", autolink.relative_path);
+            } else {
+                println!("Linked: {applied} of {total} mentions in {}", autolink.relative_path);
+            }
+        }
+    }
+}
+
+// ── check ─────────────────────────────────────────────────────────
+
+pub fn format_check_links(broken: &[BrokenLink], notes: usize, format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let json = json!({
+                "notes": notes,
+                "broken": broken.iter().map(|b| json!({
+                    "path": b.path.to_string_lossy(),
+                    "relative_path": b.relative_path,
+                    "line": b.line,
+                    "column": b.column,
+                    "target": b.target,
+                    "problem": b.missing.code(),
+                })).collect::<Vec<_>>(),
+            });
+            print_json(&json);
+        }
+        OutputFormat::Human => {
+            for b in broken {
+                let reason = match b.missing {
+                    Missing::Note => "no such note",
+                    Missing::Heading => "no such heading",
+                    Missing::Block => "no such block",
+                };
+                println!("{}: {} ({})", format!("{}:{}:{}", b.relative_path, b.line, b.column).dimmed(), b.target, reason.red());
+            }
+            if broken.is_empty() {
+                println!("No broken links in {notes} notes.");
+            } else {
+                println!("{} broken links in {notes} notes", broken.len());
+            }
+        }
+    }
+}
+
 // ── note created ──────────────────────────────────────────────────
 
 pub fn format_note_created(
@@ -309,7 +869,7 @@ pub fn format_note_created(
                 "is_new": is_new,
                 "initialized": initialized,
             });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            print_json(&obj);
         }
         OutputFormat::Human => {
             if is_new {
@@ -327,19 +887,275 @@ pub fn format_note_created(
 
 // ── show (untracked) ──────────────────────────────────────────────
 
-pub fn format_show_untracked(path: &str, relative_path: &str, format: &OutputFormat) {
+pub fn format_show_untracked(path: &str, relative_path: &str, sections: &[ReferencedSection], format: &OutputFormat) {
     match format {
         OutputFormat::Json => {
             let obj = json!({
                 "path": path,
                 "relative_path": relative_path,
                 "tracked": false,
+                "referenced_sections": referenced_sections_json(sections),
             });
-            println!("{}", serde_json::to_string(&obj).unwrap());
+            print_json(&obj);
         }
         OutputFormat::Human => {
             println!("{relative_path} [not tracked]");
+            print_referenced_sections(sections);
+        }
+    }
+}
+
+
+// ── optimize ──────────────────────────────────────────────────────
+
+pub fn format_optimize(result: &OptimizeResult, target_retention: f64, format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let params: Vec<Value> = result
+                .fits
+                .iter()
+                .map(|f| {
+                    json!({
+                        "name": f.name,
+                        "current": f.current,
+                        "recommended": f.recommended,
+                        "samples": f.samples,
+                        "fitted": f.samples >= optimize::MIN_SAMPLES,
+                    })
+                })
+                .collect();
+            let obj = json!({
+                "reviews": result.reviews,
+                "follow_ups": result.follow_ups,
+                "target_retention": target_retention,
+                "parameters": params,
+                "current_retention": result.current_retention,
+                "predicted_retention": result.predicted_retention,
+                "workload_change": result.workload_change,
+            });
+            print_json(&obj);
+        }
+        OutputFormat::Human => {
+            println!(
+                "Fitted to {} reviews ({} with a follow-up review)",
+                result.reviews, result.follow_ups
+            );
+            println!();
+            println!("Recommended config.toml values:");
+            for f in &result.fits {
+                let note = if f.samples >= optimize::MIN_SAMPLES {
+                    format!("current {:.2}, {} samples", f.current, f.samples)
+                } else {
+                    format!("unchanged, only {} samples", f.samples)
+                };
+                println!("  {} = {:.2}  # {note}", f.name, f.recommended);
+            }
+            println!();
+            println!(
+                "Predicted retention: {:.1}% → {:.1}% (target {:.1}%)",
+                result.current_retention * 100.0,
+                result.predicted_retention * 100.0,
+                target_retention * 100.0
+            );
+            println!("Predicted workload: {:+.1}%", result.workload_change * 100.0);
+            println!("{}", "Not applied: edit your config.toml to use these values.".dimmed());
+        }
+    }
+}
+
+// ── agenda ────────────────────────────────────────────────────────
+
+fn agenda_note_json(n: &NoteMetaInfo, today: NaiveDate) -> Value {
+    json!({
+        "path": n.path.to_string_lossy(),
+        "relative_path": n.relative_path,
+        "maturity": n.sprout.maturity,
+        "review_interval": n.sprout.review_interval,
+        "next_review": n.sprout.next_review.map(|d| d.to_string()),
+        "ease": n.sprout.ease,
+        "days_until_review": n.sprout.next_review.map(|d| (d - today).num_days()),
+    })
+}
+
+pub fn format_agenda(agenda: &Agenda, stale_days: u32, format: &OutputFormat) {
+    let today = agenda.today;
+    match format {
+        OutputFormat::Json => {
+            let list = |notes: &[&NoteMetaInfo]| -> Vec<Value> {
+                notes.iter().map(|n| agenda_note_json(n, today)).collect()
+            };
+            let overdue: Vec<Value> = agenda
+                .overdue
+                .iter()
+                .map(|g| {
+                    json!({
+                        "label": g.label,
+                        "min_days": g.min_days,
+                        "max_days": g.max_days,
+                        "notes": list(&g.notes),
+                    })
+                })
+                .collect();
+            let stale: Vec<Value> = agenda
+                .stale_seedlings
+                .iter()
+                .map(|n| {
+                    let mut obj = agenda_note_json(n, today);
+                    obj["created"] = json!(n.sprout.created.map(|d| d.to_string()));
+                    obj["days_since_created"] =
+                        json!(n.sprout.created.map(|d| (today - d).num_days()));
+                    obj
+                })
+                .collect();
+            let incomplete: Vec<Value> = agenda
+                .incomplete
+                .iter()
+                .map(|(n, missing)| {
+                    json!({
+                        "path": n.path.to_string_lossy(),
+                        "relative_path": n.relative_path,
+                        "maturity": n.sprout.maturity,
+                        "missing_fields": missing,
+                    })
+                })
+                .collect();
+            let obj = json!({
+                "today": today.to_string(),
+                "due_today": list(&agenda.due_today),
+                "overdue": overdue,
+                "upcoming": list(&agenda.upcoming),
+                "stale_seedlings": stale,
+                "stale_days": stale_days,
+                "incomplete": incomplete,
+            });
+            print_json(&obj);
+        }
+        OutputFormat::Human => {
+            let empty = agenda.due_today.is_empty()
+                && agenda.overdue.iter().all(|g| g.notes.is_empty())
+                && agenda.upcoming.is_empty()
+                && agenda.stale_seedlings.is_empty()
+                && agenda.incomplete.is_empty();
+            if empty {
+                println!("Nothing on the agenda.");
+                return;
+            }
+
+            if !agenda.due_today.is_empty() {
+                println!("{}", "Due today".bold());
+                for n in &agenda.due_today {
+                    println!("  {}", n.relative_path);
+                }
+            }
+
+            if agenda.overdue.iter().any(|g| !g.notes.is_empty()) {
+                println!("{}", "Overdue".red().bold());
+                for g in agenda.overdue.iter().filter(|g| !g.notes.is_empty()) {
+                    println!("  {}:", g.label);
+                    for n in &g.notes {
+                        let late = n.sprout.next_review.map(|d| (today - d).num_days()).unwrap_or(0);
+                        println!("    {}  ({late}d late)", n.relative_path);
+                    }
+                }
+            }
+
+            if !agenda.upcoming.is_empty() {
+                println!("{}", "Next 7 days".bold());
+                for n in &agenda.upcoming {
+                    let nr = n.sprout.next_review.map(|d| d.to_string()).unwrap_or_default();
+                    println!("  {}  next: {nr}", n.relative_path);
+                }
+            }
+
+            if !agenda.stale_seedlings.is_empty() {
+                println!("{}", format!("Seedlings untouched for {stale_days}+ days").green().bold());
+                for n in &agenda.stale_seedlings {
+                    let created = n.sprout.created.map(|d| d.to_string()).unwrap_or_default();
+                    println!("  {}  created: {created}", n.relative_path);
+                }
+            }
+
+            if !agenda.incomplete.is_empty() {
+                println!("{}", "Missing sprout fields".yellow().bold());
+                for (n, missing) in &agenda.incomplete {
+                    println!("  {}  missing: {}", n.relative_path, missing.join(", "));
+                }
+            }
         }
     }
 }
 
+
+// ── cache ─────────────────────────────────────────────────────────
+
+pub fn format_cache_stats(stats: &CacheStats, format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let obj = json!({
+                "vault": stats.vault.to_string_lossy(),
+                "path": stats.path.as_ref().map(|p| p.to_string_lossy()),
+                "entries": stats.entries,
+                "stale": stats.stale,
+                "size_bytes": stats.size_bytes,
+            });
+            print_json(&obj);
+        }
+        OutputFormat::Human => {
+            println!("Vault: {}", stats.vault.display());
+            match &stats.path {
+                Some(p) => println!("Cache: {} ({} bytes)", p.display(), stats.size_bytes),
+                None => println!("Cache: (no cache directory)"),
+            }
+            println!("Entries: {} ({} stale)", stats.entries, stats.stale);
+        }
+    }
+}
+
+pub fn format_cache_clear(vault: &str, path: Option<&str>, removed: bool, format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            let obj = json!({
+                "vault": vault,
+                "path": path,
+                "removed": removed,
+            });
+            print_json(&obj);
+        }
+        OutputFormat::Human => {
+            if removed {
+                println!("Cleared cache for {vault}");
+            } else {
+                println!("No cache for {vault}");
+            }
+        }
+    }
+}
+
+// ── watch ─────────────────────────────────────────────────────────
+
+/// One event per line, so that editor integrations can read stdout as a stream.
+pub fn format_watch_event(event: &IndexEvent, format: &OutputFormat) {
+    match format {
+        OutputFormat::Json => {
+            print_json(event);
+        }
+        OutputFormat::Human => match event {
+            IndexEvent::Ready { notes, due, broken_links } => {
+                println!("Watching {notes} notes ({due} due, {broken_links} broken links)");
+            }
+            IndexEvent::Added { relative_path, .. } => println!("{} {relative_path}", "added".green()),
+            IndexEvent::Changed { relative_path, .. } => println!("{} {relative_path}", "changed".cyan()),
+            IndexEvent::Removed { relative_path, .. } => println!("{} {relative_path}", "removed".red()),
+            IndexEvent::Renamed { from, relative_path, .. } => {
+                println!("{} {relative_path} (from {from})", "renamed".cyan());
+            }
+            IndexEvent::Due { relative_path, .. } => println!("{} {relative_path}", "due".yellow().bold()),
+            IndexEvent::LinkBroken { relative_path, target, .. } => {
+                println!("{} {relative_path} -> {target}", "link broken".red());
+            }
+            IndexEvent::LinkFixed { relative_path, target, .. } => {
+                println!("{} {relative_path} -> {target}", "link fixed".green());
+            }
+        },
+    }
+}
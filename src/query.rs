@@ -0,0 +1,580 @@
+use std::collections::HashSet;
+
+use chrono::{Duration, NaiveDate};
+use gray_matter::{engine::YAML, Matter};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::error::SproutError;
+use crate::note::NoteMetaInfo;
+use crate::org;
+
+/// A parsed query expression, e.g.
+/// `maturity:budding AND tag:rust AND interval>14 AND path:projects/ AND links<2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Query {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Cond(Condition),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Maturity(String),
+    Tag(String),
+    /// Substring of the vault-relative path
+    Path(String),
+    Interval(CmpOp, f64),
+    Ease(CmpOp, f64),
+    Links(CmpOp, f64),
+    Created(CmpOp, DateValue),
+    NextReview(CmpOp, DateValue),
+    LastReview(CmpOp, DateValue),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CmpOp {
+    Eq,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+/// A date literal; `today` offsets are resolved at evaluation time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateValue {
+    Absolute(NaiveDate),
+    Today(i64),
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Query, SproutError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            end: input.chars().count() + 1,
+        };
+        let expr = parser.parse_or()?;
+        if let Some(tok) = parser.peek() {
+            return Err(query_error(tok.column, &format!("unexpected {}", tok.kind.describe())));
+        }
+        Ok(Query { expr })
+    }
+
+    /// Evaluate against a note. Conditions on missing fields are false.
+    pub fn matches(&self, note: &NoteMetaInfo, today: NaiveDate) -> bool {
+        self.expr.eval(note, today)
+    }
+}
+
+impl Expr {
+    fn eval(&self, note: &NoteMetaInfo, today: NaiveDate) -> bool {
+        match self {
+            Expr::And(a, b) => a.eval(note, today) && b.eval(note, today),
+            Expr::Or(a, b) => a.eval(note, today) || b.eval(note, today),
+            Expr::Not(e) => !e.eval(note, today),
+            Expr::Cond(c) => c.eval(note, today),
+        }
+    }
+}
+
+/// Whether the note has `tag` (lowercase, without `#`), which also matches
+/// nested tags such as `rust/async` for `rust`.
+pub fn has_tag(note: &NoteMetaInfo, tag: &str) -> bool {
+    note.content
+        .tags
+        .iter()
+        .any(|t| t == tag || t.strip_prefix(tag).is_some_and(|rest| rest.starts_with('/')))
+}
+
+impl Condition {
+    fn eval(&self, note: &NoteMetaInfo, today: NaiveDate) -> bool {
+        let s = &note.sprout;
+        match self {
+            Condition::Maturity(m) => s.maturity.as_deref().is_some_and(|v| v.eq_ignore_ascii_case(m)),
+            Condition::Tag(t) => has_tag(note, t),
+            Condition::Path(p) => note.relative_path.contains(p.as_str()),
+            Condition::Interval(op, v) => s.review_interval.is_some_and(|i| op.compare(f64::from(i), *v)),
+            Condition::Ease(op, v) => s.ease.is_some_and(|e| op.compare(e, *v)),
+            Condition::Links(op, v) => op.compare(note.content.links.len() as f64, *v),
+            Condition::Created(op, d) => s.created.is_some_and(|c| op.compare(c, d.resolve(today))),
+            Condition::NextReview(op, d) => s.next_review.is_some_and(|c| op.compare(c, d.resolve(today))),
+            Condition::LastReview(op, d) => s.last_review.is_some_and(|c| op.compare(c, d.resolve(today))),
+        }
+    }
+}
+
+impl CmpOp {
+    fn compare<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            CmpOp::Eq => left == right,
+            CmpOp::Lt => left < right,
+            CmpOp::Gt => left > right,
+            CmpOp::Le => left <= right,
+            CmpOp::Ge => left >= right,
+        }
+    }
+}
+
+impl DateValue {
+    fn resolve(self, today: NaiveDate) -> NaiveDate {
+        match self {
+            DateValue::Absolute(d) => d,
+            DateValue::Today(offset) => today + Duration::days(offset),
+        }
+    }
+}
+
+/// Keep only notes matching `query` (all notes when `None`).
+/// Tags and links come from the scan, so no note is read here.
+pub fn filter(notes: Vec<NoteMetaInfo>, query: Option<&Query>, today: NaiveDate) -> Vec<NoteMetaInfo> {
+    let query = match query {
+        Some(q) => q,
+        None => return notes,
+    };
+    notes.into_iter().filter(|n| query.matches(n, today)).collect()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TagsField {
+    List(Vec<String>),
+    One(String),
+}
+
+#[derive(Deserialize)]
+struct TagsFrontmatter {
+    tags: Option<TagsField>,
+}
+
+/// Collect tags from the frontmatter `tags` field (list or space/comma separated
+/// string), org-mode `#+filetags` and inline `#tag`s in the body.
+/// Lowercased and deduplicated.
+pub fn extract_tags(content: &str, body: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut seen = HashSet::new();
+    let mut push = |tag: &str| {
+        let tag = tag.trim().trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && seen.insert(tag.clone()) {
+            tags.push(tag);
+        }
+    };
+
+    let content = content.replace("\r\n", "\n");
+    let matter: Matter<YAML> = Matter::new();
+    if let Ok(parsed) = matter.parse::<TagsFrontmatter>(&content) {
+        match parsed.data.and_then(|d| d.tags) {
+            Some(TagsField::List(list)) => list.iter().for_each(|t| push(t)),
+            Some(TagsField::One(s)) => s.split([' ', ',']).for_each(&mut push),
+            None => {}
+        }
+    }
+
+    // Org-mode `#+filetags: :a:b:`
+    for tag in org::filetags(&content) {
+        push(&tag);
+    }
+
+    // Inline tags: `#` at the start of a word, not a heading or URL fragment
+    let inline_re = Regex::new(r"(?:^|\s)#([\p{L}\p{N}_][\p{L}\p{N}_/-]*)").unwrap();
+    for cap in inline_re.captures_iter(body) {
+        push(&cap[1]);
+    }
+    tags
+}
+
+fn query_error(column: usize, message: &str) -> SproutError {
+    SproutError::ParseError(format!("query: {message} at column {column}"))
+}
+
+// ── lexer ──
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(String),
+    Op(CmpOp),
+    Colon,
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl TokenKind {
+    fn describe(&self) -> String {
+        match self {
+            TokenKind::Word(w) => format!("'{w}'"),
+            TokenKind::Op(op) => format!("'{}'", op_str(*op)),
+            TokenKind::Colon => "':'".into(),
+            TokenKind::And => "'AND'".into(),
+            TokenKind::Or => "'OR'".into(),
+            TokenKind::Not => "'NOT'".into(),
+            TokenKind::LParen => "'('".into(),
+            TokenKind::RParen => "')'".into(),
+        }
+    }
+}
+
+fn op_str(op: CmpOp) -> &'static str {
+    match op {
+        CmpOp::Eq => "=",
+        CmpOp::Lt => "<",
+        CmpOp::Gt => ">",
+        CmpOp::Le => "<=",
+        CmpOp::Ge => ">=",
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    /// 1-based character column
+    column: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, SproutError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let single = match c {
+            '(' => Some(TokenKind::LParen),
+            ')' => Some(TokenKind::RParen),
+            ':' => Some(TokenKind::Colon),
+            '=' => Some(TokenKind::Op(CmpOp::Eq)),
+            _ => None,
+        };
+        if c.is_whitespace() {
+            i += 1;
+        } else if let Some(kind) = single {
+            tokens.push(Token { kind, column });
+            i += 1;
+        } else if c == '<' || c == '>' {
+            let with_eq = chars.get(i + 1) == Some(&'=');
+            let op = match (c, with_eq) {
+                ('<', false) => CmpOp::Lt,
+                ('<', true) => CmpOp::Le,
+                ('>', false) => CmpOp::Gt,
+                _ => CmpOp::Ge,
+            };
+            tokens.push(Token { kind: TokenKind::Op(op), column });
+            i += if with_eq { 2 } else { 1 };
+        } else if c == '"' {
+            let start = i + 1;
+            let end = chars[start..]
+                .iter()
+                .position(|&ch| ch == '"')
+                .map(|p| start + p)
+                .ok_or_else(|| query_error(column, "unterminated string"))?;
+            tokens.push(Token {
+                kind: TokenKind::Word(chars[start..end].iter().collect()),
+                column,
+            });
+            i = end + 1;
+        } else {
+            let start = i;
+            while i < chars.len() && !chars[i].is_whitespace() && !"():=<>\"".contains(chars[i]) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            let kind = match word.as_str() {
+                "AND" | "and" => TokenKind::And,
+                "OR" | "or" => TokenKind::Or,
+                "NOT" | "not" => TokenKind::Not,
+                _ => TokenKind::Word(word),
+            };
+            tokens.push(Token { kind, column });
+        }
+    }
+    Ok(tokens)
+}
+
+// ── parser ──
+//
+// or      := and ("OR" and)*
+// and     := unary (["AND"] unary)*
+// unary   := "NOT" unary | "(" or ")" | field op value
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Column just past the end of the input, for "expected ..." errors
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, SproutError> {
+        let mut left = self.parse_and()?;
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, SproutError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => self.pos += 1,
+                // Juxtaposition is an implicit AND
+                Some(TokenKind::Word(_)) | Some(TokenKind::Not) | Some(TokenKind::LParen) => {}
+                _ => break,
+            }
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, SproutError> {
+        let tok = match self.next() {
+            Some(t) => t,
+            None => return Err(query_error(self.end, "expected a condition")),
+        };
+        match tok.kind {
+            TokenKind::Not => Ok(Expr::Not(Box::new(self.parse_unary()?))),
+            TokenKind::LParen => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token { kind: TokenKind::RParen, .. }) => Ok(expr),
+                    Some(t) => Err(query_error(t.column, &format!("expected ')', found {}", t.kind.describe()))),
+                    None => Err(query_error(tok.column, "unclosed '('")),
+                }
+            }
+            TokenKind::Word(field) => self.parse_condition(&field, tok.column),
+            other => Err(query_error(tok.column, &format!("expected a condition, found {}", other.describe()))),
+        }
+    }
+
+    fn parse_condition(&mut self, field: &str, column: usize) -> Result<Expr, SproutError> {
+        let op_tok = self
+            .next()
+            .ok_or_else(|| query_error(self.end, &format!("expected an operator after '{field}'")))?;
+        let op = match op_tok.kind {
+            TokenKind::Colon => None,
+            TokenKind::Op(op) => Some(op),
+            other => {
+                return Err(query_error(
+                    op_tok.column,
+                    &format!("expected an operator after '{field}', found {}", other.describe()),
+                ))
+            }
+        };
+        let value_tok = self
+            .next()
+            .ok_or_else(|| query_error(self.end, &format!("expected a value after '{field}'")))?;
+        let value = match value_tok.kind {
+            TokenKind::Word(w) => w,
+            other => {
+                return Err(query_error(
+                    value_tok.column,
+                    &format!("expected a value after '{field}', found {}", other.describe()),
+                ))
+            }
+        };
+        let value_column = value_tok.column;
+
+        let text = |op: Option<CmpOp>| -> Result<String, SproutError> {
+            match op {
+                None | Some(CmpOp::Eq) => Ok(value.clone()),
+                Some(op) => Err(query_error(
+                    op_tok.column,
+                    &format!("'{field}' does not support '{}'", op_str(op)),
+                )),
+            }
+        };
+        let number = || -> Result<f64, SproutError> {
+            value
+                .parse::<f64>()
+                .map_err(|_| query_error(value_column, &format!("'{field}' expects a number, found '{value}'")))
+        };
+        let date = || -> Result<DateValue, SproutError> {
+            parse_date(&value).ok_or_else(|| {
+                query_error(
+                    value_column,
+                    &format!("'{field}' expects a date (YYYY-MM-DD, today, today-N), found '{value}'"),
+                )
+            })
+        };
+        let cmp = op.unwrap_or(CmpOp::Eq);
+
+        let cond = match field {
+            "maturity" => {
+                let m = text(op)?.to_lowercase();
+                if !["seedling", "budding", "evergreen"].contains(&m.as_str()) {
+                    return Err(query_error(value_column, &format!("unknown maturity '{value}'")));
+                }
+                Condition::Maturity(m)
+            }
+            "tag" => Condition::Tag(text(op)?.trim_start_matches('#').to_lowercase()),
+            "path" => Condition::Path(text(op)?),
+            "interval" => Condition::Interval(cmp, number()?),
+            "ease" => Condition::Ease(cmp, number()?),
+            "links" => Condition::Links(cmp, number()?),
+            "created" => Condition::Created(cmp, date()?),
+            "next_review" => Condition::NextReview(cmp, date()?),
+            "last_review" => Condition::LastReview(cmp, date()?),
+            _ => return Err(query_error(column, &format!("unknown field '{field}'"))),
+        };
+        Ok(Expr::Cond(cond))
+    }
+}
+
+fn parse_date(value: &str) -> Option<DateValue> {
+    if let Some(rest) = value.strip_prefix("today") {
+        if rest.is_empty() {
+            return Some(DateValue::Today(0));
+        }
+        let (sign, digits) = match rest.split_at(1) {
+            ("+", d) => (1, d),
+            ("-", d) => (-1, d),
+            _ => return None,
+        };
+        return digits.parse::<i64>().ok().map(|n| DateValue::Today(sign * n));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .map(DateValue::Absolute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::SproutFrontmatter;
+    use crate::note::NoteContent;
+    use std::path::PathBuf;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn note(path: &str, maturity: &str, interval: u32) -> NoteMetaInfo {
+        NoteMetaInfo {
+            path: PathBuf::from(format!("/vault/{path}")),
+            relative_path: path.to_string(),
+            sprout: SproutFrontmatter {
+                maturity: Some(maturity.into()),
+                created: Some(date(2026, 1, 1)),
+                last_review: Some(date(2026, 3, 1)),
+                review_interval: Some(interval),
+                next_review: Some(date(2026, 3, 1) + Duration::days(interval as i64)),
+                ease: Some(2.5),
+            },
+            content: NoteContent::default(),
+        }
+    }
+
+    fn with_content(mut note: NoteMetaInfo, tags: &[&str], link_count: usize) -> NoteMetaInfo {
+        note.content.tags = tags.iter().map(|t| t.to_string()).collect();
+        note.content.links = (0..link_count).map(|i| format!("link{i}")).collect();
+        note
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let q = Query::parse("maturity:budding OR maturity:seedling AND interval>14").unwrap();
+        assert_eq!(
+            q.expr,
+            Expr::Or(
+                Box::new(Expr::Cond(Condition::Maturity("budding".into()))),
+                Box::new(Expr::And(
+                    Box::new(Expr::Cond(Condition::Maturity("seedling".into()))),
+                    Box::new(Expr::Cond(Condition::Interval(CmpOp::Gt, 14.0))),
+                )),
+            )
+        );
+    }
+
+    #[test]
+    fn test_full_example() {
+        let q = Query::parse("maturity:budding AND tag:rust AND interval>14 AND path:projects/ AND links<2").unwrap();
+        let today = date(2026, 3, 31);
+        let n = || note("projects/a.md", "budding", 20);
+        assert!(q.matches(&with_content(n(), &["rust"], 1), today));
+        assert!(!q.matches(&with_content(n(), &["rust"], 2), today));
+        assert!(!q.matches(&with_content(n(), &["go"], 0), today));
+        assert!(!q.matches(&with_content(note("projects/a.md", "budding", 10), &["rust"], 0), today));
+        assert!(!q.matches(&with_content(note("areas/a.md", "budding", 20), &["rust"], 0), today));
+        // A note without tags never matches a tag condition
+        assert!(!q.matches(&n(), today));
+    }
+
+    #[test]
+    fn test_not_and_parens() {
+        let q = Query::parse("NOT (maturity:evergreen OR ease>=3)").unwrap();
+        let today = date(2026, 3, 31);
+        assert!(q.matches(&note("a.md", "budding", 5), today));
+        assert!(!q.matches(&note("a.md", "evergreen", 5), today));
+    }
+
+    #[test]
+    fn test_dates_relative_to_today() {
+        let today = date(2026, 3, 31);
+        let n = note("a.md", "seedling", 30); // next_review 2026-03-31
+        assert!(Query::parse("next_review<=today").unwrap().matches(&n, today));
+        assert!(!Query::parse("next_review<today").unwrap().matches(&n, today));
+        assert!(Query::parse("next_review>today-1").unwrap().matches(&n, today));
+        assert!(Query::parse("created=2026-01-01").unwrap().matches(&n, today));
+        assert!(Query::parse("last_review<2026-03-02").unwrap().matches(&n, today));
+    }
+
+    #[test]
+    fn test_nested_tags_and_implicit_and() {
+        let q = Query::parse("tag:#Rust path:a").unwrap();
+        let today = date(2026, 3, 31);
+        assert!(q.matches(&with_content(note("a.md", "seedling", 3), &["rust/async"], 0), today));
+        assert!(!q.matches(&with_content(note("a.md", "seedling", 3), &["rusty"], 0), today));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |q: &str| match Query::parse(q) {
+            Err(SproutError::ParseError(msg)) => msg,
+            other => panic!("expected parse error for {q:?}, got {other:?}"),
+        };
+        assert_eq!(err("color:red"), "query: unknown field 'color' at column 1");
+        assert_eq!(err("interval>abc"), "query: 'interval' expects a number, found 'abc' at column 10");
+        assert_eq!(err("path<x"), "query: 'path' does not support '<' at column 5");
+        assert_eq!(err("maturity:tree"), "query: unknown maturity 'tree' at column 10");
+        assert_eq!(err("ease>2 AND"), "query: expected a condition at column 11");
+        assert_eq!(err("(ease>2"), "query: unclosed '(' at column 1");
+        assert_eq!(err("ease>2)"), "query: unexpected ')' at column 7");
+        assert_eq!(err("created>yesterday"), "query: 'created' expects a date (YYYY-MM-DD, today, today-N), found 'yesterday' at column 9");
+        assert_eq!(err("path:\"a b"), "query: unterminated string at column 6");
+        assert_eq!(err(""), "query: expected a condition at column 1");
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        let content = "---\ntags: [Rust, cli]\nmaturity: seedling\n---\nBody #idea and #rust but not # heading or a#b\n";
+        let parsed = crate::frontmatter::parse_note(content);
+        assert_eq!(extract_tags(content, &parsed.body), vec!["rust", "cli", "idea"]);
+
+        let content = "---\ntags: zettel, writing\n---\nBody\n";
+        assert_eq!(extract_tags(content, "Body\n"), vec!["zettel", "writing"]);
+    }
+}
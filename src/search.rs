@@ -0,0 +1,615 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use rayon::prelude::*;
+
+use crate::cache::{self, Reader};
+use crate::error::SproutError;
+use crate::frontmatter::parse_note_at;
+use crate::note;
+
+/// Search index file layout (all integers little-endian), stored next to
+/// the vault's frontmatter cache as `<hash>.idx`:
+///
+/// ```text
+/// header:  "SPRS" u32:INDEX_VERSION u32:len vault-path
+/// docs:    u32:count, per note: u32:len path i64:mtime_secs u32:mtime_nanos u64:size u32:length
+/// terms:   u32:count, per term: u32:len term u32:count, per note: u32:doc u32:count positions
+/// ```
+///
+/// `doc` is the note's index in the docs table and `length` its number of
+/// tokens; positions are token offsets, used for phrase queries. Unlike the
+/// frontmatter cache the file is rewritten in full whenever notes change.
+const MAGIC: &[u8; 4] = b"SPRS";
+const INDEX_VERSION: u32 = 1;
+
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// Longest snippet, in characters.
+const SNIPPET_CHARS: usize = 160;
+
+struct Doc {
+    path: PathBuf,
+    mtime_secs: i64,
+    mtime_nanos: u32,
+    size: u64,
+    /// Number of tokens
+    length: u32,
+    /// Distinct terms, to remove the note's postings
+    terms: Vec<String>,
+}
+
+/// Inverted index over the file names and bodies of a vault's notes.
+pub struct SearchIndex {
+    vault: PathBuf,
+    /// Where `save` writes; `None` when there is no user cache directory
+    path: Option<PathBuf>,
+    docs: HashMap<u32, Doc>,
+    ids: HashMap<PathBuf, u32>,
+    next_id: u32,
+    /// Term → positions in each note containing it
+    postings: BTreeMap<String, Vec<(u32, Vec<u32>)>>,
+    dirty: bool,
+}
+
+/// One clause of a search: consecutive words, the last matched as a prefix
+/// when `prefix` is set. A single word is a one-word phrase.
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    words: Vec<String>,
+    prefix: bool,
+}
+
+/// Parsed search terms; a note must match every one.
+///
+/// `rust async` finds notes with both words, `"borrow checker"` the phrase
+/// and `borr*` any word starting with `borr`. Punctuated words such as
+/// `e-mail` and CJK text, which is indexed character by character, are
+/// matched as phrases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchQuery {
+    terms: Vec<Term>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Result<Self, SproutError> {
+        let mut terms = Vec::new();
+        let mut rest = input.trim_start();
+        while !rest.is_empty() {
+            let (text, next) = match rest.strip_prefix('"') {
+                Some(quoted) => {
+                    let end = quoted
+                        .find('"')
+                        .ok_or_else(|| SproutError::ParseError("unterminated quote in search".into()))?;
+                    (&quoted[..end], &quoted[end + 1..])
+                }
+                None => {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            let prefix = text.ends_with('*');
+            let words = tokenize(text.trim_end_matches('*'));
+            if !words.is_empty() {
+                terms.push(Term { words, prefix });
+            }
+            rest = next.trim_start();
+        }
+        if terms.is_empty() {
+            return Err(SproutError::ParseError("empty search".into()));
+        }
+        Ok(SearchQuery { terms })
+    }
+
+    /// Whether `tokens` (one line) contain the term at index `i`.
+    fn line_has(&self, i: usize, tokens: &[String]) -> bool {
+        let term = &self.terms[i];
+        let last = term.words.len() - 1;
+        tokens.windows(term.words.len()).any(|window| {
+            window.iter().zip(&term.words).enumerate().all(|(j, (token, word))| {
+                if term.prefix && j == last {
+                    token.starts_with(word.as_str())
+                } else {
+                    token == word
+                }
+            })
+        })
+    }
+}
+
+/// Lowercased words. Letters and digits form words; CJK characters, which
+/// are written without spaces, are one token each.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in text.chars() {
+        if is_cjk(c) {
+            if !word.is_empty() {
+                tokens.push(std::mem::take(&mut word));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.extend(c.to_lowercase());
+        } else if !word.is_empty() {
+            tokens.push(std::mem::take(&mut word));
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// Kana, CJK ideographs and half-width katakana.
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0xFF66..=0xFF9F)
+}
+
+/// Up to `max` lines of `content` matching the most terms of `query`, in
+/// file order, as (1-based line number, trimmed text). Lines of the
+/// frontmatter are skipped, as they are not indexed.
+pub fn snippets(path: &Path, content: &str, query: &SearchQuery, max: usize) -> Vec<(usize, String)> {
+    let skip = body_start(path, content);
+    let mut matches: Vec<(usize, usize, &str)> = content
+        .lines()
+        .enumerate()
+        .skip(skip)
+        .filter_map(|(i, line)| {
+            let tokens = tokenize(line);
+            let hits = (0..query.terms.len()).filter(|&t| query.line_has(t, &tokens)).count();
+            (hits > 0).then_some((hits, i + 1, line))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.truncate(max);
+    matches.sort_by_key(|m| m.1);
+    matches
+        .into_iter()
+        .map(|(_, line, text)| (line, excerpt(text)))
+        .collect()
+}
+
+/// Number of lines before the body: the frontmatter or property drawer.
+pub fn body_start(path: &Path, content: &str) -> usize {
+    let body_lines = parse_note_at(path, content).body.lines().count();
+    content.lines().count().saturating_sub(body_lines)
+}
+
+/// A line trimmed and cut to `SNIPPET_CHARS` characters.
+pub fn excerpt(line: &str) -> String {
+    let text = line.trim();
+    match text.char_indices().nth(SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+fn file_stamp(path: &Path) -> Option<(i64, u32, u64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((mtime.as_secs() as i64, mtime.subsec_nanos(), meta.len()))
+}
+
+/// Tokens of a note: its file name, then its body.
+fn note_tokens(path: &Path) -> Option<Vec<String>> {
+    let content = std::fs::read_to_string(path).ok()?;
+    let stem = path.file_stem().map(|s| s.to_string_lossy()).unwrap_or_default();
+    let mut tokens = tokenize(&stem);
+    tokens.extend(tokenize(&parse_note_at(path, &content).body));
+    Some(tokens)
+}
+
+impl SearchIndex {
+    /// Load the search index for a canonical vault path.
+    pub fn load(vault: &Path) -> Self {
+        let path = cache::cache_path(vault).map(|p| p.with_extension("idx"));
+        let mut index = Self::empty(vault, path.clone());
+        if let Some(data) = path.and_then(|p| std::fs::read(p).ok()) {
+            if index.decode(&data).is_none() {
+                // Foreign, outdated or corrupt: start over
+                index = Self::empty(vault, index.path.take());
+                index.dirty = true;
+            }
+        }
+        index
+    }
+
+    /// An index that is never written, for tests.
+    #[cfg(test)]
+    pub fn in_memory(vault: &Path) -> Self {
+        Self::empty(vault, None)
+    }
+
+    fn empty(vault: &Path, path: Option<PathBuf>) -> Self {
+        SearchIndex {
+            vault: vault.to_path_buf(),
+            path,
+            docs: HashMap::new(),
+            ids: HashMap::new(),
+            next_id: 0,
+            postings: BTreeMap::new(),
+            dirty: false,
+        }
+    }
+
+    /// Bring the index in line with `notes`, the canonical paths of every
+    /// note in the vault: notes whose mtime or size changed are re-read in
+    /// parallel and notes not listed are dropped.
+    pub fn update(&mut self, notes: &[PathBuf]) {
+        let listed: HashSet<&PathBuf> = notes.iter().collect();
+        let gone: Vec<PathBuf> = self.ids.keys().filter(|p| !listed.contains(p)).cloned().collect();
+        for path in gone {
+            self.remove(&path);
+        }
+
+        let changed: Vec<(&PathBuf, (i64, u32, u64))> = notes
+            .iter()
+            .filter_map(|path| {
+                let stamp = file_stamp(path)?;
+                let current = self.ids.get(path).map(|id| &self.docs[id]);
+                match current {
+                    Some(d) if (d.mtime_secs, d.mtime_nanos, d.size) == stamp => None,
+                    _ => Some((path, stamp)),
+                }
+            })
+            .collect();
+        let read: Vec<_> = changed
+            .into_par_iter()
+            .filter_map(|(path, stamp)| Some((path, stamp, note_tokens(path)?)))
+            .collect();
+        for (path, stamp, tokens) in read {
+            self.remove(path);
+            self.insert(path.clone(), stamp, tokens);
+        }
+    }
+
+    fn insert(&mut self, path: PathBuf, (mtime_secs, mtime_nanos, size): (i64, u32, u64), tokens: Vec<String>) {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut positions: HashMap<String, Vec<u32>> = HashMap::new();
+        for (i, token) in tokens.iter().enumerate() {
+            positions.entry(token.clone()).or_default().push(i as u32);
+        }
+        let terms: Vec<String> = positions.keys().cloned().collect();
+        for (term, list) in positions {
+            self.postings.entry(term).or_default().push((id, list));
+        }
+        self.ids.insert(path.clone(), id);
+        self.docs.insert(id, Doc { path, mtime_secs, mtime_nanos, size, length: tokens.len() as u32, terms });
+        self.dirty = true;
+    }
+
+    fn remove(&mut self, path: &Path) {
+        let Some(id) = self.ids.remove(path) else {
+            return;
+        };
+        let doc = self.docs.remove(&id).unwrap();
+        for term in doc.terms {
+            if let Some(list) = self.postings.get_mut(&term) {
+                list.retain(|(d, _)| *d != id);
+                if list.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Notes matching every term of `query`, best BM25 score first.
+    pub fn search(&self, query: &SearchQuery) -> Vec<(PathBuf, f64)> {
+        let n = self.docs.len() as f64;
+        if self.docs.is_empty() {
+            return Vec::new();
+        }
+        let avg_length = self.docs.values().map(|d| d.length as f64).sum::<f64>() / n;
+
+        let mut scores: Option<HashMap<u32, f64>> = None;
+        for term in &query.terms {
+            let counts = self.term_counts(term);
+            let df = counts.len() as f64;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+            let mut next = HashMap::new();
+            for (id, tf) in counts {
+                let Some(previous) = scores.as_ref().map_or(Some(0.0), |s| s.get(&id).copied()) else {
+                    continue;
+                };
+                let tf = tf as f64;
+                let norm = K1 * (1.0 - B + B * self.docs[&id].length as f64 / avg_length);
+                next.insert(id, previous + idf * tf * (K1 + 1.0) / (tf + norm));
+            }
+            scores = Some(next);
+        }
+
+        let mut results: Vec<(PathBuf, f64)> = scores
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, score)| (self.docs[&id].path.clone(), score))
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    /// Other notes sharing words with `path`, by cosine similarity of
+    /// TF-IDF vectors (sublinear term frequency, smoothed IDF), most similar
+    /// first. Empty when `path` is not indexed.
+    pub fn similar(&self, path: &Path) -> Vec<(PathBuf, f64)> {
+        let Some(&target) = self.ids.get(path) else {
+            return Vec::new();
+        };
+        let n = self.docs.len() as f64;
+        let mut dots: HashMap<u32, f64> = HashMap::new();
+        let mut norms: HashMap<u32, f64> = HashMap::new();
+        for list in self.postings.values() {
+            let idf = ((1.0 + n) / (1.0 + list.len() as f64)).ln() + 1.0;
+            let weight = |positions: &Vec<u32>| (1.0 + (positions.len() as f64).ln()) * idf;
+            let target_weight = list.iter().find(|(id, _)| *id == target).map(|(_, p)| weight(p));
+            for (id, positions) in list {
+                let w = weight(positions);
+                *norms.entry(*id).or_default() += w * w;
+                if let Some(t) = target_weight.filter(|_| *id != target) {
+                    *dots.entry(*id).or_default() += t * w;
+                }
+            }
+        }
+
+        let target_norm = norms.get(&target).copied().unwrap_or_default().sqrt();
+        let mut results: Vec<(PathBuf, f64)> = dots
+            .into_iter()
+            .map(|(id, dot)| (self.docs[&id].path.clone(), dot / (target_norm * norms[&id].sqrt())))
+            .collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    /// Occurrences of `term` per note.
+    fn term_counts(&self, term: &Term) -> HashMap<u32, usize> {
+        let last = term.words.len() - 1;
+        let lists: Vec<HashMap<u32, HashSet<u32>>> = term
+            .words
+            .iter()
+            .enumerate()
+            .map(|(i, word)| self.positions(word, term.prefix && i == last))
+            .collect();
+        lists[0]
+            .iter()
+            .filter_map(|(id, starts)| {
+                let count = starts
+                    .iter()
+                    .filter(|&&p| {
+                        lists[1..].iter().enumerate().all(|(i, list)| {
+                            list.get(id).is_some_and(|positions| positions.contains(&(p + i as u32 + 1)))
+                        })
+                    })
+                    .count();
+                (count > 0).then_some((*id, count))
+            })
+            .collect()
+    }
+
+    /// Positions of `word`, or of every term starting with it, per note.
+    fn positions(&self, word: &str, prefix: bool) -> HashMap<u32, HashSet<u32>> {
+        let mut out: HashMap<u32, HashSet<u32>> = HashMap::new();
+        let entries = self
+            .postings
+            .range(word.to_string()..)
+            .take_while(|(term, _)| if prefix { term.starts_with(word) } else { *term == word });
+        for (_, list) in entries {
+            for (id, positions) in list {
+                out.entry(*id).or_default().extend(positions);
+            }
+        }
+        out
+    }
+
+    /// Write the index if it changed since it was loaded.
+    pub fn save(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        let _lock = cache::lock(path);
+        if note::write_atomic(path, &self.encode()).is_ok() {
+            self.dirty = false;
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(MAGIC);
+        cache::put_u32(&mut buf, INDEX_VERSION);
+        cache::put_str(&mut buf, &self.vault.to_string_lossy());
+
+        // Renumber notes densely in path order
+        let mut ids: Vec<u32> = self.docs.keys().copied().collect();
+        ids.sort_by(|a, b| self.docs[a].path.cmp(&self.docs[b].path));
+        let numbers: HashMap<u32, u32> = ids.iter().enumerate().map(|(i, id)| (*id, i as u32)).collect();
+        cache::put_u32(&mut buf, ids.len() as u32);
+        for id in &ids {
+            let d = &self.docs[id];
+            cache::put_str(&mut buf, &d.path.to_string_lossy());
+            buf.extend_from_slice(&d.mtime_secs.to_le_bytes());
+            cache::put_u32(&mut buf, d.mtime_nanos);
+            buf.extend_from_slice(&d.size.to_le_bytes());
+            cache::put_u32(&mut buf, d.length);
+        }
+
+        cache::put_u32(&mut buf, self.postings.len() as u32);
+        for (term, list) in &self.postings {
+            cache::put_str(&mut buf, term);
+            cache::put_u32(&mut buf, list.len() as u32);
+            for (id, positions) in list {
+                cache::put_u32(&mut buf, numbers[id]);
+                cache::put_u32(&mut buf, positions.len() as u32);
+                for p in positions {
+                    cache::put_u32(&mut buf, *p);
+                }
+            }
+        }
+        buf
+    }
+
+    fn decode(&mut self, data: &[u8]) -> Option<()> {
+        let mut r = Reader::new(data);
+        if r.take(4)? != MAGIC || r.u32()? != INDEX_VERSION || r.str()? != self.vault.to_string_lossy() {
+            return None;
+        }
+        let docs = r.u32()?;
+        for id in 0..docs {
+            let path = PathBuf::from(r.str()?);
+            let doc = Doc {
+                path: path.clone(),
+                mtime_secs: r.i64()?,
+                mtime_nanos: r.u32()?,
+                size: r.u64()?,
+                length: r.u32()?,
+                terms: Vec::new(),
+            };
+            self.ids.insert(path, id);
+            self.docs.insert(id, doc);
+        }
+        self.next_id = docs;
+
+        let terms = r.u32()?;
+        for _ in 0..terms {
+            let term = r.str()?;
+            let count = r.u32()?;
+            let mut list = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let id = r.u32()?;
+                let n = r.u32()?;
+                let positions = (0..n).map(|_| r.u32()).collect::<Option<Vec<u32>>>()?;
+                self.docs.get_mut(&id)?.terms.push(term.clone());
+                list.push((id, positions));
+            }
+            self.postings.insert(term, list);
+        }
+        Some(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn index(dir: &TempDir, notes: &[(&str, &str)]) -> (SearchIndex, Vec<PathBuf>) {
+        let vault = fs::canonicalize(dir.path()).unwrap();
+        let paths: Vec<PathBuf> = notes
+            .iter()
+            .map(|(name, content)| {
+                fs::write(vault.join(name), content).unwrap();
+                vault.join(name)
+            })
+            .collect();
+        let mut index = SearchIndex::in_memory(&vault);
+        index.update(&paths);
+        (index, paths)
+    }
+
+    fn names(results: &[(PathBuf, f64)]) -> Vec<String> {
+        results.iter().map(|(p, _)| p.file_name().unwrap().to_string_lossy().to_string()).collect()
+    }
+
+    fn search(index: &SearchIndex, q: &str) -> Vec<String> {
+        names(&index.search(&SearchQuery::parse(q).unwrap()))
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Hello, wörld! e-mail_2"), vec!["hello", "wörld", "e", "mail", "2"]);
+        assert_eq!(tokenize("Rust の検索"), vec!["rust", "の", "検", "索"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let q = SearchQuery::parse(r#"rust "Borrow  checker" bor* e-mail"#).unwrap();
+        let terms: Vec<(Vec<String>, bool)> = q.terms.into_iter().map(|t| (t.words, t.prefix)).collect();
+        assert_eq!(
+            terms,
+            vec![
+                (vec!["rust".to_string()], false),
+                (vec!["borrow".to_string(), "checker".to_string()], false),
+                (vec!["bor".to_string()], true),
+                (vec!["e".to_string(), "mail".to_string()], false),
+            ]
+        );
+        assert!(SearchQuery::parse("\"open").is_err());
+        assert!(SearchQuery::parse(" -- ").is_err());
+    }
+
+    #[test]
+    fn test_search_ranks_and_matches_phrases_and_prefixes() {
+        let dir = TempDir::new().unwrap();
+        let (index, _) = index(
+            &dir,
+            &[
+                ("a.md", "---\nmaturity: seedling\n---\nThe borrow checker rejects this. Borrow borrow.\n"),
+                ("b.md", "A checker of borrow rules, and a long note about many other things entirely.\n"),
+                ("c.md", "Borrowing is fine. 日本語の検索\n"),
+            ],
+        );
+        assert_eq!(search(&index, "borrow"), vec!["a.md", "b.md"]);
+        assert_eq!(search(&index, "\"borrow checker\""), vec!["a.md"]);
+        // The short note outranks the long one with the same count
+        assert_eq!(search(&index, "borrow*"), vec!["a.md", "c.md", "b.md"]);
+        assert_eq!(search(&index, "borrow rules"), vec!["b.md"]);
+        assert_eq!(search(&index, "検索"), vec!["c.md"]);
+        assert_eq!(search(&index, "索検"), Vec::<String>::new());
+        // Frontmatter is not indexed; file names are
+        assert!(search(&index, "seedling").is_empty());
+        assert_eq!(search(&index, "c"), vec!["c.md"]);
+    }
+
+    #[test]
+    fn test_update_and_roundtrip() {
+        let dir = TempDir::new().unwrap();
+        let (mut index, mut paths) = index(&dir, &[("a.md", "alpha beta\n"), ("b.md", "beta\n")]);
+        fs::write(&paths[0], "gamma alpha\n").unwrap();
+        // Same second, different size
+        paths.pop();
+        index.update(&paths);
+        assert_eq!(search(&index, "gamma"), vec!["a.md"]);
+        assert!(search(&index, "beta").is_empty());
+
+        let mut decoded = SearchIndex::in_memory(&index.vault);
+        decoded.decode(&index.encode()).unwrap();
+        assert_eq!(search(&decoded, "\"gamma alpha\""), vec!["a.md"]);
+        decoded.update(&paths);
+        assert!(!decoded.dirty);
+    }
+
+    #[test]
+    fn test_similar() {
+        let dir = TempDir::new().unwrap();
+        let (index, paths) = index(
+            &dir,
+            &[
+                ("a.md", "tokio async runtime tasks\n"),
+                ("b.md", "async runtime tasks executor\n"),
+                ("c.md", "tokio tutorial\n"),
+                ("d.md", "garden soil\n"),
+            ],
+        );
+        let similar = index.similar(&paths[0]);
+        assert_eq!(names(&similar), vec!["b.md", "c.md"]);
+        assert!(similar.iter().all(|(_, s)| *s > 0.0 && *s < 1.0));
+        assert!(index.similar(Path::new("/nowhere.md")).is_empty());
+    }
+
+    #[test]
+    fn test_snippets() {
+        let query = SearchQuery::parse("borrow check*").unwrap();
+        let content = "---\ntitle: borrow\n---\nintro\nborrow here\nthe borrow checker\nchecks\n";
+        let found = snippets(Path::new("a.md"), content, &query, 2);
+        assert_eq!(found, vec![(5, "borrow here".to_string()), (6, "the borrow checker".to_string())]);
+    }
+}
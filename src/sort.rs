@@ -0,0 +1,207 @@
+use std::cmp::Ordering;
+
+use chrono::{Datelike, NaiveDate};
+
+use crate::cli::{SortArgs, SortKey};
+use crate::note::NoteMetaInfo;
+
+/// Sort `notes` by `args.sort` (or `default_key`), then apply `--offset` / `--limit`.
+/// Notes without a value for the sort key always go last; ties are broken by
+/// `relative_path` so output is deterministic.
+pub fn sort_and_paginate(
+    mut notes: Vec<NoteMetaInfo>,
+    args: &SortArgs,
+    default_key: SortKey,
+    today: NaiveDate,
+) -> Vec<NoteMetaInfo> {
+    let key = args.sort.clone().unwrap_or(default_key);
+
+    match key {
+        SortKey::Random => {
+            let seed = args.seed.unwrap_or_else(time_seed);
+            notes.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+            shuffle(&mut notes, seed);
+            if args.reverse {
+                notes.reverse();
+            }
+        }
+        SortKey::Links => {
+            sort_by_value(&mut notes, args.reverse, |n| Some(n.content.links.len() as f64));
+        }
+        SortKey::Path => {
+            notes.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+            if args.reverse {
+                notes.reverse();
+            }
+        }
+        SortKey::NextReview => sort_by_value(&mut notes, args.reverse, |n| {
+            n.sprout.next_review.map(|d| d.num_days_from_ce() as f64)
+        }),
+        SortKey::Created => sort_by_value(&mut notes, args.reverse, |n| {
+            n.sprout.created.map(|d| d.num_days_from_ce() as f64)
+        }),
+        SortKey::Ease => sort_by_value(&mut notes, args.reverse, |n| n.sprout.ease),
+        SortKey::Interval => sort_by_value(&mut notes, args.reverse, |n| {
+            n.sprout.review_interval.map(f64::from)
+        }),
+        SortKey::OverdueRatio => sort_by_value(&mut notes, args.reverse, |n| {
+            overdue_ratio(n, today)
+        }),
+    }
+
+    notes
+        .into_iter()
+        .skip(args.offset)
+        .take(args.limit.unwrap_or(usize::MAX))
+        .collect()
+}
+
+/// Days late divided by the current interval. Negative when not yet due.
+pub fn overdue_ratio(n: &NoteMetaInfo, today: NaiveDate) -> Option<f64> {
+    let next_review = n.sprout.next_review?;
+    let interval = n.sprout.review_interval?.max(1);
+    Some((today - next_review).num_days() as f64 / interval as f64)
+}
+
+fn sort_by_value<F>(notes: &mut [NoteMetaInfo], reverse: bool, value: F)
+where
+    F: Fn(&NoteMetaInfo) -> Option<f64>,
+{
+    notes.sort_by(|a, b| {
+        let ord = match (value(a), value(b)) {
+            (Some(x), Some(y)) => {
+                let o = x.partial_cmp(&y).unwrap_or(Ordering::Equal);
+                if reverse {
+                    o.reverse()
+                } else {
+                    o
+                }
+            }
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        };
+        ord.then_with(|| a.relative_path.cmp(&b.relative_path))
+    });
+}
+
+fn time_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// Fisher–Yates shuffle driven by SplitMix64, so a given seed always
+/// produces the same order.
+fn shuffle<T>(items: &mut [T], seed: u64) {
+    let mut state = seed;
+    let mut next = || {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    };
+    for i in (1..items.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        items.swap(i, j);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::SproutFrontmatter;
+    use std::path::PathBuf;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn note(name: &str, next_review: Option<NaiveDate>, interval: Option<u32>, ease: Option<f64>) -> NoteMetaInfo {
+        NoteMetaInfo {
+            path: PathBuf::from(format!("/vault/{name}")),
+            relative_path: name.to_string(),
+            sprout: SproutFrontmatter {
+                maturity: Some("seedling".into()),
+                next_review,
+                review_interval: interval,
+                ease,
+                ..Default::default()
+            },
+            content: Default::default(),
+        }
+    }
+
+    fn args(sort: SortKey) -> SortArgs {
+        SortArgs {
+            sort: Some(sort),
+            reverse: false,
+            limit: None,
+            offset: 0,
+            seed: None,
+        }
+    }
+
+    fn names(notes: &[NoteMetaInfo]) -> Vec<&str> {
+        notes.iter().map(|n| n.relative_path.as_str()).collect()
+    }
+
+    fn sample() -> Vec<NoteMetaInfo> {
+        vec![
+            note("a.md", Some(date(2026, 3, 20)), Some(20), Some(2.5)),
+            note("b.md", Some(date(2026, 3, 28)), Some(2), Some(2.1)),
+            note("c.md", None, None, None),
+            note("d.md", Some(date(2026, 4, 5)), Some(10), Some(2.9)),
+        ]
+    }
+
+    #[test]
+    fn test_sort_overdue_ratio() {
+        // a: 11/20 = 0.55, b: 3/2 = 1.5, d: -5/10 = -0.5, c: none → last
+        let today = date(2026, 3, 31);
+        let sorted = sort_and_paginate(sample(), &args(SortKey::OverdueRatio), SortKey::Path, today);
+        assert_eq!(names(&sorted), vec!["d.md", "a.md", "b.md", "c.md"]);
+
+        let mut rev = args(SortKey::OverdueRatio);
+        rev.reverse = true;
+        let sorted = sort_and_paginate(sample(), &rev, SortKey::Path, today);
+        assert_eq!(names(&sorted), vec!["b.md", "a.md", "d.md", "c.md"]);
+    }
+
+    #[test]
+    fn test_sort_ease_missing_last() {
+        let sorted = sort_and_paginate(sample(), &args(SortKey::Ease), SortKey::Path, date(2026, 3, 31));
+        assert_eq!(names(&sorted), vec!["b.md", "a.md", "d.md", "c.md"]);
+    }
+
+    #[test]
+    fn test_default_key_and_pagination() {
+        let mut a = args(SortKey::Path);
+        a.sort = None;
+        a.offset = 1;
+        a.limit = Some(2);
+        let sorted = sort_and_paginate(sample(), &a, SortKey::NextReview, date(2026, 3, 31));
+        assert_eq!(names(&sorted), vec!["b.md", "d.md"]);
+    }
+
+    #[test]
+    fn test_random_is_seeded() {
+        let mut a = args(SortKey::Random);
+        a.seed = Some(42);
+        let first = sort_and_paginate(sample(), &a, SortKey::Path, date(2026, 3, 31));
+        let second = sort_and_paginate(sample(), &a, SortKey::Path, date(2026, 3, 31));
+        assert_eq!(names(&first), names(&second));
+        assert_eq!(first.len(), 4);
+    }
+
+    #[test]
+    fn test_shuffle_permutes() {
+        let mut items: Vec<u32> = (0..50).collect();
+        shuffle(&mut items, 7);
+        assert_ne!(items, (0..50).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..50).collect::<Vec<_>>());
+    }
+}
@@ -14,14 +14,39 @@ pub struct SrsInput {
     pub max_interval: u32,
 }
 
+/// Tunable constants of the scheduler. `sprout optimize` fits these to the
+/// review history; `Default` matches the values documented in algorithm.md.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SrsParams {
+    pub hard_ease_delta: f64,
+    pub easy_ease_delta: f64,
+    pub good_multiplier: f64,
+}
+
+impl Default for SrsParams {
+    fn default() -> Self {
+        Self {
+            hard_ease_delta: 0.15,
+            easy_ease_delta: 0.15,
+            good_multiplier: 0.8,
+        }
+    }
+}
+
 pub struct SrsOutput {
     pub new_interval: u32,
     pub new_ease: f64,
     pub next_review: NaiveDate,
 }
 
-/// Core SRS calculation. All math in f64, single round at the end.
+/// Core SRS calculation with default parameters.
+#[cfg(test)]
 pub fn calculate(input: &SrsInput) -> SrsOutput {
+    calculate_with(input, &SrsParams::default())
+}
+
+/// Core SRS calculation. All math in f64, single round at the end.
+pub fn calculate_with(input: &SrsInput, params: &SrsParams) -> SrsOutput {
     let delayed = (input.today - input.next_review).num_days().max(0) as f64;
     let interval = input.interval as f64;
     let ease = input.ease;
@@ -33,16 +58,16 @@ pub fn calculate(input: &SrsInput) -> SrsOutput {
         Rating::Hard => {
             let ni = (interval + delayed / 4.0) * 0.5;
             let ni = ni.max(1.0);
-            let ne = (ease - 0.15_f64).max(1.3);
+            let ne = (ease - params.hard_ease_delta).max(1.3);
             (ni, ne)
         }
         Rating::Good => {
-            let ni = (interval + delayed / 2.0) * effective_ease * 0.8;
+            let ni = (interval + delayed / 2.0) * effective_ease * params.good_multiplier;
             (ni, ease)
         }
         Rating::Easy => {
             let ni = (interval + delayed) * effective_ease;
-            let ne = ease + 0.15;
+            let ne = ease + params.easy_ease_delta;
             (ni, ne)
         }
     };
@@ -216,6 +241,29 @@ mod tests {
         assert!(with_links.new_interval > without.new_interval);
     }
 
+    #[test]
+    fn test_custom_params() {
+        let params = SrsParams {
+            hard_ease_delta: 0.2,
+            easy_ease_delta: 0.1,
+            good_multiplier: 1.0,
+        };
+        let input = |rating| SrsInput {
+            interval: 10,
+            ease: 2.5,
+            next_review: date(2026, 2, 26),
+            today: date(2026, 2, 26),
+            rating,
+            link_count: 0,
+            link_weight: 0.1,
+            max_interval: 90,
+        };
+        // 10 * 2.5 * 1.0 = 25
+        assert_eq!(calculate_with(&input(Rating::Good), &params).new_interval, 25);
+        assert!((calculate_with(&input(Rating::Hard), &params).new_ease - 2.3).abs() < 0.001);
+        assert!((calculate_with(&input(Rating::Easy), &params).new_ease - 2.6).abs() < 0.001);
+    }
+
     #[test]
     fn test_load_balance_no_fuzz_short_interval() {
         let result = load_balance(3, date(2026, 2, 26), &[]);
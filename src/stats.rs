@@ -0,0 +1,354 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+
+use chrono::NaiveDate;
+
+use crate::cli::Rating;
+use crate::history::HistoryEvent;
+use crate::note::NoteMetaInfo;
+
+const MATURITIES: &[&str] = &["seedling", "budding", "evergreen"];
+
+pub struct MaturityStats {
+    pub maturity: &'static str,
+    pub count: usize,
+    pub avg_ease: Option<f64>,
+    pub avg_interval: Option<f64>,
+}
+
+pub struct GrowthPoint {
+    /// Month in `YYYY-MM` form
+    pub month: String,
+    pub added: usize,
+    pub total: usize,
+}
+
+pub struct Transition {
+    pub from: String,
+    pub to: String,
+    pub count: usize,
+}
+
+pub struct DetailedStats {
+    pub hard: usize,
+    pub good: usize,
+    pub easy: usize,
+    /// Share of reviews not rated hard; `None` without review history
+    pub true_retention: Option<f64>,
+    pub by_maturity: Vec<MaturityStats>,
+    pub growth: Vec<GrowthPoint>,
+    pub reviews_30d: usize,
+    pub reviews_90d: usize,
+    pub transitions: Vec<Transition>,
+}
+
+pub struct ReviewCalendar {
+    /// Review count for every day of the past year, oldest first
+    pub days: Vec<(NaiveDate, usize)>,
+    pub current_streak: usize,
+    pub longest_streak: usize,
+}
+
+pub fn detailed_stats(
+    tracked: &[&NoteMetaInfo],
+    events: &[HistoryEvent],
+    today: NaiveDate,
+) -> DetailedStats {
+    let reviews: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            HistoryEvent::Review(r) => Some(r),
+            _ => None,
+        })
+        .collect();
+
+    let count_rating = |rating: Rating| reviews.iter().filter(|r| r.rating == rating).count();
+    let (hard, good, easy) = (
+        count_rating(Rating::Hard),
+        count_rating(Rating::Good),
+        count_rating(Rating::Easy),
+    );
+    let true_retention = if reviews.is_empty() {
+        None
+    } else {
+        Some((good + easy) as f64 / reviews.len() as f64)
+    };
+
+    let by_maturity = MATURITIES
+        .iter()
+        .map(|&maturity| {
+            let notes: Vec<_> = tracked
+                .iter()
+                .filter(|n| n.sprout.maturity.as_deref() == Some(maturity))
+                .collect();
+            MaturityStats {
+                maturity,
+                count: notes.len(),
+                avg_ease: mean(notes.iter().filter_map(|n| n.sprout.ease)),
+                avg_interval: mean(
+                    notes
+                        .iter()
+                        .filter_map(|n| n.sprout.review_interval.map(f64::from)),
+                ),
+            }
+        })
+        .collect();
+
+    let mut added_per_month: BTreeMap<String, usize> = BTreeMap::new();
+    for created in tracked.iter().filter_map(|n| n.sprout.created) {
+        *added_per_month
+            .entry(created.format("%Y-%m").to_string())
+            .or_default() += 1;
+    }
+    let mut running = 0;
+    let growth = added_per_month
+        .into_iter()
+        .map(|(month, added)| {
+            running += added;
+            GrowthPoint {
+                month,
+                added,
+                total: running,
+            }
+        })
+        .collect();
+
+    let reviews_since = |days: i64| {
+        let start = today - chrono::Duration::days(days - 1);
+        reviews
+            .iter()
+            .filter(|r| r.date >= start && r.date <= today)
+            .count()
+    };
+
+    let mut transition_counts: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for event in events {
+        if let HistoryEvent::Promote(p) = event {
+            *transition_counts
+                .entry((p.from.clone(), p.to.clone()))
+                .or_default() += 1;
+        }
+    }
+    let transitions = transition_counts
+        .into_iter()
+        .map(|((from, to), count)| Transition { from, to, count })
+        .collect();
+
+    DetailedStats {
+        hard,
+        good,
+        easy,
+        true_retention,
+        by_maturity,
+        growth,
+        reviews_30d: reviews_since(30),
+        reviews_90d: reviews_since(90),
+        transitions,
+    }
+}
+
+/// Count reviews per day from the history log, plus `last_review` dates the
+/// log does not cover (reviews made before logging existed). A `last_review`
+/// equal to `created` is the value `init` writes, not a review, and is ignored.
+pub fn review_calendar(
+    tracked: &[&NoteMetaInfo],
+    events: &[HistoryEvent],
+    today: NaiveDate,
+) -> ReviewCalendar {
+    let mut per_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    let mut logged: HashSet<(&Path, NaiveDate)> = HashSet::new();
+    for event in events {
+        if let HistoryEvent::Review(r) = event {
+            *per_day.entry(r.date).or_default() += 1;
+            logged.insert((&r.path, r.date));
+        }
+    }
+    for n in tracked {
+        if let Some(d) = n.sprout.last_review {
+            if n.sprout.created != Some(d) && !logged.contains(&(n.path.as_path(), d)) {
+                *per_day.entry(d).or_default() += 1;
+            }
+        }
+    }
+
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for &day in per_day.keys().filter(|&&d| d <= today) {
+        run = match prev {
+            Some(p) if day - p == chrono::Duration::days(1) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        prev = Some(day);
+    }
+
+    // A streak stays alive until the end of today even if today has no review yet
+    let mut current = 0;
+    let mut day = if per_day.contains_key(&today) {
+        today
+    } else {
+        today - chrono::Duration::days(1)
+    };
+    while per_day.contains_key(&day) {
+        current += 1;
+        day -= chrono::Duration::days(1);
+    }
+
+    let start = today - chrono::Duration::days(364);
+    let days = (0..365)
+        .map(|i| {
+            let d = start + chrono::Duration::days(i);
+            (d, per_day.get(&d).copied().unwrap_or(0))
+        })
+        .collect();
+
+    ReviewCalendar {
+        days,
+        current_streak: current,
+        longest_streak: longest,
+    }
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(s, c), v| (s + v, c + 1));
+    if count == 0 {
+        None
+    } else {
+        Some(sum / count as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontmatter::SproutFrontmatter;
+    use crate::history::{PromoteRecord, ReviewRecord};
+    use std::path::PathBuf;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn tracked_note(maturity: &str, created: NaiveDate, ease: f64, interval: u32) -> NoteMetaInfo {
+        NoteMetaInfo {
+            path: PathBuf::from(format!("/vault/{maturity}-{created}.md")),
+            relative_path: format!("{maturity}-{created}.md"),
+            sprout: SproutFrontmatter {
+                maturity: Some(maturity.into()),
+                created: Some(created),
+                ease: Some(ease),
+                review_interval: Some(interval),
+                ..Default::default()
+            },
+            content: Default::default(),
+        }
+    }
+
+    fn review(date: NaiveDate, rating: Rating) -> HistoryEvent {
+        HistoryEvent::Review(ReviewRecord {
+            date,
+            path: PathBuf::from("/vault/a.md"),
+            rating,
+            maturity: "seedling".into(),
+            interval: 1,
+            ease: 2.5,
+            delayed: 0,
+            link_count: 0,
+            new_interval: 2,
+            new_ease: 2.5,
+        })
+    }
+
+    #[test]
+    fn test_detailed_stats() {
+        let today = date(2026, 3, 31);
+        let notes = [
+            tracked_note("seedling", date(2026, 1, 5), 2.5, 2),
+            tracked_note("seedling", date(2026, 1, 20), 2.3, 4),
+            tracked_note("budding", date(2026, 3, 1), 2.7, 10),
+        ];
+        let refs: Vec<&NoteMetaInfo> = notes.iter().collect();
+        let events = vec![
+            review(date(2025, 12, 1), Rating::Hard),
+            review(date(2026, 3, 1), Rating::Good),
+            review(date(2026, 3, 30), Rating::Easy),
+            review(date(2026, 3, 31), Rating::Good),
+            HistoryEvent::Promote(PromoteRecord {
+                date: date(2026, 3, 31),
+                path: PathBuf::from("/vault/a.md"),
+                from: "seedling".into(),
+                to: "budding".into(),
+            }),
+        ];
+
+        let stats = detailed_stats(&refs, &events, today);
+        assert_eq!((stats.hard, stats.good, stats.easy), (1, 2, 1));
+        assert!((stats.true_retention.unwrap() - 0.75).abs() < 1e-9);
+        assert_eq!(stats.reviews_30d, 2);
+        assert_eq!(stats.reviews_90d, 3);
+
+        let seedling = &stats.by_maturity[0];
+        assert_eq!(seedling.count, 2);
+        assert!((seedling.avg_ease.unwrap() - 2.4).abs() < 1e-9);
+        assert!((seedling.avg_interval.unwrap() - 3.0).abs() < 1e-9);
+        assert!(stats.by_maturity[2].avg_ease.is_none());
+
+        assert_eq!(stats.growth.len(), 2);
+        assert_eq!(stats.growth[0].month, "2026-01");
+        assert_eq!(stats.growth[1].total, 3);
+
+        assert_eq!(stats.transitions.len(), 1);
+        assert_eq!(stats.transitions[0].to, "budding");
+    }
+
+    #[test]
+    fn test_review_calendar_streaks() {
+        let today = date(2026, 3, 31);
+        let events = vec![
+            review(date(2026, 3, 1), Rating::Good),
+            review(date(2026, 3, 2), Rating::Good),
+            review(date(2026, 3, 3), Rating::Hard),
+            review(date(2026, 3, 3), Rating::Good),
+            review(date(2026, 3, 29), Rating::Easy),
+        ];
+        // Reviewed yesterday (from frontmatter only) → streak still alive today
+        let mut note = tracked_note("seedling", date(2026, 1, 1), 2.5, 1);
+        note.sprout.last_review = Some(date(2026, 3, 30));
+        let refs = vec![&note];
+
+        let cal = review_calendar(&refs, &events, today);
+        assert_eq!(cal.current_streak, 2);
+        assert_eq!(cal.longest_streak, 3);
+        assert_eq!(cal.days.len(), 365);
+        assert_eq!(cal.days.last().unwrap(), &(today, 0));
+        let march3 = cal.days.iter().find(|(d, _)| *d == date(2026, 3, 3)).unwrap();
+        assert_eq!(march3.1, 2);
+    }
+
+    #[test]
+    fn test_review_calendar_ignores_init_and_logged_last_review() {
+        let today = date(2026, 3, 31);
+        // last_review == created → written by init, not a review
+        let mut fresh = tracked_note("seedling", date(2026, 3, 31), 2.5, 1);
+        fresh.sprout.last_review = Some(date(2026, 3, 31));
+        // last_review already covered by the log → counted once
+        let mut logged = tracked_note("budding", date(2026, 1, 1), 2.5, 1);
+        logged.path = PathBuf::from("/vault/a.md");
+        logged.sprout.last_review = Some(date(2026, 3, 20));
+        let events = vec![review(date(2026, 3, 20), Rating::Good)];
+
+        let cal = review_calendar(&[&fresh, &logged], &events, today);
+        assert_eq!(cal.current_streak, 0);
+        assert_eq!(cal.longest_streak, 1);
+        assert_eq!(cal.days.iter().map(|&(_, c)| c).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_detailed_stats_without_history() {
+        let stats = detailed_stats(&[], &[], date(2026, 3, 31));
+        assert!(stats.true_retention.is_none());
+        assert_eq!(stats.reviews_90d, 0);
+        assert!(stats.growth.is_empty());
+    }
+}
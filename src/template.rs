@@ -1,5 +1,8 @@
 use std::path::Path;
 
+use chrono::format::{Item, StrftimeItems};
+use chrono::{Duration, NaiveDateTime};
+
 use crate::error::SproutError;
 
 /// Load a template file from the template directory.
@@ -18,70 +21,182 @@ pub fn load_template(template_dir: &Path, name: &str) -> Result<String, SproutEr
     }
 }
 
+/// Values of the template variables.
+pub struct TemplateVars<'a> {
+    pub title: &'a str,
+    /// Creation time, for `{{date}}`, `{{time}}`, `{{yesterday}}` and the like
+    pub now: NaiveDateTime,
+    /// The note's Zettelkasten ID, whether or not the file name uses it
+    pub id: &'a str,
+    pub slug: &'a str,
+    pub vault: &'a Path,
+    /// Standard input, read only when the template uses `{{stdin}}`
+    pub stdin: Option<&'a str>,
+    /// `--var key=value` pairs
+    pub user: &'a [(String, String)],
+}
+
+/// Variables that `--var` cannot redefine.
+const BUILTINS: &[&str] = &[
+    "title", "date", "time", "datetime", "yesterday", "tomorrow", "id", "slug", "vault", "uuid", "stdin",
+];
+
+enum Token<'t> {
+    Text(&'t str),
+    /// `{{name}}` or `{{name:format}}`, trimmed
+    Var(&'t str),
+    /// The whole `{{$(...)}}` and the command inside it
+    Exec(&'t str, &'t str),
+}
+
+/// Split a template into text, `{{variables}}` and `{{$(commands)}}`.
+/// `\{{` is a literal `{{`.
+fn tokenize(template: &str) -> Result<Vec<Token<'_>>, SproutError> {
+    let mut tokens = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            tokens.push(Token::Text(&rest[..start - 1]));
+            tokens.push(Token::Text("{{"));
+            rest = &rest[start + 2..];
+            continue;
+        }
+        tokens.push(Token::Text(&rest[..start]));
+        let after = &rest[start + 2..];
+        if let Some(cmd) = after.strip_prefix("$(") {
+            let end = cmd.find(")}}").ok_or_else(|| {
+                SproutError::ParseError("unclosed {{$(...)}} in template".into())
+            })?;
+            tokens.push(Token::Exec(&rest[start..start + 2 + 2 + end + 3], &cmd[..end]));
+            rest = &cmd[end + 3..];
+        } else {
+            let end = after.find("}}").ok_or_else(|| {
+                SproutError::ParseError("unclosed {{ in template (write \\{{ for a literal {{)".into())
+            })?;
+            tokens.push(Token::Var(after[..end].trim()));
+            rest = &after[end + 2..];
+        }
+    }
+    tokens.push(Token::Text(rest));
+    Ok(tokens)
+}
+
+/// Whether the template reads `{{stdin}}`.
+pub fn uses_stdin(template: &str) -> bool {
+    tokenize(template).is_ok_and(|tokens| tokens.iter().any(|t| matches!(t, Token::Var("stdin"))))
+}
+
 /// Expand template variables.
-/// Built-in: `{{title}}` → title, `{{date}}` → YYYY-MM-DD.
-/// Shell commands: `{{$(...)}}` → only when `allow_exec` is true.
+/// Built-in: `{{title}}`, `{{date}}`, `{{time}}`, `{{datetime}}`,
+/// `{{yesterday}}`, `{{tomorrow}}` (all five take a strftime format after
+/// `:`, e.g. `{{date:%Y/%m}}`), `{{id}}`, `{{slug}}`, `{{vault}}`,
+/// `{{uuid}}` and `{{stdin}}`, plus the `--var` names. An unknown name is
+/// an error.
+/// Shell commands: `{{$(...)}}` → only when `allow_exec` is true, otherwise
+/// left as is.
 pub fn expand(
     template: &str,
-    title: &str,
-    date: &str,
+    vars: &TemplateVars,
     allow_exec: bool,
 ) -> Result<String, SproutError> {
-    let mut result = template
-        .replace("{{title}}", title)
-        .replace("{{date}}", date);
+    let uuid = uuid::Uuid::new_v4().to_string();
+    let mut result = String::new();
+    for token in tokenize(template)? {
+        match token {
+            Token::Text(text) => result.push_str(text),
+            Token::Var(name) => result.push_str(&variable(name, vars, &uuid)?),
+            Token::Exec(raw, _) if !allow_exec => result.push_str(raw),
+            Token::Exec(_, cmd) => {
+                // Variables in the command line are expanded first, so
+                // `{{$(echo {{title}})}}` sees the title.
+                let cmd = expand(cmd, vars, false)?;
+                result.push_str(&run_command(&cmd, vars)?);
+            }
+        }
+    }
+    Ok(result)
+}
+
+/// Check `--var` pairs: names are identifiers and must not shadow a
+/// built-in variable.
+pub fn validate_user_vars(user: &[(String, String)]) -> Result<(), SproutError> {
+    for (key, _) in user {
+        let valid = key.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if !valid || BUILTINS.contains(&key.as_str()) {
+            return Err(SproutError::ParseError(format!("--var {key}: not a usable variable name")));
+        }
+    }
+    Ok(())
+}
 
-    if allow_exec {
-        result = expand_exec(&result, title)?;
+fn variable(token: &str, vars: &TemplateVars, uuid: &str) -> Result<String, SproutError> {
+    let (name, format) = match token.split_once(':') {
+        Some((name, format)) => (name.trim_end(), Some(format)),
+        None => (token, None),
+    };
+    let time = |at: NaiveDateTime, default: &str| format_time(at, format.unwrap_or(default));
+    let plain = |value: &str| match format {
+        Some(_) => Err(SproutError::ParseError(format!("{{{{{token}}}}}: {name} takes no format"))),
+        None => Ok(value.to_string()),
+    };
+    match name {
+        "date" => time(vars.now, "%Y-%m-%d"),
+        "time" => time(vars.now, "%H:%M"),
+        "datetime" => time(vars.now, "%Y-%m-%dT%H:%M:%S"),
+        "yesterday" => time(vars.now - Duration::days(1), "%Y-%m-%d"),
+        "tomorrow" => time(vars.now + Duration::days(1), "%Y-%m-%d"),
+        "title" => plain(vars.title),
+        "id" => plain(vars.id),
+        "slug" => plain(vars.slug),
+        "vault" => plain(&vars.vault.to_string_lossy()),
+        "uuid" => plain(uuid),
+        "stdin" => plain(vars.stdin.unwrap_or_default()),
+        _ => match vars.user.iter().rev().find(|(key, _)| key == name) {
+            Some((_, value)) => plain(value),
+            None => Err(SproutError::UnknownTemplateVariable(token.to_string())),
+        },
     }
+}
 
-    Ok(result)
+fn format_time(at: NaiveDateTime, format: &str) -> Result<String, SproutError> {
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        return Err(SproutError::ParseError(format!("invalid date format in template: {format}")));
+    }
+    Ok(at.format_with_items(StrftimeItems::new(format)).to_string())
 }
 
-/// Expand `{{$(...)}}` patterns by executing shell commands.
-fn expand_exec(template: &str, title: &str) -> Result<String, SproutError> {
+/// Run a `{{$(...)}}` command and return its output without trailing newlines.
+fn run_command(cmd: &str, vars: &TemplateVars) -> Result<String, SproutError> {
     use std::process::Command;
     use std::time::Duration;
 
-    let mut result = String::new();
-    let mut rest = template;
+    let child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .env("SPROUT_TITLE", vars.title)
+        .env("SPROUT_ID", vars.id)
+        .env("SPROUT_SLUG", vars.slug)
+        .env("SPROUT_VAULT", vars.vault)
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(|e| SproutError::ParseError(format!("failed to execute template command: {e}")))?;
 
-    while let Some(start) = rest.find("{{$(") {
-        result.push_str(&rest[..start]);
-        let after_start = &rest[start + 4..]; // skip "{{$("
-        let end = after_start.find(")}}").ok_or_else(|| {
-            SproutError::ParseError("unclosed {{$(...)}} in template".into())
-        })?;
-        let cmd = &after_start[..end];
-
-        let child = Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .env("SPROUT_TITLE", title)
-            .stdout(std::process::Stdio::piped())
-            .stderr(std::process::Stdio::piped())
-            .spawn()
-            .map_err(|e| SproutError::ParseError(format!("failed to execute template command: {e}")))?;
-
-        let output = wait_with_timeout(child, Duration::from_secs(5))?;
-
-        if !output.status.success() {
-            return Err(SproutError::ParseError(format!(
-                "template command failed: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
-        }
-
-        let stdout = String::from_utf8(output.stdout).map_err(|_| {
-            SproutError::ParseError("template command produced non-UTF-8 output".into())
-        })?;
+    let output = wait_with_timeout(child, Duration::from_secs(5))?;
 
-        result.push_str(stdout.trim_end_matches('\n'));
-        rest = &after_start[end + 3..]; // skip ")}}"
+    if !output.status.success() {
+        return Err(SproutError::ParseError(format!(
+            "template command failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )));
     }
 
-    result.push_str(rest);
-    Ok(result)
+    let stdout = String::from_utf8(output.stdout).map_err(|_| {
+        SproutError::ParseError("template command produced non-UTF-8 output".into())
+    })?;
+
+    Ok(stdout.trim_end_matches('\n').to_string())
 }
 
 fn wait_with_timeout(
@@ -113,6 +228,18 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    fn vars(title: &str) -> TemplateVars<'_> {
+        TemplateVars {
+            title,
+            now: NaiveDateTime::parse_from_str("2026-02-27 15:30:45", "%Y-%m-%d %H:%M:%S").unwrap(),
+            id: "202602271530",
+            slug: "my-note",
+            vault: Path::new("/notes"),
+            stdin: None,
+            user: &[],
+        }
+    }
+
     #[test]
     fn test_load_template_fallback() {
         let dir = TempDir::new().unwrap();
@@ -132,28 +259,88 @@ mod tests {
     #[test]
     fn test_expand_builtin_variables() {
         let template = "# {{title}}\n\nCreated: {{date}}\n";
-        let result = expand(template, "My Note", "2026-02-27", false).unwrap();
+        let result = expand(template, &vars("My Note"), false).unwrap();
         assert_eq!(result, "# My Note\n\nCreated: 2026-02-27\n");
     }
 
+    #[test]
+    fn test_expand_id_and_slug() {
+        let template = "---\nid: {{id}}\n---\n# {{title}}\n[[{{slug}}]]\n";
+        let result = expand(template, &vars("My Note"), false).unwrap();
+        assert_eq!(result, "---\nid: 202602271530\n---\n# My Note\n[[my-note]]\n");
+    }
+
+    #[test]
+    fn test_expand_dates_and_formats() {
+        let template = "{{time}} {{datetime}} {{date:%Y/%m}} {{ yesterday }} {{tomorrow:%a}} {{vault}}";
+        let result = expand(template, &vars("My Note"), false).unwrap();
+        assert_eq!(result, "15:30 2026-02-27T15:30:45 2026/02 2026-02-26 Sat /notes");
+        assert!(expand("{{date:%Q}}", &vars("My Note"), false).is_err());
+        assert!(expand("{{title:%Y}}", &vars("My Note"), false).is_err());
+    }
+
+    #[test]
+    fn test_expand_uuid_stdin_and_user_variables() {
+        let user = vec![("project".to_string(), "sprout".to_string())];
+        let vars = TemplateVars { stdin: Some("piped\n"), user: &user, ..vars("My Note") };
+        let result = expand("{{uuid}} {{uuid}}|{{project}}|{{stdin}}", &vars, false).unwrap();
+        let (uuids, rest) = result.split_once('|').unwrap();
+        let (a, b) = uuids.split_once(' ').unwrap();
+        assert_eq!(a.len(), 36);
+        assert_eq!(a, b);
+        assert_eq!(rest, "sprout|piped\n");
+        assert!(uses_stdin("{{ stdin }}"));
+        assert!(!uses_stdin("\\{{stdin}}"));
+    }
+
+    #[test]
+    fn test_expand_rejects_unknown_variables() {
+        let err = expand("# {{title}} {{auther}}", &vars("My Note"), false).unwrap_err();
+        assert_eq!(err.error_code(), "unknown_template_variable");
+        assert!(expand("# {{title", &vars("My Note"), false).is_err());
+    }
+
+    #[test]
+    fn test_expand_escaped_braces() {
+        let template = "\\{{title}} {{title}} \\{{$(echo no)}} }}";
+        let result = expand(template, &vars("My Note"), true).unwrap();
+        assert_eq!(result, "{{title}} My Note {{$(echo no)}} }}");
+    }
+
+    #[test]
+    fn test_validate_user_vars() {
+        let var = |k: &str| vec![(k.to_string(), "v".to_string())];
+        assert!(validate_user_vars(&var("project")).is_ok());
+        assert!(validate_user_vars(&var("my-var_2")).is_ok());
+        assert!(validate_user_vars(&var("title")).is_err());
+        assert!(validate_user_vars(&var("a:b")).is_err());
+        assert!(validate_user_vars(&var("")).is_err());
+    }
+
     #[test]
     fn test_expand_preserves_exec_when_disabled() {
         let template = "# {{title}}\nYear: {{$(date +%Y)}}\n";
-        let result = expand(template, "Test", "2026-02-27", false).unwrap();
+        let result = expand(template, &vars("Test"), false).unwrap();
         assert!(result.contains("{{$(date +%Y)}}"));
     }
 
     #[test]
     fn test_expand_exec_when_enabled() {
         let template = "# {{title}}\nEcho: {{$(echo hello)}}\n";
-        let result = expand(template, "Test", "2026-02-27", true).unwrap();
+        let result = expand(template, &vars("Test"), true).unwrap();
         assert_eq!(result, "# Test\nEcho: hello\n");
     }
 
     #[test]
     fn test_expand_exec_receives_sprout_title() {
         let template = "Title: {{$(echo $SPROUT_TITLE)}}\n";
-        let result = expand(template, "My Note", "2026-02-27", true).unwrap();
+        let result = expand(template, &vars("My Note"), true).unwrap();
         assert_eq!(result, "Title: My Note\n");
+
+        let template = "{{$(echo $SPROUT_ID $SPROUT_SLUG)}}";
+        assert_eq!(expand(template, &vars("My Note"), true).unwrap(), "202602271530 my-note");
+
+        let template = "{{$(echo {{slug}} $SPROUT_VAULT)}}";
+        assert_eq!(expand(template, &vars("My Note"), true).unwrap(), "my-note /notes");
     }
 }
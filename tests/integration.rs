@@ -4,7 +4,12 @@ use std::fs;
 use tempfile::TempDir;
 
 fn sprout() -> Command {
-    Command::cargo_bin("sprout").unwrap()
+    let mut cmd = assert_cmd::cargo::cargo_bin_cmd!("sprout");
+    // Keep the review history log out of the user's data directory
+    cmd.env("XDG_DATA_HOME", std::env::temp_dir().join("sprout-test-data"));
+    // Same for the per-vault frontmatter caches
+    cmd.env("XDG_CACHE_HOME", std::env::temp_dir().join("sprout-test-cache"));
+    cmd
 }
 
 /// Copy a fixture file into a temp vault directory and return (dir, file_path).
@@ -167,6 +172,163 @@ fn review_shows_due_notes() {
         .stdout(predicate::str::contains("tracked.md"));
 }
 
+// ── agenda ────────────────────────────────────────────────────────
+
+#[test]
+fn agenda_groups_notes_into_sections() {
+    // tracked.md: next_review 2026-02-21 → overdue; partial.md → incomplete
+    let dir = setup_vault_multi(&["tracked.md", "partial.md", "untracked.md"]);
+    let output = sprout()
+        .args(["agenda", "--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    let overdue: Vec<_> = json["overdue"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|g| g["notes"].as_array().unwrap().clone())
+        .collect();
+    assert_eq!(overdue.len(), 1);
+    assert_eq!(overdue[0]["relative_path"], "tracked.md");
+    assert_eq!(json["incomplete"][0]["relative_path"], "partial.md");
+    assert!(json["incomplete"][0]["missing_fields"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!("created")));
+    assert_eq!(json["stale_days"], 14);
+}
+
+#[test]
+fn agenda_empty_vault() {
+    let dir = TempDir::new().unwrap();
+    sprout()
+        .args(["agenda", "--vault", dir.path().to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Nothing on the agenda."));
+}
+
+// ── sort / limit ──────────────────────────────────────────────────
+
+fn write_note(dir: &std::path::Path, name: &str, interval: u32, ease: f64, next_review: &str) {
+    fs::write(
+        dir.join(name),
+        format!(
+            "---\nmaturity: seedling\ncreated: 2026-01-01\nlast_review: 2026-01-01\nreview_interval: {interval}\nnext_review: {next_review}\nease: {ease}\n---\nBody\n"
+        ),
+    )
+    .unwrap();
+}
+
+fn relative_paths(stdout: &[u8]) -> Vec<String> {
+    let json: serde_json::Value = serde_json::from_slice(stdout).unwrap();
+    json.as_array()
+        .unwrap()
+        .iter()
+        .map(|n| n["relative_path"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn list_sort_ease_with_limit_and_offset() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md", 5, 2.7, "2026-01-10");
+    write_note(dir.path(), "b.md", 5, 2.1, "2026-01-10");
+    write_note(dir.path(), "c.md", 5, 2.4, "2026-01-10");
+
+    let output = sprout()
+        .args(["list", "--sort", "ease", "--reverse", "--offset", "1", "--limit", "1"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(relative_paths(&output.stdout), vec!["c.md"]);
+}
+
+#[test]
+fn review_sort_overdue_ratio() {
+    let dir = TempDir::new().unwrap();
+    // Both 30 days late relative to 2026-01-10; short interval is relatively more overdue
+    write_note(dir.path(), "long.md", 60, 2.5, "2026-01-10");
+    write_note(dir.path(), "short.md", 2, 2.5, "2026-01-10");
+
+    let output = sprout()
+        .args(["review", "--sort", "overdue-ratio", "--reverse"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(relative_paths(&output.stdout), vec!["short.md", "long.md"]);
+}
+
+#[test]
+fn list_sort_random_is_reproducible_with_seed() {
+    let dir = TempDir::new().unwrap();
+    for name in ["a.md", "b.md", "c.md", "d.md", "e.md"] {
+        write_note(dir.path(), name, 5, 2.5, "2026-01-10");
+    }
+    let run = || {
+        let output = sprout()
+            .args(["list", "--sort", "random", "--seed", "7"])
+            .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+            .output()
+            .unwrap();
+        relative_paths(&output.stdout)
+    };
+    assert_eq!(run(), run());
+}
+
+// ── query ──────────────────────────────────────────────────────────
+
+#[test]
+fn list_query_filters_by_fields_tags_and_links() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("projects")).unwrap();
+    fs::write(
+        dir.path().join("projects/a.md"),
+        "---\ntags: [rust]\nmaturity: budding\ncreated: 2026-01-01\nlast_review: 2026-01-01\nreview_interval: 20\nnext_review: 2026-01-21\nease: 2.5\n---\nSee [[b]]\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("projects/b.md"),
+        "---\nmaturity: budding\ncreated: 2026-01-01\nlast_review: 2026-01-01\nreview_interval: 20\nnext_review: 2026-01-21\nease: 2.5\n---\n#rust [[a]] [[c]]\n",
+    )
+    .unwrap();
+    write_note(dir.path(), "c.md", 20, 2.5, "2026-01-21");
+
+    let output = sprout()
+        .args(["list", "--query", "maturity:budding AND tag:rust AND interval>14 AND path:projects/ AND links<2"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(relative_paths(&output.stdout), vec!["projects/a.md"]);
+
+    let output = sprout()
+        .args(["stats", "--query", "NOT path:projects/"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 1);
+}
+
+#[test]
+fn review_query_parse_error() {
+    let dir = TempDir::new().unwrap();
+    sprout()
+        .args(["review", "--query", "interval>>3"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .assert()
+        .failure()
+        .stdout("")
+        .stderr(predicate::str::contains("\"error\":\"parse_error\""))
+        .stderr(predicate::str::contains("column 10"));
+}
+
 // ── stats ──────────────────────────────────────────────────────────
 
 #[test]
@@ -181,6 +343,65 @@ fn stats_returns_correct_counts() {
         .stdout(predicate::str::contains("\"budding\":1"));
 }
 
+#[test]
+fn stats_detailed_includes_history() {
+    let data = TempDir::new().unwrap();
+    let (dir, file) = setup_vault("tracked.md");
+    let vault = dir.path().to_str().unwrap();
+    sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["done", file.to_str().unwrap(), "hard", "--vault", vault])
+        .assert()
+        .success();
+    sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["promote", file.to_str().unwrap(), "budding", "--vault", vault])
+        .assert()
+        .success();
+
+    let output = sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["stats", "--detailed", "--vault", vault, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["total"], 1);
+    assert_eq!(json["ratings"]["hard"], 1);
+    assert_eq!(json["true_retention"], 0.0);
+    assert_eq!(json["by_maturity"]["budding"]["count"], 1);
+    assert_eq!(json["growth"][0]["month"], "2026-02");
+    assert_eq!(json["transitions"][0]["from"], "seedling");
+    assert_eq!(json["transitions"][0]["to"], "budding");
+}
+
+#[test]
+fn stats_calendar_reports_streak_and_days() {
+    let data = TempDir::new().unwrap();
+    let (dir, file) = setup_vault("tracked.md");
+    let vault = dir.path().to_str().unwrap();
+    sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["done", file.to_str().unwrap(), "good", "--vault", vault])
+        .assert()
+        .success();
+
+    let output = sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["stats", "--calendar", "--vault", vault, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["streak"]["current"], 1);
+    assert_eq!(json["streak"]["longest"], 1);
+    let days = json["calendar"].as_array().unwrap();
+    assert_eq!(days.len(), 365);
+    assert_eq!(days.last().unwrap()["count"], 1);
+    // --detailed keys are only present when requested
+    assert!(json.get("ratings").is_none());
+}
+
 // ── done ───────────────────────────────────────────────────────────
 
 #[test]
@@ -285,6 +506,102 @@ fn done_easy_increases_ease() {
     assert!(content.contains("ease: 2.65")); // 2.50 + 0.15
 }
 
+// ── bulk ──────────────────────────────────────────────────────────
+
+fn bulk_json(output: &std::process::Output) -> serde_json::Value {
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn done_bulk_with_query_and_stdin() {
+    let dir = TempDir::new().unwrap();
+    let data = TempDir::new().unwrap();
+    for name in ["a.md", "b.md", "c.md"] {
+        write_note(dir.path(), name, 3, 2.5, "2026-01-04");
+    }
+
+    let output = sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .current_dir(dir.path())
+        .args(["done", "-", "good", "--query", "path:c"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .write_stdin("a.md\n\na.md\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json = bulk_json(&output);
+    assert_eq!(json["applied"], true);
+    assert_eq!(json["succeeded"], 2);
+    assert_eq!(json["results"].as_array().unwrap().len(), 2);
+
+    let b = fs::read_to_string(dir.path().join("b.md")).unwrap();
+    assert!(b.contains("review_interval: 3"));
+    let c = fs::read_to_string(dir.path().join("c.md")).unwrap();
+    assert!(!c.contains("review_interval: 3"));
+}
+
+#[test]
+fn promote_bulk_reports_errors_and_atomic_writes_nothing() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md", 3, 2.5, "2026-01-04");
+    fs::write(dir.path().join("plain.md"), "No frontmatter\n").unwrap();
+    let a = dir.path().join("a.md");
+    let plain = dir.path().join("plain.md");
+
+    let output = sprout()
+        .args(["promote", a.to_str().unwrap(), plain.to_str().unwrap(), "budding", "--atomic"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json = bulk_json(&output);
+    assert_eq!(json["applied"], false);
+    assert_eq!(json["errors"][0]["error"], "no_frontmatter");
+    assert!(String::from_utf8_lossy(&output.stderr).contains("bulk_failed"));
+    assert!(fs::read_to_string(&a).unwrap().contains("maturity: seedling"));
+
+    // Without --atomic the valid note is still promoted
+    let output = sprout()
+        .args(["promote", a.to_str().unwrap(), plain.to_str().unwrap(), "budding"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json = bulk_json(&output);
+    assert_eq!(json["succeeded"], 1);
+    assert_eq!(json["failed"], 1);
+    assert!(fs::read_to_string(&a).unwrap().contains("maturity: budding"));
+}
+
+#[test]
+fn init_all_and_glob_skip_initialized_notes() {
+    let dir = TempDir::new().unwrap();
+    fs::create_dir(dir.path().join("inbox")).unwrap();
+    write_note(dir.path(), "done.md", 3, 2.5, "2026-01-04");
+    fs::write(dir.path().join("inbox/new.md"), "Body\n").unwrap();
+    fs::write(dir.path().join("top.md"), "Body\n").unwrap();
+
+    let output = sprout()
+        .args(["init", "--glob", "inbox/*.md"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json = bulk_json(&output);
+    assert_eq!(json["succeeded"], 1);
+    assert_eq!(json["results"][0]["relative_path"], "inbox/new.md");
+
+    let output = sprout()
+        .args(["init", "--all"])
+        .args(["--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json = bulk_json(&output);
+    assert_eq!(json["succeeded"], 1);
+    assert_eq!(json["results"][0]["relative_path"], "top.md");
+}
+
 // ── promote edge cases ────────────────────────────────────────
 
 #[test]
@@ -523,6 +840,32 @@ fn note_create_already_exists_idempotent() {
     assert_eq!(content, "# Existing note\n");
 }
 
+#[test]
+fn note_create_and_links_go_through_aliases() {
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+    fs::create_dir(dir.path().join("topics")).unwrap();
+    fs::write(dir.path().join("topics/machine-learning.md"), "---\naliases: [ML]\n---\n# Machine Learning\n").unwrap();
+
+    let output = sprout().args(["note", "ml", "--vault", vault, "--format", "json"]).output().unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["relative_path"], "topics/machine-learning.md");
+    assert_eq!(json["is_new"], false);
+    assert!(!dir.path().join("ml.md").exists());
+
+    // A link by alias and one by file name are the same link
+    let note = dir.path().join("a.md");
+    fs::write(
+        &note,
+        "---\nmaturity: seedling\ncreated: 2026-01-01\nlast_review: 2026-01-01\nreview_interval: 1\nnext_review: 2026-01-02\nease: 2.5\n---\n[[ML]] and [[machine-learning]], [[topics/machine-learning]], [[Other]]\n",
+    )
+    .unwrap();
+    let output = sprout().args(["show", note.to_str().unwrap(), "--vault", vault, "--format", "json"]).output().unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["link_count"], 2);
+}
+
 #[test]
 fn note_create_human_format() {
     let dir = TempDir::new().unwrap();
@@ -547,6 +890,191 @@ fn note_create_strips_md_suffix() {
     assert!(!dir.path().join("test.md.md").exists());
 }
 
+#[test]
+fn note_create_names_files_by_scheme_and_avoids_collisions() {
+    let config = TempDir::new().unwrap();
+    fs::create_dir_all(config.path().join("sprout")).unwrap();
+    fs::write(config.path().join("sprout/config.toml"), "filename_scheme = \"slug\"\n").unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    fs::write(dir.path().join("sub/my-note.md"), "# Something else\n").unwrap();
+
+    let create = |title: &str| {
+        let output = sprout()
+            .env("XDG_CONFIG_HOME", config.path())
+            .args(["note", title, "--vault", vault, "--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // The slug is taken in another directory, so the new note gets -2
+    let json = create("My Note!");
+    assert_eq!(json["relative_path"], "my-note-2.md");
+    assert_eq!(json["is_new"], true);
+    let content = fs::read_to_string(dir.path().join("my-note-2.md")).unwrap();
+    assert!(content.contains("# My Note!"));
+
+    // The same title again finds the note by its heading
+    let json = create("my note!");
+    assert_eq!(json["relative_path"], "my-note-2.md");
+    assert_eq!(json["is_new"], false);
+}
+
+#[test]
+fn note_create_luhmann_ids_with_parent() {
+    let config = TempDir::new().unwrap();
+    fs::create_dir_all(config.path().join("sprout")).unwrap();
+    fs::write(
+        config.path().join("sprout/config.toml"),
+        "filename_scheme = \"id-slug\"\nid_scheme = \"luhmann\"\nauto_init = false\n",
+    )
+    .unwrap();
+    let templates = config.path().join("sprout/templates");
+    fs::create_dir_all(&templates).unwrap();
+    fs::write(templates.join("default.md"), "---\nid: \"{{id}}\"\ntitle: {{title}}\n---\n").unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+    fs::write(dir.path().join("1-first.md"), "# First\n").unwrap();
+    fs::write(dir.path().join("1a-child.md"), "# Child\n").unwrap();
+
+    let create = |args: &[&str]| {
+        sprout()
+            .env("XDG_CONFIG_HOME", config.path())
+            .args(["note", "--vault", vault, "--format", "json"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+
+    let output = create(&["Second"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["relative_path"], "2-second.md");
+
+    let output = create(&["Sibling", "--parent", "1"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["relative_path"], "1b-sibling.md");
+    // The frontmatter title counts as the note's title: no heading is added
+    let content = fs::read_to_string(dir.path().join("1b-sibling.md")).unwrap();
+    assert_eq!(content, "---\nid: \"1b\"\ntitle: Sibling\n---\n");
+
+    let output = create(&["Sibling"]);
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["is_new"], false);
+
+    let output = create(&["Bad", "--parent", "x1"]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn note_create_luhmann_ids_ignore_daily_notes() {
+    let config = TempDir::new().unwrap();
+    fs::create_dir_all(config.path().join("sprout")).unwrap();
+    fs::write(
+        config.path().join("sprout/config.toml"),
+        "filename_scheme = \"id\"\nid_scheme = \"luhmann\"\nauto_init = false\n",
+    )
+    .unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+    fs::write(dir.path().join("1.md"), "# First\n").unwrap();
+    fs::write(dir.path().join("2026-10-18.md"), "# Daily\n").unwrap();
+
+    let create = |title: &str| {
+        let output = sprout()
+            .env("XDG_CONFIG_HOME", config.path())
+            .args(["note", title, "--vault", vault, "--format", "json"])
+            .output()
+            .unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    // `2026-10-18` is not a Luhmann ID, so it does not push numbering to 2027
+    assert_eq!(create("New idea")["relative_path"], "2.md");
+    let content = fs::read_to_string(dir.path().join("2.md")).unwrap();
+    assert!(content.starts_with("---\nid: 2\n"), "{content}");
+
+    // The recorded ID counts even after the note is renamed
+    fs::rename(dir.path().join("2.md"), dir.path().join("renamed.md")).unwrap();
+    assert_eq!(create("Another")["relative_path"], "3.md");
+}
+
+#[test]
+fn note_create_places_notes_in_directories() {
+    let config = TempDir::new().unwrap();
+    fs::create_dir_all(config.path().join("sprout")).unwrap();
+    fs::write(
+        config.path().join("sprout/config.toml"),
+        "default_note_dir = \"inbox\"\n\n[template_note_dirs]\ndaily = \"journal/daily\"\n",
+    )
+    .unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+
+    let create = |args: &[&str]| {
+        sprout()
+            .env("XDG_CONFIG_HOME", config.path())
+            .args(["note", "--vault", vault, "--format", "json"])
+            .args(args)
+            .output()
+            .unwrap()
+    };
+    let relative_path = |output: std::process::Output| {
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json["relative_path"].as_str().unwrap().to_string()
+    };
+
+    assert_eq!(relative_path(create(&["Idea"])), "inbox/Idea.md");
+    assert_eq!(relative_path(create(&["Today", "--template", "daily"])), "journal/daily/Today.md");
+    assert_eq!(relative_path(create(&["Topic", "--dir", "topics/rust"])), "topics/rust/Topic.md");
+    assert!(dir.path().join("topics/rust/Topic.md").exists());
+
+    let output = create(&["Escape", "--dir", "../outside"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("outside_vault"));
+
+    #[cfg(unix)]
+    {
+        let outside = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+        let output = create(&["Escape", "--dir", "link/sub"]);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("outside_vault"));
+        assert!(!outside.path().join("sub").exists());
+    }
+}
+
+#[test]
+fn note_create_expands_user_variables_and_stdin() {
+    let config = TempDir::new().unwrap();
+    let templates = config.path().join("sprout/templates");
+    fs::create_dir_all(&templates).unwrap();
+    fs::write(config.path().join("sprout/config.toml"), "auto_init = false\n").unwrap();
+    fs::write(templates.join("clip.md"), "# {{title}}\nproject: {{project}}\n\\{{literal}}\n{{stdin}}").unwrap();
+    fs::write(templates.join("typo.md"), "# {{title}}\n{{auther}}\n").unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+
+    sprout()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["note", "Clip", "--template", "clip", "--var", "project=sprout", "--vault", vault])
+        .write_stdin("piped text\n")
+        .assert()
+        .success();
+    let content = fs::read_to_string(dir.path().join("Clip.md")).unwrap();
+    assert_eq!(content, "# Clip\nproject: sprout\n{{literal}}\npiped text\n");
+
+    sprout()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["note", "Typo", "--template", "typo", "--vault", vault, "--format", "json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown_template_variable"));
+    assert!(!dir.path().join("Typo.md").exists());
+}
+
 // ── note validation ───────────────────────────────────────────────
 
 #[test]
@@ -606,3 +1134,799 @@ fn error_exit_code_is_one() {
         .failure()
         .code(1);
 }
+
+// ── optimize ──────────────────────────────────────────────────────
+
+#[test]
+fn done_appends_review_history() {
+    let data = TempDir::new().unwrap();
+    let (dir, file) = setup_vault("tracked.md");
+    sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["done", file.to_str().unwrap(), "easy", "--vault", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+
+    let log = fs::read_to_string(data.path().join("sprout/history.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 1);
+    assert!(log.contains("\"event\":\"review\""));
+    assert!(log.contains("\"rating\":\"easy\""));
+}
+
+#[test]
+fn optimize_insufficient_history() {
+    let data = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["optimize", "--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("insufficient_history"));
+}
+
+#[test]
+fn optimize_recommends_parameters() {
+    let data = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = fs::canonicalize(dir.path()).unwrap();
+    let mut log = String::new();
+    for i in 0..15 {
+        for date in ["2026-03-01", "2026-03-11"] {
+            log.push_str(&format!(
+                "{{\"event\":\"review\",\"date\":\"{date}\",\"path\":\"{}/n{i}.md\",\"rating\":\"good\",\"maturity\":\"seedling\",\"interval\":10,\"ease\":2.5,\"delayed\":0,\"link_count\":0,\"new_interval\":20,\"new_ease\":2.5}}\n",
+                vault.display()
+            ));
+        }
+    }
+    fs::create_dir_all(data.path().join("sprout")).unwrap();
+    fs::write(data.path().join("sprout/history.jsonl"), log).unwrap();
+
+    let output = sprout()
+        .env("XDG_DATA_HOME", data.path())
+        .args(["optimize", "--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["follow_ups"], 15);
+    let good = json["parameters"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|p| p["name"] == "good_multiplier")
+        .unwrap();
+    assert!(good["recommended"].as_f64().unwrap() > 0.8);
+}
+
+// ── extensions & org notes ────────────────────────────────────────
+
+#[test]
+fn org_notes_are_scanned_and_reviewed() {
+    let config = TempDir::new().unwrap();
+    fs::create_dir_all(config.path().join("sprout")).unwrap();
+    fs::write(
+        config.path().join("sprout/config.toml"),
+        "extensions = [\"md\", \"org\"]\nload_balance = false\n",
+    )
+    .unwrap();
+
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md", 3, 2.5, "2026-01-02");
+    let org = dir.path().join("zettel.org");
+    fs::write(
+        &org,
+        ":PROPERTIES:\n:ID:       1234\n:MATURITY: seedling\n:CREATED: 2026-01-01\n:LAST_REVIEW: 2026-01-01\n:REVIEW_INTERVAL: 3\n:NEXT_REVIEW: 2026-01-02\n:EASE: 2.5\n:END:\n#+title: Zettel\n\nBody\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("ignored.txt"), "text").unwrap();
+
+    let output = sprout()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["list", "--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut paths = relative_paths(&output.stdout);
+    paths.sort();
+    assert_eq!(paths, vec!["a.md", "zettel.org"]);
+
+    sprout()
+        .env("XDG_CONFIG_HOME", config.path())
+        .args(["done", org.to_str().unwrap(), "good", "--vault", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&org).unwrap();
+    assert!(content.starts_with(":PROPERTIES:\n:ID:       1234\n"));
+    assert!(!content.contains(":NEXT_REVIEW: 2026-01-02\n"));
+    assert!(!content.contains("---"));
+    assert!(content.ends_with(":END:\n#+title: Zettel\n\nBody\n"));
+}
+
+#[test]
+fn init_org_note_adds_property_drawer() {
+    let dir = TempDir::new().unwrap();
+    let org = dir.path().join("new.org");
+    fs::write(&org, "#+title: New\n").unwrap();
+
+    sprout()
+        .args(["init", org.to_str().unwrap(), "--vault", dir.path().to_str().unwrap()])
+        .assert()
+        .success();
+    let content = fs::read_to_string(&org).unwrap();
+    assert!(content.starts_with(":PROPERTIES:\n:MATURITY: seedling\n:CREATED: "));
+    assert!(content.ends_with(":END:\n#+title: New\n"));
+}
+
+// ── cache ─────────────────────────────────────────────────────────
+
+fn cache_stats(cache: &std::path::Path, vault: &std::path::Path) -> serde_json::Value {
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache)
+        .args(["cache", "stats", "--vault", vault.to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    serde_json::from_slice(&output.stdout).unwrap()
+}
+
+#[test]
+fn cache_is_per_vault_and_pruned() {
+    let cache = TempDir::new().unwrap();
+    let a = TempDir::new().unwrap();
+    let b = TempDir::new().unwrap();
+    write_note(a.path(), "one.md", 3, 2.5, "2026-01-02");
+    write_note(a.path(), "two.md", 3, 2.5, "2026-01-02");
+    write_note(b.path(), "other.md", 3, 2.5, "2026-01-02");
+
+    for vault in [a.path(), b.path()] {
+        sprout()
+            .env("XDG_CACHE_HOME", cache.path())
+            .args(["list", "--vault", vault.to_str().unwrap(), "--format", "json"])
+            .assert()
+            .success();
+    }
+    let stats_a = cache_stats(cache.path(), a.path());
+    let stats_b = cache_stats(cache.path(), b.path());
+    assert_eq!(stats_a["entries"], 2);
+    assert_eq!(stats_a["stale"], 0);
+    assert_eq!(stats_b["entries"], 1);
+    assert_ne!(stats_a["path"], stats_b["path"]);
+
+    // Deleted files are pruned on the next scan
+    fs::remove_file(a.path().join("two.md")).unwrap();
+    assert_eq!(cache_stats(cache.path(), a.path())["stale"], 1);
+    sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["review", "--vault", a.path().to_str().unwrap(), "--format", "json"])
+        .assert()
+        .success();
+    let stats_a = cache_stats(cache.path(), a.path());
+    assert_eq!(stats_a["entries"], 1);
+    assert_eq!(stats_a["stale"], 0);
+}
+
+#[test]
+fn cache_clear_and_rebuild() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "one.md", 3, 2.5, "2026-01-02");
+    let vault = dir.path().to_str().unwrap();
+
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["cache", "rebuild", "--vault", vault, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["entries"], 1);
+    let cache_file = std::path::PathBuf::from(json["path"].as_str().unwrap());
+    assert!(cache_file.starts_with(cache.path()));
+    assert!(cache_file.exists());
+
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["cache", "clear", "--vault", vault, "--format", "json"])
+        .output()
+        .unwrap();
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["removed"], true);
+    assert!(!cache_file.exists());
+    // Another process may hold the lock: its file is left for it
+    assert!(cache_file.with_extension("lock").exists());
+    assert_eq!(cache_stats(cache.path(), dir.path())["entries"], 0);
+}
+
+#[test]
+fn cache_imports_and_clears_the_global_json_cache() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "one.md", 3, 2.5, "2026-01-02");
+    let vault = dir.path().to_str().unwrap();
+    let note = fs::canonicalize(dir.path().join("one.md")).unwrap();
+    let meta = fs::metadata(&note).unwrap();
+    let mtime = meta.modified().unwrap().duration_since(std::time::UNIX_EPOCH).unwrap();
+    let legacy = cache.path().join("sprout/frontmatter.json");
+    fs::create_dir_all(legacy.parent().unwrap()).unwrap();
+    let entries = serde_json::json!({
+        note.to_str().unwrap(): {
+            "mtime_secs": mtime.as_secs(),
+            "mtime_nanos": mtime.subsec_nanos(),
+            "size": meta.len(),
+            "frontmatter": {"maturity": "seedling"}
+        },
+        "/elsewhere/two.md": {"mtime_secs": 0, "mtime_nanos": 0, "size": 0, "frontmatter": {}}
+    });
+    let legacy_json = serde_json::json!({"version": 1, "entries": entries}).to_string();
+    fs::write(&legacy, &legacy_json).unwrap();
+
+    let legacy_paths = || {
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&legacy).unwrap()).unwrap();
+        json["entries"].as_object().unwrap().keys().cloned().collect::<Vec<_>>()
+    };
+
+    // The vault's entry is imported and dropped from the global file; the
+    // other vault's entry stays for it to import
+    let stats = cache_stats(cache.path(), dir.path());
+    assert_eq!(stats["entries"], 1);
+    assert_eq!(stats["stale"], 0);
+    sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["list", "--vault", vault, "--format", "json"])
+        .assert()
+        .success();
+    assert_eq!(legacy_paths(), vec!["/elsewhere/two.md"]);
+
+    // `cache clear` drops only this vault's entries from the global file
+    fs::write(&legacy, &legacy_json).unwrap();
+    sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["cache", "clear", "--vault", vault, "--format", "json"])
+        .assert()
+        .success();
+    assert_eq!(legacy_paths(), vec!["/elsewhere/two.md"]);
+}
+
+// ── search ────────────────────────────────────────────────────────
+
+#[test]
+fn search_ranks_notes_and_filters_results() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(
+        dir.path().join("ownership.md"),
+        "---\nmaturity: budding\ntags: [rust]\n---\n# Ownership\n\nEvery value has one owner.\nThe borrow checker enforces borrowing rules.\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.path().join("lifetimes.md"),
+        "---\nmaturity: seedling\ntags: [rust/advanced]\n---\n# Lifetimes\n\nLifetimes tell the borrow checker how long references live.\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("garden.md"), "# Garden\n\nWater the seedlings.\n").unwrap();
+    let vault = dir.path().to_str().unwrap();
+
+    let search = |args: &[&str]| -> serde_json::Value {
+        let output = sprout()
+            .env("XDG_CACHE_HOME", cache.path())
+            .arg("search")
+            .args(args)
+            .args(["--vault", vault, "--format", "json"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+    let paths = |json: &serde_json::Value| -> Vec<String> {
+        json.as_array().unwrap().iter().map(|n| n["relative_path"].as_str().unwrap().to_string()).collect()
+    };
+
+    let json = search(&["borrow", "checker"]);
+    assert_eq!(paths(&json).len(), 2);
+    assert!(json[0]["score"].as_f64().unwrap() >= json[1]["score"].as_f64().unwrap());
+    let snippet = &json.as_array().unwrap().iter().find(|n| n["relative_path"] == "ownership.md").unwrap()["snippets"][0];
+    assert_eq!(snippet["line"], 8);
+    assert_eq!(snippet["text"], "The borrow checker enforces borrowing rules.");
+
+    assert_eq!(paths(&search(&["checker borrow"])), Vec::<String>::new());
+    assert_eq!(paths(&search(&["own*"])), vec!["ownership.md"]);
+    assert_eq!(paths(&search(&["borrow", "--maturity", "seedling"])), vec!["lifetimes.md"]);
+    assert_eq!(paths(&search(&["borrow", "--tag", "#rust", "--limit", "1"])).len(), 1);
+    assert_eq!(paths(&search(&["garden"])), vec!["garden.md"]);
+
+    // The index lives next to the frontmatter cache and follows edits
+    let index = fs::read_dir(cache.path().join("sprout/vaults"))
+        .unwrap()
+        .filter_map(Result::ok)
+        .any(|e| e.path().extension().is_some_and(|x| x == "idx"));
+    assert!(index);
+    fs::write(dir.path().join("garden.md"), "# Garden\n\nThe borrow checker grows here too.\n").unwrap();
+    assert_eq!(search(&["borrow", "checker"]).as_array().unwrap().len(), 3);
+}
+
+// ── related ───────────────────────────────────────────────────────
+
+#[test]
+fn related_ranks_unlinked_notes_and_finds_mentions() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let notes = [
+        ("ownership.md", "---\ntags: [rust]\n---\n# Ownership\n\nRust values have one owner. See [[borrowing]].\n"),
+        ("borrowing.md", "# Borrowing\n\nReferences borrow values without taking ownership. [[lifetimes]]\n"),
+        ("lifetimes.md", "---\ntags: [rust]\n---\n# Lifetimes\n\nLifetimes keep references valid; Ownership rules apply.\n"),
+        ("moves.md", "# Moves\n\nMoving values transfers ownership, see [[Ownership]].\n"),
+        ("garden.md", "# Garden\n\nWater the plants.\n"),
+    ];
+    for (name, content) in notes {
+        fs::write(dir.path().join(name), content).unwrap();
+    }
+
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["related", dir.path().join("ownership.md").to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["title"], "Ownership");
+
+    // borrowing.md and moves.md are already linked; garden.md shares nothing
+    let related = json["related"].as_array().unwrap();
+    assert_eq!(related.len(), 1);
+    assert_eq!(related[0]["relative_path"], "lifetimes.md");
+    assert_eq!(related[0]["shared_tags"], serde_json::json!(["rust"]));
+    assert_eq!(related[0]["distance"], 2);
+    assert!(related[0]["similarity"].as_f64().unwrap() > 0.0);
+
+    let mentions: Vec<(String, u64, u64)> = json["unlinked_mentions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| (m["relative_path"].as_str().unwrap().to_string(), m["line"].as_u64().unwrap(), m["column"].as_u64().unwrap()))
+        .collect();
+    assert_eq!(
+        mentions,
+        vec![("borrowing.md".to_string(), 3, 41), ("lifetimes.md".to_string(), 6, 34), ("moves.md".to_string(), 3, 25)]
+    );
+}
+
+#[test]
+fn autolink_shows_a_diff_then_links_mentions() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("machine-learning.md"), "---\naliases: [ML]\n---\n# Machine Learning\n").unwrap();
+    fs::write(dir.path().join("rust.md"), "# Rust\n").unwrap();
+    let note = dir.path().join("notes.md");
+    let content = "# Notes on Rust\n\nRust for ML, see [[rust]].\n\n```\nML in code\n```\n";
+    fs::write(&note, content).unwrap();
+
+    let autolink = |args: &[&str]| -> serde_json::Value {
+        let output = sprout()
+            .env("XDG_CACHE_HOME", cache.path())
+            .args(["autolink", note.to_str().unwrap(), "--format", "json"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice(&output.stdout).unwrap()
+    };
+
+    // Without --yes and no terminal to ask on, nothing is written
+    let json = autolink(&[]);
+    assert_eq!(json["dry_run"], true);
+    assert_eq!(json["applied"], 0);
+    let links: Vec<(&str, &str)> = json["links"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|l| (l["text"].as_str().unwrap(), l["link"].as_str().unwrap()))
+        .collect();
+    assert_eq!(links, vec![("Rust", "[[Rust]]"), ("ML", "[[ML]]")]);
+    assert_eq!(json["diff"][0]["line"], 3);
+    assert_eq!(json["diff"][0]["new"], "[[Rust]] for [[ML]], see [[rust]].");
+    assert_eq!(fs::read_to_string(&note).unwrap(), content);
+
+    let json = autolink(&["--yes"]);
+    assert_eq!(json["dry_run"], false);
+    assert_eq!(json["applied"], 2);
+    assert_eq!(
+        fs::read_to_string(&note).unwrap(),
+        "# Notes on Rust\n\n[[Rust]] for [[ML]], see [[rust]].\n\n```\nML in code\n```\n"
+    );
+    assert_eq!(autolink(&["--yes"])["links"], serde_json::json!([]));
+}
+
+// ── check / anchors ──────────────────────────────────────────────
+
+#[test]
+fn check_links_reports_stale_anchors_and_show_lists_referenced_sections() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = dir.path().to_str().unwrap();
+    let notes = [
+        ("guide.md", "# Guide\n\n## Setup Guide\n\nInstall it. ^install\n\n## Usage\n"),
+        ("a.md", "See [[guide#Setup Guide]] and [usage](guide.md#usage).\n\n`[[guide#Ignored]]`\n"),
+        ("b.md", "# B\n\n[[guide#^install]], [[guide#Removed]]\nand [[guide#^gone]] or [[nowhere]].\n"),
+    ];
+    for (name, content) in notes {
+        fs::write(dir.path().join(name), content).unwrap();
+    }
+
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["check", "links", "--vault", vault, "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["notes"], 3);
+    let broken: Vec<String> = json["broken"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|b| format!("{}:{}:{} {} {}", b["relative_path"].as_str().unwrap(), b["line"], b["column"], b["target"].as_str().unwrap(), b["problem"].as_str().unwrap()))
+        .collect();
+    assert_eq!(
+        broken,
+        vec!["b.md:3:21 guide#Removed missing_heading", "b.md:4:5 guide#^gone missing_block", "b.md:4:24 nowhere missing_note"]
+    );
+    let stderr: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+    assert_eq!(stderr["error"], "broken_links");
+
+    let output = sprout()
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["show", dir.path().join("guide.md").to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        json["referenced_sections"],
+        serde_json::json!([
+            {"section": "Setup Guide", "referenced_by": ["a.md"]},
+            {"section": "Usage", "referenced_by": ["a.md"]},
+            {"section": "^install", "referenced_by": ["b.md"]},
+        ])
+    );
+}
+
+// ── concurrency ───────────────────────────────────────────────────
+
+#[test]
+fn concurrent_scans_share_a_consistent_cache() {
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    for i in 0..200 {
+        write_note(dir.path(), &format!("n{i:03}.md"), 3, 2.5, "2026-01-02");
+    }
+    let vault = dir.path().to_str().unwrap();
+
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            let command = if i % 2 == 0 { "review" } else { "list" };
+            std::process::Command::new(assert_cmd::cargo::cargo_bin!("sprout"))
+                .env("XDG_CACHE_HOME", cache.path())
+                .env("XDG_DATA_HOME", std::env::temp_dir().join("sprout-test-data"))
+                .args([command, "--vault", vault, "--format", "json"])
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut c in children {
+        assert!(c.wait().unwrap().success());
+    }
+
+    let stats = cache_stats(cache.path(), dir.path());
+    assert_eq!(stats["entries"], 200);
+    assert_eq!(stats["stale"], 0);
+    let cache_dir = cache.path().join("sprout/vaults");
+    let leftovers: Vec<_> = fs::read_dir(&cache_dir)
+        .unwrap()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_name().to_string_lossy().ends_with(".tmp"))
+        .collect();
+    assert!(leftovers.is_empty());
+}
+
+#[test]
+fn concurrent_reviews_never_leave_a_partial_note() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "shared.md", 3, 2.5, "2026-01-02");
+    let file = dir.path().join("shared.md");
+
+    let children: Vec<_> = (0..8)
+        .map(|_| {
+            std::process::Command::new(assert_cmd::cargo::cargo_bin!("sprout"))
+                .env("XDG_CACHE_HOME", std::env::temp_dir().join("sprout-test-cache"))
+                .env("XDG_DATA_HOME", std::env::temp_dir().join("sprout-test-data"))
+                .args(["done", file.to_str().unwrap(), "good", "--vault", dir.path().to_str().unwrap()])
+                .stdout(std::process::Stdio::null())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    for mut c in children {
+        assert!(c.wait().unwrap().success());
+    }
+
+    let content = fs::read_to_string(&file).unwrap();
+    assert!(content.starts_with("---\nmaturity: seedling\n"));
+    assert!(content.ends_with("\n---\nBody"));
+    assert_eq!(content.matches("next_review:").count(), 1);
+    let names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(names, vec!["shared.md"]);
+}
+
+#[test]
+fn watch_reports_events_as_json_lines() {
+    use std::io::{BufRead, BufReader};
+
+    let cache = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    fs::write(dir.path().join("a.md"), "See [[b]]\n").unwrap();
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("sprout"))
+        .env("XDG_CACHE_HOME", cache.path())
+        .args(["watch", "--events", "--vault", dir.path().to_str().unwrap(), "--format", "json"])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    let (tx, rx) = std::sync::mpsc::channel();
+    let stdout = child.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = tx.send(serde_json::from_str::<serde_json::Value>(&line).unwrap());
+        }
+    });
+    let next = || rx.recv_timeout(std::time::Duration::from_secs(10)).expect("watch event");
+
+    let ready = next();
+    assert_eq!(ready["event"], "ready");
+    assert_eq!(ready["broken_links"], 1);
+
+    write_note(dir.path(), "b.md", 1, 2.5, "2026-01-01");
+    let mut events = Vec::new();
+    while events.len() < 3 {
+        let e = next();
+        events.push(format!("{} {}", e["event"].as_str().unwrap(), e["relative_path"].as_str().unwrap()));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(events, vec!["added b.md", "due b.md", "link_fixed a.md"]);
+    let stats = cache_stats(cache.path(), dir.path());
+    assert_eq!(stats["entries"], 2);
+}
+
+#[test]
+fn serve_answers_json_rpc_over_stdio() {
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md", 1, 2.5, "2026-01-01");
+    write_note(dir.path(), "b.md", 1, 2.5, "2099-01-01");
+
+    let requests = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"review"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"done","params":{"file":"a.md","rating":"good"}}"#,
+        r#"{"jsonrpc":"2.0","method":"review"}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"review"}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"show","params":{"file":"missing.md"}}"#,
+        r#"{"jsonrpc":"2.0","id":5,"method":"done","params":{"file":"a.md"}}"#,
+        r#"{"jsonrpc":"2.0","id":6,"method":"rename"}"#,
+        "not json",
+        r#"{"jsonrpc":"2.0","id":7,"method":"show","params":{"file":"b.md"}}"#,
+    ];
+    let output = sprout()
+        .args(["serve", "--vault", dir.path().to_str().unwrap()])
+        .write_stdin(requests.join("\n") + "\n")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let responses: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    // The notification gets no response
+    assert_eq!(responses.len(), 8);
+    assert!(responses.iter().all(|r| r["jsonrpc"] == "2.0"));
+
+    assert_eq!(responses[0]["id"], 1);
+    let due = responses[0]["result"].as_array().unwrap();
+    assert_eq!(due.len(), 1);
+    assert_eq!(due[0]["relative_path"], "a.md");
+
+    assert_eq!(responses[1]["id"], 2);
+    assert!(responses[1]["result"]["next_review"].is_string());
+    // The index saw the write before the next request
+    assert_eq!(responses[2]["id"], 3);
+    assert_eq!(responses[2]["result"], serde_json::json!([]));
+
+    assert_eq!(responses[3]["error"]["code"], -32000);
+    assert_eq!(responses[3]["error"]["data"]["error"], "file_not_found");
+    assert_eq!(responses[4]["error"]["code"], -32602);
+    assert_eq!(responses[5]["error"]["code"], -32601);
+    assert_eq!(responses[6]["error"]["code"], -32700);
+    assert!(responses[6]["id"].is_null());
+    assert_eq!(responses[7]["result"]["relative_path"], "b.md");
+    assert_eq!(responses[7]["result"]["is_due"], false);
+}
+
+#[test]
+fn serve_listens_on_a_unix_socket_and_tracks_edits() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let data = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    write_note(dir.path(), "a.md", 1, 2.5, "2026-01-01");
+    let socket = data.path().join("sprout.sock");
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("sprout"))
+        .env("XDG_DATA_HOME", data.path())
+        .env("XDG_CACHE_HOME", data.path())
+        .args(["serve", "--vault", dir.path().to_str().unwrap(), "--socket", socket.to_str().unwrap()])
+        .spawn()
+        .unwrap();
+
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
+    let stream = loop {
+        match UnixStream::connect(&socket) {
+            Ok(stream) => break stream,
+            Err(_) if std::time::Instant::now() < deadline => {
+                std::thread::sleep(std::time::Duration::from_millis(50))
+            }
+            Err(e) => panic!("connect: {e}"),
+        }
+    };
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut call = |id: u64, method: &str| {
+        writeln!(writer, r#"{{"jsonrpc":"2.0","id":{id},"method":"{method}"}}"#).unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], id);
+        response["result"].clone()
+    };
+
+    assert_eq!(call(1, "review").as_array().unwrap().len(), 1);
+
+    // An edit made outside the server shows up once the watcher delivers it
+    write_note(dir.path(), "b.md", 1, 2.5, "2026-01-01");
+    let mut id = 2;
+    let due = loop {
+        let due = call(id, "review").as_array().unwrap().len();
+        if due == 2 || std::time::Instant::now() > deadline {
+            break due;
+        }
+        id += 1;
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    let stats = call(id + 1, "stats");
+
+    // A second server on the same socket is refused
+    let second = sprout()
+        .args(["serve", "--vault", dir.path().to_str().unwrap(), "--socket", socket.to_str().unwrap(), "--format", "json"])
+        .output()
+        .unwrap();
+    child.kill().unwrap();
+    child.wait().unwrap();
+
+    assert_eq!(due, 2);
+    assert_eq!(stats["total"], 2);
+    assert_eq!(stats["overdue"], 2);
+    assert!(!second.status.success());
+    assert!(String::from_utf8_lossy(&second.stderr).contains("\"error\":\"serve_error\""));
+}
+
+#[test]
+fn lsp_serves_links_diagnostics_and_review_actions() {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let data = TempDir::new().unwrap();
+    let dir = TempDir::new().unwrap();
+    let vault = fs::canonicalize(dir.path()).unwrap();
+    write_note(&vault, "b.md", 3, 2.5, "2026-01-01");
+    let text = "---\nmaturity: seedling\ncreated: 2026-01-01\nlast_review: 2026-01-01\nreview_interval: 1\nnext_review: 2026-01-02\nease: 2.5\n---\nSee [[b]] and [[missing]]\n[[\n";
+    fs::write(vault.join("a.md"), text).unwrap();
+    let uri = format!("file://{}", vault.join("a.md").display());
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin!("sprout"))
+        .env("XDG_DATA_HOME", data.path())
+        .env("XDG_CACHE_HOME", data.path())
+        .args(["lsp", "--vault", vault.to_str().unwrap()])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let position = |line: u32, character: u32| {
+        serde_json::json!({ "textDocument": { "uri": uri }, "position": { "line": line, "character": character } })
+    };
+    let messages = [
+        serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "capabilities": { "workspace": { "applyEdit": true } } } }),
+        serde_json::json!({ "jsonrpc": "2.0", "method": "initialized", "params": {} }),
+        serde_json::json!({ "jsonrpc": "2.0", "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "markdown", "version": 1, "text": text } } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": position(8, 6) }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "textDocument/definition", "params": position(8, 6) }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 4, "method": "textDocument/completion", "params": position(9, 2) }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 5, "method": "textDocument/codeAction",
+            "params": { "textDocument": { "uri": uri }, "range": position(8, 0)["position"].clone(), "context": { "diagnostics": [] } } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 6, "method": "workspace/executeCommand",
+            "params": { "command": "sprout.done", "arguments": [uri, "good"] } }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 7, "method": "shutdown" }),
+        serde_json::json!({ "jsonrpc": "2.0", "method": "exit" }),
+    ];
+    let mut stdin = child.stdin.take().unwrap();
+    for message in &messages {
+        let body = message.to_string();
+        write!(stdin, "Content-Length: {}\r\n\r\n{body}", body.len()).unwrap();
+    }
+    stdin.flush().unwrap();
+
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut received = Vec::new();
+    loop {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            assert!(stdout.read_line(&mut line).unwrap() > 0, "server closed stdout");
+            match line.trim_end().strip_prefix("Content-Length: ") {
+                Some(n) => length = n.parse().unwrap(),
+                None if line.trim_end().is_empty() => break,
+                None => {}
+            }
+        }
+        let mut body = vec![0; length];
+        stdout.read_exact(&mut body).unwrap();
+        let message: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let done = message["id"] == 7 && message.get("method").is_none();
+        received.push(message);
+        if done {
+            break;
+        }
+    }
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+
+    let response = |id: u64| {
+        received
+            .iter()
+            .find(|m| m["id"] == id && m.get("method").is_none())
+            .unwrap_or_else(|| panic!("no response {id}"))["result"]
+            .clone()
+    };
+    let notification = |method: &str| received.iter().find(|m| m["method"] == method).unwrap()["params"].clone();
+
+    assert_eq!(response(1)["capabilities"]["hoverProvider"], true);
+
+    let diagnostics = notification("textDocument/publishDiagnostics")["diagnostics"].clone();
+    assert_eq!(diagnostics.as_array().unwrap().len(), 1);
+    assert_eq!(diagnostics[0]["code"], "broken_link");
+    assert_eq!(diagnostics[0]["range"]["start"], serde_json::json!({ "line": 8, "character": 14 }));
+
+    let hover = response(2)["contents"]["value"].as_str().unwrap().to_string();
+    assert!(hover.contains("`b.md`") && hover.contains("seedling · next review 2026-01-01 (overdue)"), "{hover}");
+    assert_eq!(response(3)["uri"], format!("file://{}", vault.join("b.md").display()));
+
+    let labels: Vec<_> = response(4)["items"].as_array().unwrap().iter().map(|i| i["textEdit"]["newText"].clone()).collect();
+    assert_eq!(labels, vec!["a", "b"]);
+
+    let actions: Vec<_> = response(5).as_array().unwrap().iter().map(|a| a["title"].as_str().unwrap().to_string()).collect();
+    assert!(actions.contains(&"sprout: reviewed (good)".to_string()));
+    assert!(actions.contains(&"sprout: promote to budding".to_string()));
+
+    assert!(response(6)["next_review"].is_string());
+    assert!(notification("window/showMessage")["message"].as_str().unwrap().starts_with("Reviewed a.md"));
+    let edit = notification("workspace/applyEdit")["edit"]["changes"][&uri][0]["newText"].clone();
+    assert_eq!(edit.as_str().unwrap(), fs::read_to_string(vault.join("a.md")).unwrap());
+    assert!(edit.as_str().unwrap().contains("review_interval: "));
+}